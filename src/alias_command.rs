@@ -0,0 +1,66 @@
+use crate::aliases::Aliases;
+use crate::utils;
+
+pub struct AliasCommand;
+
+impl AliasCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-alias
+    pub fn execute(args: &[String], aliases: &mut Aliases) -> i32 {
+        let mut print = false;
+        let mut operands = &args[1..];
+        while let Some(arg) = operands.first() {
+            match arg.as_str() {
+                "-p" => print = true,
+                "--" => {
+                    operands = &operands[1..];
+                    break;
+                }
+                arg if arg.starts_with('-') && arg.len() > 1 => {
+                    eprintln!("alias: {}: invalid option", arg);
+                    eprintln!("alias: usage: alias [-p] [name[=value] ... ]");
+                    return 2;
+                }
+                _ => break,
+            }
+            operands = &operands[1..];
+        }
+        if operands.is_empty() || print {
+            for (name, value) in aliases.all() {
+                println!("{}", AliasCommand::definition(name, value));
+            }
+        }
+
+        let mut status = 0;
+        for operand in operands {
+            match operand.split_once('=') {
+                Some((name, value)) => {
+                    if !is_alias_name(name) {
+                        eprintln!("alias: `{}': invalid alias name", name);
+                        status = 1;
+                        continue;
+                    }
+                    aliases.set(name.to_string(), value.to_string());
+                }
+                None => match aliases.get(operand) {
+                    Some(value) => println!("{}", AliasCommand::definition(operand, value)),
+                    None => {
+                        eprintln!("alias: {}: not found", operand);
+                        status = 1;
+                    }
+                },
+            }
+        }
+        status
+    }
+
+    /// `alias name='value'`, which defines the alias again
+    fn definition(name: &str, value: &str) -> String {
+        format!("alias {}={}", name, utils::single_quote(value))
+    }
+}
+
+/// Alias names may not contain quotes, `/`, `$`, `` ` ``, `=` or
+/// metacharacters
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| " \t\n|&;()<>'\"\\/$`=".contains(c))
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use crate::parser::Parser;
+use crate::scanner::{Scanner, ScannerError};
+use crate::token::{Token, TokenType};
+
+/// Reserved words after which a command starts
+const COMMAND_PREFIXES: [&str; 9] = [
+    "if", "then", "elif", "else", "while", "until", "do", "{", "!",
+];
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Aliases
+pub struct Aliases {
+    aliases: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    pub fn new() -> Self {
+        Aliases {
+            aliases: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.aliases.get(name)
+    }
+
+    pub fn set(&mut self, name: String, value: String) {
+        self.aliases.insert(name, value);
+    }
+
+    /// Returns false if there was no such alias
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.aliases.clear();
+    }
+
+    /// All aliases, sorted by name
+    pub fn all(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.aliases.iter()
+    }
+
+    /// Replace the first word of each simple command by its alias, scanned
+    /// into tokens. If the value ends with a blank, the word after it is
+    /// checked for an alias too. An alias is not expanded again inside its
+    /// own value, so `alias ls='ls -F'` works.
+    pub fn expand(&self, tokens: Vec<Token>) -> Result<Vec<Token>, ScannerError> {
+        let mut expanded = Vec::new();
+        self.expand_into(tokens, &mut Vec::new(), &mut expanded, true)?;
+        Ok(expanded)
+    }
+
+    /// Returns whether the token after `tokens` is at the start of a command
+    fn expand_into(
+        &self,
+        tokens: Vec<Token>,
+        expanding: &mut Vec<String>,
+        expanded: &mut Vec<Token>,
+        mut command_start: bool,
+    ) -> Result<bool, ScannerError> {
+        // the word after a redirection operator is its target
+        let mut redirection_target = false;
+        for token in tokens {
+            match token.type_ {
+                TokenType::String if redirection_target => redirection_target = false,
+                TokenType::String => {
+                    let alias = self
+                        .aliases
+                        .get(&token.raw)
+                        // quoting any part of the word prevents expansion
+                        .filter(|_| command_start && token.raw == token.lexeme)
+                        .filter(|_| !expanding.contains(&token.raw));
+                    if let Some(value) = alias {
+                        let mut value_tokens = Scanner::new(value.clone()).scan_tokens()?;
+                        value_tokens.pop(); // Eof
+                        expanding.push(token.raw);
+                        command_start =
+                            self.expand_into(value_tokens, expanding, expanded, true)?
+                                || value.ends_with([' ', '\t']);
+                        expanding.pop();
+                        continue;
+                    }
+                    command_start = command_start
+                        && (COMMAND_PREFIXES.contains(&token.raw.as_str())
+                            || Parser::assignment(&token.raw).is_some());
+                }
+                TokenType::Redirection => redirection_target = true,
+                TokenType::IoNumber => {}
+                TokenType::RightParen => command_start = false,
+                _ => command_start = true,
+            }
+            expanded.push(token);
+        }
+        Ok(command_start)
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::Aliases;
+    use crate::scanner::Scanner;
+
+    fn expand(aliases: &Aliases, source: &str) -> Vec<String> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        let tokens = aliases.expand(tokens).unwrap();
+        tokens.into_iter().map(|token| token.raw).collect()
+    }
+
+    #[test]
+    fn test_command_words() {
+        let mut aliases = Aliases::new();
+        aliases.set("ll".to_string(), "ls -l".to_string());
+        assert_eq!(expand(&aliases, "ll ll"), ["ls", "-l", "ll", ""]);
+        assert_eq!(
+            expand(&aliases, "x=1 ll; if ll; then >out ll; fi"),
+            [
+                "x=1", "ls", "-l", ";", "if", "ls", "-l", ";", "then", ">", "out", "ls", "-l", ";",
+                "fi", ""
+            ]
+        );
+        assert_eq!(expand(&aliases, "'ll' \\ll"), ["'ll'", "\\ll", ""]);
+    }
+
+    #[test]
+    fn test_recursive_aliases() {
+        let mut aliases = Aliases::new();
+        aliases.set("ls".to_string(), "ls -F".to_string());
+        aliases.set("sudo".to_string(), "sudo ".to_string());
+        aliases.set("a".to_string(), "b".to_string());
+        aliases.set("b".to_string(), "a".to_string());
+        assert_eq!(expand(&aliases, "ls"), ["ls", "-F", ""]);
+        assert_eq!(expand(&aliases, "sudo ls"), ["sudo", "ls", "-F", ""]);
+        assert_eq!(expand(&aliases, "a"), ["a", ""]);
+    }
+}
@@ -0,0 +1,405 @@
+/// Variables whose values refer to each other are evaluated at most this deep
+const MAX_DEPTH: usize = 256;
+
+/// Operators, longer ones first so that `<=` is not read as `<`
+const OPERATORS: [&str; 25] = [
+    "**", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "&",
+    "^", "|", "!", "~", "?", ":", "(", ")",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+    /// a character that cannot start a token
+    Invalid,
+    End,
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Shell-Arithmetic
+///
+/// Evaluates `expression` with 64-bit integers, which is an error when they
+/// overflow. `variable` looks up the value of a name, which is evaluated as an
+/// expression in turn. Unset and empty variables are 0. There is no
+/// assignment.
+pub fn evaluate(
+    expression: &str,
+    variable: &dyn Fn(&str) -> Option<String>,
+) -> Result<i64, String> {
+    Evaluator::new(expression, variable, 0)?.run()
+}
+
+struct Evaluator<'a> {
+    expression: &'a str,
+    chars: Vec<char>,
+    current: usize,
+    token: Token,
+    /// where the current token starts, shown in error messages
+    token_start: usize,
+    variable: &'a dyn Fn(&str) -> Option<String>,
+    depth: usize,
+    /// above 0 in operands of `&&`, `||` and `?:` that are not evaluated
+    skipping: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(
+        expression: &'a str,
+        variable: &'a dyn Fn(&str) -> Option<String>,
+        depth: usize,
+    ) -> Result<Self, String> {
+        let mut evaluator = Evaluator {
+            expression,
+            chars: expression.chars().collect(),
+            current: 0,
+            token: Token::End,
+            token_start: 0,
+            variable,
+            depth,
+            skipping: 0,
+        };
+        evaluator.next()?;
+        Ok(evaluator)
+    }
+
+    fn run(&mut self) -> Result<i64, String> {
+        if self.token == Token::End {
+            return Ok(0);
+        }
+        let value = self.conditional()?;
+        if self.token != Token::End {
+            return Err(self.error("syntax error in expression"));
+        }
+        Ok(value)
+    }
+
+    /// `condition ? value : value`
+    fn conditional(&mut self) -> Result<i64, String> {
+        let condition = self.binary(1)?;
+        if self.token != Token::Operator("?") {
+            return Ok(condition);
+        }
+        self.next()?;
+        let then = self.skip_if(condition == 0, |evaluator| evaluator.conditional())?;
+        if self.token != Token::Operator(":") {
+            return Err(self.error("`:' expected for conditional expression"));
+        }
+        self.next()?;
+        let otherwise = self.skip_if(condition != 0, |evaluator| evaluator.conditional())?;
+        Ok(if condition != 0 { then } else { otherwise })
+    }
+
+    /// Binary operators of at least `min_precedence`
+    fn binary(&mut self, min_precedence: u8) -> Result<i64, String> {
+        let mut left = self.unary()?;
+        while let Token::Operator(operator) = self.token {
+            let Some(precedence) = precedence(operator).filter(|&p| p >= min_precedence) else {
+                break;
+            };
+            self.next()?;
+            let right_start = self.token_start;
+            // `**` groups from the right
+            let next_precedence = match operator {
+                "**" => precedence,
+                _ => precedence + 1,
+            };
+            let skip = match operator {
+                "&&" => left == 0,
+                "||" => left != 0,
+                _ => false,
+            };
+            let right = self.skip_if(skip, |evaluator| evaluator.binary(next_precedence))?;
+            left = self.apply(operator, left, right, right_start)?;
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        let operator = match self.token {
+            Token::Operator(operator @ ("-" | "+" | "!" | "~")) => operator,
+            _ => return self.primary(),
+        };
+        self.next()?;
+        let value = self.unary()?;
+        Ok(match operator {
+            "-" => value.checked_neg().ok_or_else(|| self.overflow())?,
+            "!" => (value == 0) as i64,
+            "~" => !value,
+            _ => value,
+        })
+    }
+
+    fn primary(&mut self) -> Result<i64, String> {
+        let value = match self.token.clone() {
+            Token::Number(value) => value,
+            Token::Name(name) => self.variable_value(&name)?,
+            Token::Operator("(") => {
+                self.next()?;
+                let value = self.conditional()?;
+                if self.token != Token::Operator(")") {
+                    return Err(self.error("missing `)'"));
+                }
+                value
+            }
+            _ => return Err(self.error("syntax error: operand expected")),
+        };
+        self.next()?;
+        Ok(value)
+    }
+
+    fn variable_value(&self, name: &str) -> Result<i64, String> {
+        if self.skipping > 0 {
+            return Ok(0);
+        }
+        let value = (self.variable)(name).unwrap_or_default();
+        if self.depth + 1 >= MAX_DEPTH {
+            return Err(format!(
+                "{}: expression recursion level exceeded (error token is \"{}\")",
+                name, name
+            ));
+        }
+        Evaluator::new(value.trim(), self.variable, self.depth + 1)?.run()
+    }
+
+    fn apply(
+        &self,
+        operator: &str,
+        left: i64,
+        right: i64,
+        right_start: usize,
+    ) -> Result<i64, String> {
+        if self.skipping > 0 {
+            return Ok(0);
+        }
+        let value = match operator {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "/" | "%" if right == 0 => return Err(self.error_at(right_start, "division by 0")),
+            "/" => left.checked_div(right),
+            "%" => left.checked_rem(right),
+            "**" if right < 0 => {
+                return Err(self.error_at(right_start, "exponent less than 0"));
+            }
+            "**" => u32::try_from(right)
+                .ok()
+                .and_then(|right| left.checked_pow(right)),
+            "<<" => Some(left.wrapping_shl(right as u32)),
+            ">>" => Some(left.wrapping_shr(right as u32)),
+            "<" => Some((left < right) as i64),
+            "<=" => Some((left <= right) as i64),
+            ">" => Some((left > right) as i64),
+            ">=" => Some((left >= right) as i64),
+            "==" => Some((left == right) as i64),
+            "!=" => Some((left != right) as i64),
+            "&" => Some(left & right),
+            "^" => Some(left ^ right),
+            "|" => Some(left | right),
+            "&&" => Some((left != 0 && right != 0) as i64),
+            _ => Some((left != 0 || right != 0) as i64),
+        };
+        value.ok_or_else(|| self.overflow())
+    }
+
+    /// Runs `parse` without evaluating anything if `skip` is set
+    fn skip_if(
+        &mut self,
+        skip: bool,
+        parse: impl FnOnce(&mut Self) -> Result<i64, String>,
+    ) -> Result<i64, String> {
+        self.skipping += skip as usize;
+        let value = parse(self);
+        self.skipping -= skip as usize;
+        value
+    }
+
+    /// Reads the next token. The start of the last token is kept at the end.
+    fn next(&mut self) -> Result<(), String> {
+        while self
+            .chars
+            .get(self.current)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.current += 1;
+        }
+        let Some(&c) = self.chars.get(self.current) else {
+            self.token = Token::End;
+            return Ok(());
+        };
+        self.token_start = self.current;
+        let word_length = self.chars[self.current..]
+            .iter()
+            .take_while(|&&c| c.is_ascii_alphanumeric() || c == '_' || c == '#' || c == '@')
+            .count();
+        let word: String = self.chars[self.current..self.current + word_length]
+            .iter()
+            .collect();
+        self.token = if c.is_ascii_digit() {
+            self.current += word_length;
+            Token::Number(self.number(&word)?)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let length = word
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(word.len());
+            self.current += length;
+            Token::Name(word[..length].to_string())
+        } else {
+            let rest: String = self.chars[self.current..].iter().take(2).collect();
+            match OPERATORS
+                .iter()
+                .find(|operator| rest.starts_with(*operator))
+            {
+                Some(operator) => {
+                    self.current += operator.len();
+                    Token::Operator(operator)
+                }
+                None => Token::Invalid,
+            }
+        };
+        Ok(())
+    }
+
+    /// Decimal, octal with a leading `0`, hexadecimal with a leading `0x`, or
+    /// `base#digits` with a base from 2 to 64
+    fn number(&self, word: &str) -> Result<i64, String> {
+        let (base, digits) = if let Some((base, digits)) = word.split_once('#') {
+            match base.parse() {
+                Ok(base @ 2..=64) => (base, digits),
+                _ => return Err(self.error("invalid arithmetic base")),
+            }
+        } else if let Some(digits) = word.strip_prefix("0x").or(word.strip_prefix("0X")) {
+            (16, digits)
+        } else if word.len() > 1 && word.starts_with('0') {
+            (8, &word[1..])
+        } else {
+            (10, word)
+        };
+        let mut value: i64 = 0;
+        for c in digits.chars() {
+            let digit = match c {
+                '0'..='9' => c as i64 - '0' as i64,
+                'a'..='z' => c as i64 - 'a' as i64 + 10,
+                // letters have the same value in either case up to base 36
+                'A'..='Z' if base <= 36 => c as i64 - 'A' as i64 + 10,
+                'A'..='Z' => c as i64 - 'A' as i64 + 36,
+                '@' => 62,
+                _ => 63,
+            };
+            if digit >= base {
+                return Err(self.error("value too great for base"));
+            }
+            value = value
+                .checked_mul(base)
+                .and_then(|value| value.checked_add(digit))
+                .ok_or_else(|| self.overflow())?;
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: &str) -> String {
+        self.error_at(self.token_start, message)
+    }
+
+    fn error_at(&self, start: usize, message: &str) -> String {
+        let token: String = self.chars[start..].iter().collect();
+        format!(
+            "{}: {} (error token is \"{}\")",
+            self.expression, message, token
+        )
+    }
+
+    fn overflow(&self) -> String {
+        format!("{}: integer overflow", self.expression)
+    }
+}
+
+/// The precedence of binary operators, from `||` to `**`
+fn precedence(operator: &str) -> Option<u8> {
+    let precedence = match operator {
+        "||" => 1,
+        "&&" => 2,
+        "|" => 3,
+        "^" => 4,
+        "&" => 5,
+        "==" | "!=" => 6,
+        "<" | "<=" | ">" | ">=" => 7,
+        "<<" | ">>" => 8,
+        "+" | "-" => 9,
+        "*" | "/" | "%" => 10,
+        "**" => 11,
+        _ => return None,
+    };
+    Some(precedence)
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+
+    fn variable(name: &str) -> Option<String> {
+        match name {
+            "x" => Some("4".to_string()),
+            "y" => Some("x * 2".to_string()),
+            "loop" => Some("loop".to_string()),
+            _ => None,
+        }
+    }
+
+    fn eval(expression: &str) -> Result<i64, String> {
+        evaluate(expression, &variable)
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(eval("3+4"), Ok(7));
+        assert_eq!(eval(" 1 + 2 * 3 - (4 - 1) "), Ok(4));
+        assert_eq!(eval("-2**2 + 2**3**2"), Ok(516));
+        assert_eq!(eval("7 / 2 + 7 % 2 + -7 / 2"), Ok(1));
+        assert_eq!(eval("!0 + ~0 + (3 < 4 == 1) + (1 << 4 >> 2)"), Ok(5));
+        assert_eq!(eval("6 & 3 | 8 ^ 1"), Ok(11));
+        assert_eq!(eval("0 && 1/0 || 2 > 1 ? 10 : 1/0"), Ok(10));
+        assert_eq!(eval(""), Ok(0));
+    }
+
+    #[test]
+    fn test_numbers_and_variables() {
+        assert_eq!(
+            eval("010 + 0x1f + 2#101 + 64#@_ + 16#fF"),
+            Ok(8 + 31 + 5 + 4031 + 255)
+        );
+        assert_eq!(eval("x + y + unset"), Ok(12));
+        assert!(eval("loop")
+            .unwrap_err()
+            .contains("recursion level exceeded"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            eval("3+"),
+            Err("3+: syntax error: operand expected (error token is \"+\")".to_string())
+        );
+        assert_eq!(
+            eval("1 2"),
+            Err("1 2: syntax error in expression (error token is \"2\")".to_string())
+        );
+        assert_eq!(
+            eval("1/0"),
+            Err("1/0: division by 0 (error token is \"0\")".to_string())
+        );
+        assert!(eval("08").unwrap_err().contains("value too great for base"));
+        assert!(eval("65#1")
+            .unwrap_err()
+            .contains("invalid arithmetic base"));
+        assert!(eval("(1").unwrap_err().contains("missing `)'"));
+        assert_eq!(
+            eval("9223372036854775807 + 1"),
+            Err("9223372036854775807 + 1: integer overflow".to_string())
+        );
+        assert!(eval("-9223372036854775807 - 1 / -1").is_ok());
+        assert!(eval("(-9223372036854775807 - 1) / -1").is_err());
+    }
+}
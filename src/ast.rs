@@ -0,0 +1,224 @@
+/// `name=value`, `name[subscript]=value` or `name=(words...)`, the value is
+/// expanded when the command runs
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub name: String,
+    /// as written in the source
+    pub subscript: Option<String>,
+    /// `+=` appends to the current value
+    pub append: bool,
+    pub value: AssignmentValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssignmentValue {
+    Scalar(String),
+    /// https://www.gnu.org/software/bash/manual/bash.html#Arrays
+    ///
+    /// `(words...)`, where a word may be `[subscript]=value`
+    Array(Vec<String>),
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Simple-Commands
+#[derive(Debug, Clone)]
+pub struct SimpleCommand {
+    pub assignments: Vec<Assignment>,
+    /// words as written in the source, expanded when the command runs
+    pub words: Vec<String>,
+    pub redirections: Vec<Redirection>,
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Redirections
+#[derive(Debug, Clone)]
+pub struct Redirection {
+    /// file descriptor written before the operator, like `2` in `2>file`
+    pub fd: Option<i32>,
+    pub operator: RedirectionOperator,
+    /// word as written in the source, or the body of a here-document
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedirectionOperator {
+    /// `<`
+    Input,
+    /// `>`
+    Output,
+    /// `>|`
+    Clobber,
+    /// `>>`
+    Append,
+    /// `<>`
+    ReadWrite,
+    /// `<&`
+    DuplicateInput,
+    /// `>&`
+    DuplicateOutput,
+    /// `&>`
+    OutputAndError,
+    /// `&>>`
+    AppendOutputAndError,
+    /// `<<` and `<<-`, the body is not expanded when the delimiter is quoted
+    HereDocument { quoted: bool },
+    /// `<<<`
+    HereString,
+}
+
+impl RedirectionOperator {
+    pub fn from_lexeme(lexeme: &str) -> Option<RedirectionOperator> {
+        match lexeme {
+            "<" => Some(RedirectionOperator::Input),
+            ">" => Some(RedirectionOperator::Output),
+            ">|" => Some(RedirectionOperator::Clobber),
+            ">>" => Some(RedirectionOperator::Append),
+            "<>" => Some(RedirectionOperator::ReadWrite),
+            "<&" => Some(RedirectionOperator::DuplicateInput),
+            ">&" => Some(RedirectionOperator::DuplicateOutput),
+            "&>" => Some(RedirectionOperator::OutputAndError),
+            "&>>" => Some(RedirectionOperator::AppendOutputAndError),
+            "<<" | "<<-" => Some(RedirectionOperator::HereDocument { quoted: false }),
+            "<<<" => Some(RedirectionOperator::HereString),
+            _ => None,
+        }
+    }
+
+    /// The file descriptor that is redirected when none is given
+    pub fn default_fd(&self) -> i32 {
+        match self {
+            RedirectionOperator::Input
+            | RedirectionOperator::ReadWrite
+            | RedirectionOperator::DuplicateInput
+            | RedirectionOperator::HereDocument { .. }
+            | RedirectionOperator::HereString => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Compound-Commands
+#[derive(Debug, Clone)]
+pub enum CompoundCommand {
+    /// `{ list; }`
+    BraceGroup(CommandList),
+    /// `( list )`
+    Subshell(CommandList),
+    If(IfCommand),
+    Loop(LoopCommand),
+    For(ForCommand),
+    /// `select name [in words...]; do list; done`
+    Select(ForCommand),
+    /// `[[ expression ]]`
+    Conditional(ConditionalExpression),
+}
+
+/// `if list; then list; [elif list; then list;]... [else list;] fi`
+#[derive(Debug, Clone)]
+pub struct IfCommand {
+    /// conditions and the lists that run when they succeed
+    pub branches: Vec<(CommandList, CommandList)>,
+    pub else_branch: Option<CommandList>,
+}
+
+/// `while list; do list; done` and `until list; do list; done`
+#[derive(Debug, Clone)]
+pub struct LoopCommand {
+    pub until: bool,
+    pub condition: CommandList,
+    pub body: CommandList,
+}
+
+/// `for name [in words...]; do list; done`
+#[derive(Debug, Clone)]
+pub struct ForCommand {
+    pub name: String,
+    /// `None` loops over the positional parameters
+    pub words: Option<Vec<String>>,
+    pub body: CommandList,
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Bash-Conditional-Expressions
+///
+/// Words are kept as written in the source, and are expanded without word
+/// splitting when the expression is evaluated.
+#[derive(Debug, Clone)]
+pub enum ConditionalExpression {
+    /// a word on its own is true if it is not empty
+    Word(String),
+    /// `-f word`
+    Unary(String, String),
+    /// `word == pattern`, `word -lt word`, ...
+    Binary(String, String, String),
+    Not(Box<ConditionalExpression>),
+    And(Box<ConditionalExpression>, Box<ConditionalExpression>),
+    Or(Box<ConditionalExpression>, Box<ConditionalExpression>),
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Shell-Functions
+#[derive(Debug, Clone)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub body: CompoundCommand,
+    /// applied each time the function is called
+    pub redirections: Vec<Redirection>,
+    /// source text of the definition
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Simple(SimpleCommand),
+    Compound(CompoundCommand, Vec<Redirection>),
+    FunctionDefinition(FunctionDefinition),
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Pipelines
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+    /// `!` inverts the exit status
+    pub negated: bool,
+    /// source text of the pipeline, used when reporting the status of jobs
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+}
+
+/// Pipelines joined by `&&` and `||`
+#[derive(Debug, Clone)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+}
+
+impl AndOr {
+    pub fn text(&self) -> String {
+        let mut text = self.first.text.clone();
+        for (connector, pipeline) in &self.rest {
+            let op = match connector {
+                Connector::And => "&&",
+                Connector::Or => "||",
+            };
+            text.push_str(&format!(" {} {}", op, pipeline.text));
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListItem {
+    pub and_or: AndOr,
+    /// terminated by `&`
+    pub background: bool,
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Lists
+#[derive(Debug, Clone)]
+pub struct CommandList {
+    pub items: Vec<ListItem>,
+}
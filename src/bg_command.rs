@@ -0,0 +1,35 @@
+use crate::jobs::Jobs;
+
+pub struct BgCommand;
+
+impl BgCommand {
+    pub fn execute(args: &[String], jobs: &mut Jobs) -> i32 {
+        if !jobs.is_enabled() {
+            eprintln!("bg: no job control");
+            return 1;
+        }
+
+        let specs: Vec<Option<&str>> = if args.len() == 1 {
+            vec![None]
+        } else {
+            args.iter().skip(1).map(|arg| Some(arg.as_str())).collect()
+        };
+
+        let mut return_code: i32 = 0;
+        for spec in specs {
+            match jobs.find(spec) {
+                Some(id) if jobs.get(id).is_some_and(|job| job.is_stopped()) => {
+                    jobs.resume_background(id)
+                }
+                Some(id) => {
+                    eprintln!("bg: job {} already in background", id);
+                }
+                None => {
+                    eprintln!("bg: {}: no such job", spec.unwrap_or("current"));
+                    return_code = 1;
+                }
+            }
+        }
+        return_code
+    }
+}
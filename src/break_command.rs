@@ -0,0 +1,37 @@
+use crate::shell::{Jump, Shell};
+
+pub struct BreakCommand;
+
+impl BreakCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-break
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let n = match loop_count(args) {
+            Ok(n) => n,
+            Err(status) => return status,
+        };
+        if shell.loop_depth() == 0 {
+            eprintln!("break: only meaningful in a `for', `while', or `until' loop");
+            return 0;
+        }
+        shell.jump(Jump::Break(n.min(shell.loop_depth())));
+        0
+    }
+}
+
+/// The number of enclosing loops that `break` or `continue` applies to
+pub fn loop_count(args: &[String]) -> Result<usize, i32> {
+    let Some(arg) = args.get(1) else {
+        return Ok(1);
+    };
+    match arg.parse::<i64>() {
+        Ok(n) if n >= 1 => Ok(n as usize),
+        Ok(_) => {
+            eprintln!("{}: {}: loop count out of range", args[0], arg);
+            Err(1)
+        }
+        Err(_) => {
+            eprintln!("{}: {}: numeric argument required", args[0], arg);
+            Err(1)
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::shell::Shell;
+
+pub struct BuiltinCommand;
+
+impl BuiltinCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-builtin
+    ///
+    /// Runs a builtin even if a function has the same name.
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let Some(name) = args.get(1) else {
+            return 0;
+        };
+        match shell.run_builtin(&args[1..]) {
+            Some(status) => status,
+            None => {
+                eprintln!("builtin: {}: not a shell builtin", name);
+                1
+            }
+        }
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+
+    #[test]
+    fn test_functions_are_skipped() {
+        let mut shell = Shell::new();
+        shell.eval("read() { called=1; }");
+        assert_eq!(shell.eval("builtin read -r line <<< hello"), 0);
+        assert_eq!(shell.variables().get("called"), None);
+        assert_eq!(shell.variables().get("line").as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn test_not_a_builtin() {
+        let mut shell = Shell::new();
+        shell.eval("f() { :; }");
+        assert_eq!(shell.eval("builtin f"), 1);
+        assert_eq!(shell.eval("builtin ls"), 1);
+        assert_eq!(shell.eval("builtin"), 0);
+    }
+}
@@ -1,14 +1,17 @@
 use std::{
-    env,
-    path::{absolute, Path, PathBuf},
+    env, fs,
+    path::{absolute, Component, Path, PathBuf},
 };
 
+use crate::options::Options;
 use crate::utils;
 
 pub struct CdCommand;
 
 impl CdCommand {
-    pub fn execute(args: &[String]) -> i32 {
+    /// With `shopt -s cdspell`, a directory name with a small spelling
+    /// mistake is corrected, and the corrected path printed.
+    pub fn execute(args: &[String], options: &Options) -> i32 {
         // initially target is args[1] or home dir
         let initial: String = {
             if args.len() == 1 {
@@ -31,6 +34,12 @@ impl CdCommand {
             )
             .to_path_buf();
         }
+        if options.cdspell && !target.is_dir() {
+            if let Some(corrected) = correct_spelling(&target) {
+                println!("{}", corrected.display());
+                target = corrected;
+            }
+        }
         // convert to absolute path
         let target: PathBuf = {
             let path = target.as_path();
@@ -51,3 +60,65 @@ impl CdCommand {
         1
     }
 }
+
+/// `path` with each directory that does not exist replaced by one in the same
+/// directory whose name differs by a single typo
+fn correct_spelling(path: &Path) -> Option<PathBuf> {
+    let mut corrected = PathBuf::new();
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            corrected.push(component);
+            continue;
+        };
+        if corrected.join(name).is_dir() {
+            corrected.push(name);
+            continue;
+        }
+        let name = name.to_str()?;
+        let parent = match corrected.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => corrected.as_path(),
+        };
+        let candidate = fs::read_dir(parent)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .find(|candidate| is_one_typo(name, candidate))?;
+        corrected.push(candidate);
+    }
+    Some(corrected)
+}
+
+/// Whether `a` and `b` differ by one swapped, wrong, extra or missing character
+fn is_one_typo(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    match (a, b) {
+        ([], []) => false,
+        ([x, y, a @ ..], [y2, x2, b @ ..]) if x == x2 && y == y2 && a == b => true,
+        ([_, a @ ..], [_, b @ ..]) if a == b => true,
+        ([_, a @ ..], b) if a == b => true,
+        (a, [_, b @ ..]) => a == b,
+        _ => false,
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::is_one_typo;
+
+    #[test]
+    fn test_typos() {
+        assert!(is_one_typo("scr", "src"));
+        assert!(is_one_typo("srx", "src"));
+        assert!(is_one_typo("srcc", "src"));
+        assert!(is_one_typo("sr", "src"));
+        assert!(!is_one_typo("src", "src"));
+        assert!(!is_one_typo("abc", "xyz"));
+    }
+}
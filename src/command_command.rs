@@ -0,0 +1,136 @@
+use crate::parser::is_reserved_word;
+use crate::shell::Shell;
+use crate::type_command::TypeCommand;
+use crate::utils;
+
+pub struct CommandCommand;
+
+/// The `PATH` of `command -p`, where the standard utilities are
+const DEFAULT_PATH: &str = "/usr/bin:/bin:/usr/sbin:/sbin";
+
+impl CommandCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-command
+    ///
+    /// Runs a builtin or external command, skipping functions. `-v` prints
+    /// how a name would be run and `-V` describes it like `type`.
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let mut default_path = false;
+        let mut describe = false;
+        let mut verbose = false;
+        let mut index = 1;
+        while let Some(arg) = args.get(index) {
+            if arg == "--" {
+                index += 1;
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            for letter in arg[1..].chars() {
+                match letter {
+                    'p' => default_path = true,
+                    'v' => describe = true,
+                    'V' => verbose = true,
+                    _ => {
+                        eprintln!("command: -{}: invalid option", letter);
+                        eprintln!("command: usage: command [-pVv] command [arg ...]");
+                        return 2;
+                    }
+                }
+            }
+            index += 1;
+        }
+        let operands = &args[index..];
+        let path = default_path.then_some(DEFAULT_PATH);
+        if verbose {
+            let mut type_args = vec!["type".to_string()];
+            type_args.extend_from_slice(operands);
+            return TypeCommand::execute(
+                &type_args,
+                shell.aliases(),
+                shell.built_in_commands(),
+                shell.functions(),
+            );
+        }
+        if describe {
+            // fails only if none of the names is found
+            let mut status = 1;
+            for name in operands {
+                if let Some(description) = CommandCommand::describe(name, path, shell) {
+                    println!("{}", description);
+                    status = 0;
+                }
+            }
+            return status;
+        }
+
+        let Some(name) = operands.first() else {
+            return 0;
+        };
+        if let Some(status) = shell.run_builtin(operands) {
+            return status;
+        }
+        let mut args = operands.to_vec();
+        if let Some(found) = path.and_then(|path| TypeCommand::check_in_path(name, Some(path))) {
+            args[0] = found;
+        }
+        shell.run_external(&args)
+    }
+
+    /// How `name` would be run, `None` if it cannot be
+    fn describe(name: &str, path: Option<&str>, shell: &mut Shell) -> Option<String> {
+        let alias = shell.aliases().get(name).cloned();
+        if let Some(value) = alias.filter(|_| shell.options().expand_aliases) {
+            return Some(format!("alias {}={}", name, utils::single_quote(&value)));
+        }
+        if is_reserved_word(name)
+            || shell.functions().contains_key(name)
+            || shell.built_in_commands().contains(name)
+        {
+            return Some(name.to_string());
+        }
+        TypeCommand::check_in_path(name, path)
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandCommand, DEFAULT_PATH};
+    use crate::shell::Shell;
+
+    #[test]
+    fn test_describe() {
+        let mut shell = Shell::new();
+        shell.options().expand_aliases = true;
+        shell.eval("alias ll='ls -l'; f() { :; }");
+        let mut describe = |name, path| CommandCommand::describe(name, path, &mut shell);
+        assert_eq!(describe("ll", None).as_deref(), Some("alias ll='ls -l'"));
+        assert_eq!(describe("f", None).as_deref(), Some("f"));
+        assert_eq!(describe("cd", None).as_deref(), Some("cd"));
+        assert_eq!(describe("while", None).as_deref(), Some("while"));
+        assert!(describe("sh", Some(DEFAULT_PATH)).is_some_and(|path| path.ends_with("/sh")));
+        assert_eq!(describe("rushell-missing", None), None);
+    }
+
+    #[test]
+    fn test_status() {
+        let mut shell = Shell::new();
+        assert_eq!(shell.eval("command -v cd rushell-missing"), 0);
+        assert_eq!(shell.eval("command -v rushell-missing"), 1);
+        assert_eq!(shell.eval("command -V cd"), 0);
+        assert_eq!(shell.eval("command -V rushell-missing"), 1);
+        assert_eq!(shell.eval("command -x"), 2);
+    }
+
+    #[test]
+    fn test_functions_are_skipped() {
+        let mut shell = Shell::new();
+        shell.eval("false() { called=1; }");
+        assert_eq!(shell.eval("command false"), 1);
+        assert_eq!(shell.variables().get("called"), None);
+        assert_eq!(shell.eval("false"), 0);
+        assert_eq!(shell.variables().get("called").as_deref(), Some("1"));
+    }
+}
@@ -0,0 +1,20 @@
+use crate::break_command::loop_count;
+use crate::shell::{Jump, Shell};
+
+pub struct ContinueCommand;
+
+impl ContinueCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-continue
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let n = match loop_count(args) {
+            Ok(n) => n,
+            Err(status) => return status,
+        };
+        if shell.loop_depth() == 0 {
+            eprintln!("continue: only meaningful in a `for', `while', or `until' loop");
+            return 0;
+        }
+        shell.jump(Jump::Continue(n.min(shell.loop_depth())));
+        0
+    }
+}
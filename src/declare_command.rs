@@ -0,0 +1,335 @@
+use crate::ast::{Assignment, AssignmentValue};
+use crate::expansion;
+use crate::parser::{is_name, Parser};
+use crate::shell::Shell;
+use crate::utils;
+use crate::variables::{Attributes, Value, Variable};
+
+pub struct DeclareCommand;
+
+/// Options of `declare`, where attributes are turned on with `-x` and off
+/// with `+x`
+#[derive(Default)]
+struct Options {
+    set: Attributes,
+    clear: Attributes,
+    indexed: bool,
+    associative: bool,
+    print: bool,
+    global: bool,
+}
+
+impl DeclareCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-declare
+    ///
+    /// Also runs as `typeset`, `local`, `readonly` and `export`, where `-n`
+    /// removes the export attribute. Assignments in the
+    /// arguments arrive with their values expanded and quoted, see
+    /// `Shell::expand_declaration`.
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let command = args[0].as_str();
+        let allowed = match command {
+            "readonly" => "aAp",
+            "export" => "np",
+            "local" => "aAilnprux",
+            _ => "aAgilnprux",
+        };
+        let mut options = Options::default();
+        options.set.readonly = command == "readonly";
+        options.set.exported = command == "export";
+        let mut operands = &args[1..];
+        while let Some(arg) = operands.first() {
+            if arg == "--" {
+                operands = &operands[1..];
+                break;
+            }
+            let on = match arg.chars().next() {
+                Some('-') => true,
+                Some('+') => false,
+                _ => break,
+            };
+            if arg.len() == 1 {
+                break;
+            }
+            for option in arg[1..].chars() {
+                if !allowed.contains(option) || (!on && "aApg".contains(option)) {
+                    eprintln!("{}: {}{}: invalid option", command, &arg[..1], option);
+                    return DeclareCommand::usage(command, allowed);
+                }
+                if command == "export" && option == 'n' {
+                    options.set.exported = false;
+                    options.clear.exported = true;
+                    continue;
+                }
+                options.apply(option, on);
+            }
+            operands = &operands[1..];
+        }
+
+        if options.print || operands.is_empty() {
+            return DeclareCommand::print(command, shell, &options, operands);
+        }
+        if command == "local" && !shell.in_function() {
+            eprintln!("local: can only be used in a function");
+            return 1;
+        }
+        // `declare` in a function makes local variables too, unless `-g` is
+        // given
+        let local = command == "local"
+            || (!matches!(command, "readonly" | "export")
+                && shell.in_function()
+                && !options.global);
+        let mut status = 0;
+        for operand in operands {
+            if let Err(message) = DeclareCommand::declare(shell, operand, &options, local) {
+                eprintln!("{}: {}", command, message);
+                status = 1;
+            }
+        }
+        status
+    }
+
+    fn declare(
+        shell: &mut Shell,
+        operand: &str,
+        options: &Options,
+        local: bool,
+    ) -> Result<(), String> {
+        let assignment = Parser::assignment(operand);
+        let name = match &assignment {
+            Some(assignment) => assignment.name.clone(),
+            None => operand.to_string(),
+        };
+        if !is_name(&name) {
+            return Err(format!("`{}': not a valid identifier", operand));
+        }
+        let variables = shell.variables();
+        if local {
+            variables.declare_local(&name)?;
+        }
+        if options.associative {
+            variables.make_associative(&name)?;
+        } else if options.indexed {
+            variables.make_indexed(&name)?;
+        }
+        // readonly only applies after the assignment that comes with it
+        let set = Attributes {
+            readonly: false,
+            ..options.set
+        };
+        variables.set_attributes(&name, set, options.clear)?;
+
+        match assignment {
+            Some(Assignment {
+                value: AssignmentValue::Scalar(target),
+                subscript: None,
+                ..
+            }) if options.set.nameref => {
+                let target =
+                    expansion::expand_word_to_string(&target, shell).map_err(|e| e.to_string())?;
+                shell.variables().set_reference(&name, target)?;
+            }
+            Some(assignment) => {
+                shell.assign(&assignment)?;
+            }
+            None => {}
+        }
+        if options.set.readonly {
+            let readonly = Attributes {
+                readonly: true,
+                ..Attributes::default()
+            };
+            shell
+                .variables()
+                .set_attributes(&name, readonly, Attributes::default())?;
+        }
+        Ok(())
+    }
+
+    /// `declare -p names`, or without names every variable with the given
+    /// attributes. Plain `declare` lists variables as assignments.
+    fn print(command: &str, shell: &mut Shell, options: &Options, names: &[String]) -> i32 {
+        let variables = shell.variables();
+        if names.is_empty() {
+            let plain = command != "readonly" && options.set == Attributes::default();
+            let plain = plain && !options.indexed && !options.associative && !options.print;
+            for (name, variable) in variables.all() {
+                let kind = match variable.value {
+                    Some(Value::Indexed(_)) => (true, false),
+                    Some(Value::Associative(_)) => (false, true),
+                    _ => (false, false),
+                };
+                if !variable.attributes.contains(options.set)
+                    || (options.indexed && !kind.0)
+                    || (options.associative && !kind.1)
+                {
+                    continue;
+                }
+                match plain {
+                    true if variable.value.is_some() => println!("{}", definition(name, variable)),
+                    true => {}
+                    false => println!("{}", declaration(name, variable)),
+                }
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for name in names {
+            match variables.variable(name) {
+                Some(variable) => println!("{}", declaration(name, variable)),
+                None => {
+                    eprintln!("{}: {}: not found", command, name);
+                    status = 1;
+                }
+            }
+        }
+        status
+    }
+
+    fn usage(command: &str, allowed: &str) -> i32 {
+        match command {
+            "local" => eprintln!("local: usage: local [option] name[=value] ..."),
+            _ => eprintln!(
+                "{}: usage: {} [-{}] [name[=value] ...]",
+                command, command, allowed
+            ),
+        }
+        2
+    }
+}
+
+impl Options {
+    fn apply(&mut self, option: char, on: bool) {
+        match option {
+            'a' => self.indexed = true,
+            'A' => self.associative = true,
+            'p' => self.print = true,
+            'g' => self.global = true,
+            _ => {
+                let attributes = if on { &mut self.set } else { &mut self.clear };
+                match option {
+                    'i' => attributes.integer = true,
+                    'l' => attributes.lowercase = true,
+                    'n' => attributes.nameref = true,
+                    'r' => attributes.readonly = true,
+                    'u' => attributes.uppercase = true,
+                    'x' => attributes.exported = true,
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+/// A `declare` command that recreates the variable, as printed by
+/// `declare -p`
+fn declaration(name: &str, variable: &Variable) -> String {
+    let attributes = variable.attributes;
+    let flags: String = [
+        (matches!(variable.value, Some(Value::Indexed(_))), 'a'),
+        (matches!(variable.value, Some(Value::Associative(_))), 'A'),
+        (attributes.integer, 'i'),
+        (attributes.nameref, 'n'),
+        (attributes.readonly, 'r'),
+        (attributes.exported, 'x'),
+        (attributes.lowercase, 'l'),
+        (attributes.uppercase, 'u'),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, flag)| flag)
+    .collect();
+    let flags = match flags.is_empty() {
+        true => "--".to_string(),
+        false => format!("-{}", flags),
+    };
+    format!("declare {} {}", flags, definition(name, variable))
+}
+
+/// `name="value"` or `name=([key]="value" ...)`
+pub fn definition(name: &str, variable: &Variable) -> String {
+    match &variable.value {
+        None => name.to_string(),
+        Some(Value::Scalar(value)) => format!("{}={}", name, utils::double_quote(value)),
+        Some(Value::Indexed(map)) => {
+            let elements: Vec<String> = map
+                .iter()
+                .map(|(index, value)| format!("[{}]={}", index, utils::double_quote(value)))
+                .collect();
+            format!("{}=({})", name, elements.join(" "))
+        }
+        Some(Value::Associative(map)) => {
+            let elements: String = map
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                        true => key.clone(),
+                        false => utils::double_quote(key),
+                    };
+                    format!("[{}]={} ", key, utils::double_quote(value))
+                })
+                .collect();
+            format!("{}=({})", name, elements)
+        }
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::declaration;
+    use crate::shell::Shell;
+    use crate::variables::{Attributes, Value, Variable};
+
+    #[test]
+    fn test_declarations() {
+        let variable = Variable {
+            value: Some(Value::Scalar("a\"$b".to_string())),
+            attributes: Attributes {
+                readonly: true,
+                exported: true,
+                ..Attributes::default()
+            },
+        };
+        assert_eq!(declaration("x", &variable), "declare -rx x=\"a\\\"\\$b\"");
+
+        let variable = Variable {
+            value: Some(Value::Indexed(BTreeMap::from([(1, "b".to_string())]))),
+            attributes: Attributes::default(),
+        };
+        assert_eq!(declaration("a", &variable), "declare -a a=([1]=\"b\")");
+
+        let variable = Variable {
+            value: Some(Value::Associative(BTreeMap::from([(
+                "a b".to_string(),
+                "v".to_string(),
+            )]))),
+            attributes: Attributes::default(),
+        };
+        assert_eq!(
+            declaration("m", &variable),
+            "declare -A m=([\"a b\"]=\"v\" )"
+        );
+
+        assert_eq!(declaration("n", &Variable::default()), "declare -- n");
+    }
+
+    #[test]
+    fn test_export() {
+        let mut shell = Shell::new();
+        let exported = |shell: &mut Shell, name: &str| {
+            let variable = shell.variables().variable(name).cloned();
+            variable.map(|variable| variable.attributes.exported)
+        };
+        assert_eq!(shell.eval("f() { export rushell_e=\"a b\"; }; f"), 0);
+        assert_eq!(exported(&mut shell, "rushell_e"), Some(true));
+        assert_eq!(shell.variables().get("rushell_e").as_deref(), Some("a b"));
+        assert_eq!(shell.eval("export -n rushell_e"), 0);
+        assert_eq!(exported(&mut shell, "rushell_e"), Some(false));
+        assert_eq!(shell.eval("export -x rushell_e"), 2);
+    }
+}
@@ -1,12 +1,81 @@
+use std::io::{stdout, Write};
+
+use crate::escapes;
+
 pub struct EchoCommand;
+
 impl EchoCommand {
-    pub fn execute(args: &[String]) -> i32 {
-        for arg in args.iter().take(args.len() - 1).skip(1) {
-            print!("{} ", arg);
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-echo
+    ///
+    /// With `xpg_echo`, backslash escapes are interpreted by default.
+    pub fn execute(args: &[String], xpg_echo: bool) -> i32 {
+        let mut newline = true;
+        let mut interpret_escapes = xpg_echo;
+
+        // only arguments made of valid option letters are options
+        let mut operands = &args[1..];
+        while let Some(arg) = operands.first() {
+            let is_option = arg.len() > 1
+                && arg.starts_with('-')
+                && arg[1..].chars().all(|c| matches!(c, 'n' | 'e' | 'E'));
+            if !is_option {
+                break;
+            }
+            for c in arg[1..].chars() {
+                match c {
+                    'n' => newline = false,
+                    'e' => interpret_escapes = true,
+                    _ => interpret_escapes = false,
+                }
+            }
+            operands = &operands[1..];
         }
-        if args.len() > 1 {
-            println!("{}", args[args.len() - 1]);
+
+        // escapes can write bytes that are not UTF-8
+        let mut output = Vec::new();
+        for (i, arg) in operands.iter().enumerate() {
+            if i > 0 {
+                output.push(b' ');
+            }
+            if !interpret_escapes {
+                output.extend_from_slice(arg.as_bytes());
+                continue;
+            }
+            if !EchoCommand::push_escaped(&mut output, arg) {
+                // `\c` suppresses all further output
+                newline = false;
+                break;
+            }
         }
-        0
+        if newline {
+            output.push(b'\n');
+        }
+
+        let mut stdout = stdout().lock();
+        match stdout.write_all(&output).and_then(|_| stdout.flush()) {
+            Ok(_) => 0,
+            Err(_) => 1,
+        }
+    }
+
+    /// Returns false if the output stops at `\c`
+    fn push_escaped(output: &mut Vec<u8>, arg: &str) -> bool {
+        let chars: Vec<char> = arg.chars().collect();
+        let mut current = 0;
+        while current < chars.len() {
+            if chars[current] != '\\' {
+                escapes::push_char(output, chars[current]);
+                current += 1;
+                continue;
+            }
+            match escapes::decode_echo(&chars, current + 1, false) {
+                Some((decoded, next)) => {
+                    output.extend_from_slice(&decoded);
+                    current = next;
+                }
+                None => return false,
+            }
+        }
+        true
     }
 }
@@ -0,0 +1,173 @@
+/// https://www.gnu.org/software/bash/manual/bash.html#ANSI_002dC-Quoting
+///
+/// Decodes the contents of `$'...'` starting at `start`, right after the
+/// opening quote. Returns the index after the closing quote and the decoded
+/// string, or `None` if the closing quote is missing. Bytes written with `\x`
+/// or in octal make up UTF-8 sequences, and those that are not valid UTF-8
+/// become replacement characters.
+pub fn scan_ansi_c_quoted(chars: &[char], start: usize) -> Option<(usize, String)> {
+    let mut value = Vec::new();
+    let mut current = start;
+    while current < chars.len() {
+        match chars[current] {
+            '\'' => {
+                let value = String::from_utf8_lossy(&value).into_owned();
+                return Some((current + 1, value));
+            }
+            '\\' => {
+                let (decoded, next) = decode(chars, current + 1);
+                value.extend_from_slice(&decoded);
+                current = next;
+            }
+            c => {
+                push_char(&mut value, c);
+                current += 1;
+            }
+        }
+    }
+    None
+}
+
+/// Decodes the escape sequence following a backslash, `start` being the index
+/// after the backslash. Returns the decoded bytes and the index after the
+/// sequence. Unknown sequences are left as they are, backslash included.
+pub fn decode(chars: &[char], start: usize) -> (Vec<u8>, usize) {
+    let Some(&c) = chars.get(start) else {
+        return (b"\\".to_vec(), start);
+    };
+    let simple = match c {
+        'a' => Some('\x07'),
+        'b' => Some('\x08'),
+        'e' | 'E' => Some('\x1b'),
+        'f' => Some('\x0c'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        'v' => Some('\x0b'),
+        '\\' | '\'' | '"' | '?' => Some(c),
+        _ => None,
+    };
+    if let Some(decoded) = simple {
+        return (decoded.to_string().into_bytes(), start + 1);
+    }
+
+    match c {
+        // octal and hexadecimal values are single bytes
+        '0'..='7' => {
+            let (value, next) = parse_digits(chars, start, 8, 3);
+            (vec![value as u8], next)
+        }
+        'x' | 'u' | 'U' => {
+            let max_digits = match c {
+                'x' => 2,
+                'u' => 4,
+                _ => 8,
+            };
+            let (value, next) = parse_digits(chars, start + 1, 16, max_digits);
+            if next == start + 1 {
+                return (format!("\\{}", c).into_bytes(), start + 1);
+            }
+            let decoded = match c {
+                'x' => vec![value as u8],
+                _ => char::from_u32(value)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER)
+                    .to_string()
+                    .into_bytes(),
+            };
+            (decoded, next)
+        }
+        // control character, `\c[` is escape
+        'c' => match chars.get(start + 1) {
+            Some(&x) => (vec![(x as u32 & 0x1f) as u8], start + 2),
+            None => (b"\\c".to_vec(), start + 1),
+        },
+        _ => (format!("\\{}", c).into_bytes(), start + 1),
+    }
+}
+
+/// Like `decode`, for `echo -e` and `printf %b`: octal values are written
+/// `\0nnn`, or also `\nnn` with `plain_octal` as `%b` allows, and quotes are
+/// not escaped. Returns `None` for `\c`, which stops the output.
+pub fn decode_echo(chars: &[char], start: usize, plain_octal: bool) -> Option<(Vec<u8>, usize)> {
+    match chars.get(start) {
+        Some('c') => None,
+        Some('0') => {
+            let (value, next) = parse_digits(chars, start + 1, 8, 3);
+            Some((vec![value as u8], next))
+        }
+        Some('1'..='7') if plain_octal => {
+            let (value, next) = parse_digits(chars, start, 8, 3);
+            Some((vec![value as u8], next))
+        }
+        Some(&c @ ('1'..='7' | '\'' | '"' | '?')) => {
+            Some((format!("\\{}", c).into_bytes(), start + 1))
+        }
+        _ => Some(decode(chars, start)),
+    }
+}
+
+/// Parses up to `max_digits` digits in `radix` from `start`, returns the value
+/// and the index after the last digit. Values too large for a `u32` are
+/// `u32::MAX`.
+pub fn parse_digits(chars: &[char], start: usize, radix: u32, max_digits: usize) -> (u32, usize) {
+    let mut value: u32 = 0;
+    let mut current = start;
+    while current < start.saturating_add(max_digits) {
+        match chars.get(current).and_then(|c| c.to_digit(radix)) {
+            Some(digit) => {
+                value = value
+                    .checked_mul(radix)
+                    .and_then(|value| value.checked_add(digit))
+                    .unwrap_or(u32::MAX)
+            }
+            None => break,
+        }
+        current += 1;
+    }
+    (value, current)
+}
+
+/// Appends the UTF-8 encoding of `c`
+pub fn push_char(bytes: &mut Vec<u8>, c: char) {
+    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_echo, scan_ansi_c_quoted};
+
+    fn decode(s: &str) -> String {
+        let chars: Vec<char> = s.chars().collect();
+        scan_ansi_c_quoted(&chars, 0).unwrap().1
+    }
+
+    #[test]
+    fn test_ansi_c_quoting() {
+        assert_eq!(decode("a\\nb\\tc'"), "a\nb\tc");
+        assert_eq!(decode("\\e[1m\\E'"), "\x1b[1m\x1b");
+        assert_eq!(decode("\\x41\\x4g\\101\\0'"), "A\x04gA\0");
+        assert_eq!(decode("\\u00e9\\U0001F600'"), "é😀");
+        assert_eq!(decode("\\cA\\c['"), "\x01\x1b");
+        assert_eq!(decode("\\xe2\\x82\\xac \\342\\202\\254'"), "€ €");
+        assert_eq!(decode("\\xff'"), "\u{fffd}");
+        assert_eq!(decode("it\\'s \\\\ \\q \\x'"), "it's \\ \\q \\x");
+    }
+
+    #[test]
+    fn test_unterminated() {
+        let chars: Vec<char> = "abc\\'".chars().collect();
+        assert!(scan_ansi_c_quoted(&chars, 0).is_none());
+    }
+
+    #[test]
+    fn test_echo_escapes() {
+        let chars: Vec<char> = "\\0101\\101\\c".chars().collect();
+        assert_eq!(decode_echo(&chars, 1, false), Some((b"A".to_vec(), 5)));
+        assert_eq!(decode_echo(&chars, 6, false), Some((b"\\1".to_vec(), 7)));
+        assert_eq!(decode_echo(&chars, 10, false), None);
+        assert_eq!(decode_echo(&chars, 1, true), Some((b"A".to_vec(), 5)));
+        assert_eq!(decode_echo(&chars, 6, true), Some((b"A".to_vec(), 9)));
+    }
+}
@@ -0,0 +1,43 @@
+use crate::shell::Shell;
+
+pub struct EvalCommand;
+
+impl EvalCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-eval
+    ///
+    /// The arguments are joined with spaces and run as commands.
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let mut operands = &args[1..];
+        if operands.first().is_some_and(|arg| arg == "--") {
+            operands = &operands[1..];
+        }
+        shell.eval(&operands.join(" "))
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+
+    #[test]
+    fn test_arguments_are_joined() {
+        let mut shell = Shell::new();
+        shell.eval("command='a=1;' value='$a'");
+        assert_eq!(shell.eval("eval $command b=$value '&&' c=2"), 0);
+        assert_eq!(shell.variable("b").as_deref(), Some("1"));
+        assert_eq!(shell.variable("c").as_deref(), Some("2"));
+        assert_eq!(shell.eval("eval -- d=3"), 0);
+        assert_eq!(shell.variable("d").as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn test_status() {
+        let mut shell = Shell::new();
+        assert_eq!(shell.eval("eval"), 0);
+        assert_eq!(shell.eval("eval false"), 1);
+        assert_eq!(shell.eval("eval 'if'"), 2);
+        assert_eq!(shell.eval("eval 'f() { return 4; }; f'"), 4);
+    }
+}
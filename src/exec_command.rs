@@ -0,0 +1,155 @@
+use std::env;
+use std::io::{stdout, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::shell::Shell;
+use crate::signals;
+use crate::utils;
+
+pub struct ExecCommand;
+
+impl ExecCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-exec
+    ///
+    /// Replaces the shell with the command. `-a name` passes `name` as its
+    /// `$0`, `-l` puts a dash in front of it like `login` does, and `-c` runs
+    /// it with an empty environment. Without a command, the redirections are
+    /// made permanent before this is called.
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let mut name: Option<String> = None;
+        let mut login = false;
+        let mut clear_environment = false;
+        let mut index = 1;
+        while let Some(arg) = args.get(index) {
+            index += 1;
+            if arg == "--" {
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                index -= 1;
+                break;
+            }
+            for letter in arg[1..].chars() {
+                match letter {
+                    'c' => clear_environment = true,
+                    'l' => login = true,
+                    'a' => match args.get(index) {
+                        Some(value) => {
+                            name = Some(value.clone());
+                            index += 1;
+                        }
+                        None => {
+                            eprintln!("exec: -a: option requires an argument");
+                            return ExecCommand::usage();
+                        }
+                    },
+                    _ => {
+                        eprintln!("exec: -{}: invalid option", letter);
+                        return ExecCommand::usage();
+                    }
+                }
+            }
+        }
+        let Some(program) = args.get(index) else {
+            return 0;
+        };
+
+        if find_program(program).is_none() {
+            eprintln!("exec: {}: not found", program);
+            return ExecCommand::fail(127, shell);
+        }
+
+        let mut name = name.unwrap_or_else(|| program.clone());
+        if login {
+            name.insert(0, '-');
+        }
+        let mut command = process::Command::new(program);
+        command.arg0(name).args(&args[index + 1..]);
+        if clear_environment {
+            command.env_clear();
+        }
+        stdout().flush().ok();
+        signals::reset();
+        shell.traps().ignore_signals();
+        let error = command.exec();
+
+        eprintln!("exec: {}: {}", program, utils::error_message(&error));
+        signals::ignore(libc::SIGPIPE);
+        if shell.is_interactive() {
+            signals::init_interactive();
+            signals::init_job_control();
+        }
+        ExecCommand::fail(126, shell)
+    }
+
+    /// A script whose shell could not be replaced has nothing left to run
+    fn fail(status: i32, shell: &mut Shell) -> i32 {
+        if !shell.is_interactive() {
+            shell.request_exit(status);
+        }
+        status
+    }
+
+    fn usage() -> i32 {
+        eprintln!("exec: usage: exec [-cl] [-a name] [command [argument ...]] [redirection ...]");
+        2
+    }
+}
+
+/// Where `program` is, looking in `PATH` if it has no slash
+fn find_program(program: &str) -> Option<PathBuf> {
+    let executable = |path: &Path| {
+        path.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    };
+    if program.contains('/') {
+        return Some(PathBuf::from(program)).filter(|path| path.exists());
+    }
+    env::var("PATH")
+        .ok()?
+        .split(':')
+        .map(|directory| Path::new(directory).join(program))
+        .find(|path| executable(path))
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::shell::Shell;
+
+    #[test]
+    fn test_redirections_are_permanent() {
+        let path = std::env::temp_dir().join(format!("rushell-{}-exec", std::process::id()));
+        let mut shell = Shell::new();
+        // descriptors well above those the tests use
+        assert_eq!(shell.eval(&format!("exec 57>{}", path.display())), 0);
+        assert_eq!(shell.eval("echo first >&57; echo second >&57"), 0);
+        assert_eq!(shell.eval("exec 57>&-"), 0);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+        assert_eq!(shell.eval("echo closed >&57"), 1);
+
+        shell.eval(&format!(
+            "exec 58<{}; read -r a <&58; read -r b <&58; exec 58<&-",
+            path.display()
+        ));
+        assert_eq!(shell.variables().get("a").as_deref(), Some("first"));
+        assert_eq!(shell.variables().get("b").as_deref(), Some("second"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut shell = Shell::new();
+        assert_eq!(shell.eval("exec -z"), 2);
+        assert_eq!(shell.eval("exec -a"), 2);
+        // a script cannot go on without the command that replaces it
+        assert_eq!(shell.eval("exec rushell-missing; not_run=1"), 127);
+        assert_eq!(shell.variables().get("not_run"), None);
+    }
+}
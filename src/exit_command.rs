@@ -1,9 +1,31 @@
-use std::process::exit;
+use crate::shell::Shell;
 
 pub struct ExitCommand;
 
 impl ExitCommand {
-    pub fn execute(_: &[String]) -> i32 {
-        exit(0)
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-exit
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        if args.len() > 2 {
+            eprintln!("exit: too many arguments");
+            return 1;
+        }
+        if shell.warn_stopped_jobs() {
+            eprintln!("There are stopped jobs.");
+            return 1;
+        }
+
+        let status = match args.get(1) {
+            None => shell.last_status(),
+            Some(arg) => match arg.trim().parse::<i64>() {
+                // only the low 8 bits are reported to the parent process
+                Ok(n) => n.rem_euclid(256) as i32,
+                Err(_) => {
+                    eprintln!("exit: {}: numeric argument required", arg);
+                    2
+                }
+            },
+        };
+        shell.request_exit(status);
+        status
     }
 }
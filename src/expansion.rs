@@ -0,0 +1,1145 @@
+use std::fmt::Display;
+
+use crate::escapes;
+use crate::parser::is_name;
+use crate::scanner;
+
+/// What the expansions need to know about the shell
+pub trait Environment {
+    /// Value of a variable or special parameter, `None` if it is unset
+    fn parameter(&mut self, name: &str) -> Option<String>;
+    fn positional_parameters(&self) -> Vec<String>;
+    /// `${name[subscript]}` with the subscript already expanded, `None` if the
+    /// element is unset
+    fn element(&mut self, name: &str, subscript: &str) -> Result<Option<String>, ExpansionError>;
+    /// Keys and values of the elements of an array, a scalar is an array with
+    /// the single element 0
+    fn elements(&mut self, name: &str) -> Vec<(String, String)>;
+    /// Called when an unset parameter is expanded, which is an error with
+    /// `set -u`
+    fn unbound(&mut self, name: &str) -> Result<(), ExpansionError>;
+    /// Sets a variable for `${name=word}`
+    fn assign(&mut self, name: &str, value: String) -> Result<(), ExpansionError>;
+    /// The error of `${parameter?word}` for an unset parameter, which also
+    /// stops a script
+    fn unset_error(&mut self, message: String) -> ExpansionError;
+    /// Output of running `command` in a subshell, without trailing newlines
+    fn command_substitution(&mut self, command: &str) -> Result<String, ExpansionError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpansionError {
+    pub message: String,
+}
+
+impl Display for ExpansionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Shell-Expansions
+///
+/// Expands the parameters in `word` as written in the source, removes quotes
+/// and splits the result of unquoted expansions into fields.
+pub fn expand_word(word: &str, env: &mut dyn Environment) -> Result<Vec<String>, ExpansionError> {
+    let ifs = env.parameter("IFS").unwrap_or_else(|| " \t\n".to_string());
+    let mut expander = Expander {
+        chars: word.chars().collect(),
+        current: 0,
+        empty_list: false,
+        fields: Fields::new(Some(ifs)),
+    };
+    expander.expand(env)?;
+    Ok(expander.fields.finish())
+}
+
+/// Like `expand_word`, but without field splitting, for assignments and other
+/// places where a word always expands to a single string
+pub fn expand_word_to_string(
+    word: &str,
+    env: &mut dyn Environment,
+) -> Result<String, ExpansionError> {
+    let mut expander = Expander {
+        chars: word.chars().collect(),
+        current: 0,
+        empty_list: false,
+        fields: Fields::new(None),
+    };
+    expander.expand(env)?;
+    Ok(expander.fields.finish().join(" "))
+}
+
+/// Like `expand_word_to_string`, for the pattern in `[[ word == pattern ]]`.
+/// Quoted characters are escaped with a backslash so that they match
+/// themselves.
+pub fn expand_pattern(word: &str, env: &mut dyn Environment) -> Result<String, ExpansionError> {
+    expand_escaping_quoted(word, "\\*?[", env)
+}
+
+/// Like `expand_pattern`, for the regular expression in `[[ word =~ regex ]]`
+pub fn expand_regex(word: &str, env: &mut dyn Environment) -> Result<String, ExpansionError> {
+    expand_escaping_quoted(word, "\\.+*?()|[]{}^$", env)
+}
+
+fn expand_escaping_quoted(
+    word: &str,
+    special: &'static str,
+    env: &mut dyn Environment,
+) -> Result<String, ExpansionError> {
+    let mut fields = Fields::new(None);
+    fields.special = Some(special);
+    let mut expander = Expander {
+        chars: word.chars().collect(),
+        current: 0,
+        empty_list: false,
+        fields,
+    };
+    expander.expand(env)?;
+    Ok(expander.fields.finish().join(" "))
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Here-Documents
+///
+/// Expands the parameters in the body of a here-document whose delimiter is
+/// not quoted. Quotes are ordinary characters, and a backslash only escapes
+/// `$`, `` ` ``, `\` and newline.
+pub fn expand_here_document(
+    body: &str,
+    env: &mut dyn Environment,
+) -> Result<String, ExpansionError> {
+    let mut expander = Expander {
+        chars: body.chars().collect(),
+        current: 0,
+        empty_list: false,
+        fields: Fields::new(None),
+    };
+    while let Some(c) = expander.advance() {
+        match c {
+            '\\' => match expander.advance() {
+                Some(c @ ('$' | '`' | '\\')) => expander.fields.push_quoted(&c.to_string()),
+                Some('\n') => {}
+                Some(c) => expander.fields.push_quoted(&format!("\\{}", c)),
+                None => expander.fields.push_quoted("\\"),
+            },
+            '$' => expander.expand_parameter(env, false)?,
+            '`' => expander.expand_backquoted(env, false)?,
+            _ => expander.fields.push_quoted(&c.to_string()),
+        }
+    }
+    Ok(expander.fields.finish().concat())
+}
+
+/// Fields produced by expanding a word
+struct Fields {
+    /// `None` disables field splitting
+    ifs: Option<String>,
+    /// characters escaped with a backslash when they are quoted, so that they
+    /// lose their special meaning in a pattern
+    special: Option<&'static str>,
+    /// unquoted characters of the word are split like expansions, in the word
+    /// of an unquoted `${parameter-word}`
+    split_literals: bool,
+    fields: Vec<String>,
+    current: String,
+    /// whether `current` is a field even if it is empty, like `""`
+    started: bool,
+    /// the last field was delimited by IFS whitespace, which may be followed by
+    /// one non-whitespace IFS character belonging to the same delimiter
+    after_whitespace: bool,
+}
+
+impl Fields {
+    fn new(ifs: Option<String>) -> Self {
+        Fields {
+            ifs,
+            special: None,
+            split_literals: false,
+            fields: Vec::new(),
+            current: String::new(),
+            started: false,
+            after_whitespace: false,
+        }
+    }
+
+    /// Text that is not subject to field splitting
+    fn push_quoted(&mut self, s: &str) {
+        match self.special {
+            Some(special) => {
+                for c in s.chars() {
+                    if special.contains(c) {
+                        self.current.push('\\');
+                    }
+                    self.current.push(c);
+                }
+            }
+            None => self.current.push_str(s),
+        }
+        self.started = true;
+        self.after_whitespace = false;
+    }
+
+    /// Unquoted characters of the word itself, which are never split and keep
+    /// their special meaning in a pattern
+    fn push_literal(&mut self, s: &str) {
+        if self.split_literals && self.ifs.is_some() {
+            return self.push_unquoted(s);
+        }
+        self.current.push_str(s);
+        self.started = true;
+        self.after_whitespace = false;
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Word-Splitting
+    fn push_unquoted(&mut self, s: &str) {
+        let ifs = match &self.ifs {
+            Some(ifs) => ifs.clone(),
+            None => return self.push_literal(s),
+        };
+        for c in s.chars() {
+            if !ifs.contains(c) {
+                self.current.push(c);
+                self.started = true;
+                self.after_whitespace = false;
+            } else if c == ' ' || c == '\t' || c == '\n' {
+                if self.started {
+                    self.end_field();
+                    self.after_whitespace = true;
+                }
+            } else if self.after_whitespace {
+                self.after_whitespace = false;
+            } else {
+                self.end_field();
+            }
+        }
+    }
+
+    fn end_field(&mut self) {
+        self.fields.push(std::mem::take(&mut self.current));
+        self.started = false;
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        if self.started {
+            self.end_field();
+        }
+        self.fields
+    }
+}
+
+struct Expander {
+    chars: Vec<char>,
+    current: usize,
+    fields: Fields,
+    /// the last list expansion, like `"$@"`, expanded to nothing
+    empty_list: bool,
+}
+
+impl Expander {
+    fn expand(&mut self, env: &mut dyn Environment) -> Result<(), ExpansionError> {
+        while let Some(c) = self.advance() {
+            match c {
+                '\\' => match self.advance() {
+                    // line continuation
+                    Some('\n') => {}
+                    Some(c) => self.fields.push_quoted(&c.to_string()),
+                    None => {}
+                },
+                '\'' => {
+                    let mut value = String::new();
+                    while let Some(c) = self.advance() {
+                        if c == '\'' {
+                            break;
+                        }
+                        value.push(c);
+                    }
+                    self.fields.push_quoted(&value);
+                }
+                '"' => self.expand_double_quoted(env)?,
+                '$' if self.peek() == Some('\'') => {
+                    let (end, value) = escapes::scan_ansi_c_quoted(&self.chars, self.current + 1)
+                        .unwrap_or((self.chars.len(), String::new()));
+                    self.fields.push_quoted(&value);
+                    self.current = end;
+                }
+                '$' => self.expand_parameter(env, false)?,
+                '`' => self.expand_backquoted(env, false)?,
+                _ => self.fields.push_literal(&c.to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Double-Quotes
+    fn expand_double_quoted(&mut self, env: &mut dyn Environment) -> Result<(), ExpansionError> {
+        let started = self.fields.started;
+        let start = self.current;
+        // `""` is an empty field rather than no field at all
+        self.fields.push_quoted("");
+        // except for `"$@"`, which is no field at all without positional
+        // parameters, and likewise `"${name[@]}"` for an empty array
+        let mut empty_list = false;
+        while let Some(c) = self.advance() {
+            match c {
+                '"' => break,
+                '\\' => match self.advance() {
+                    Some(c @ ('$' | '`' | '"' | '\\')) => self.fields.push_quoted(&c.to_string()),
+                    Some('\n') => {}
+                    Some(c) => self.fields.push_quoted(&format!("\\{}", c)),
+                    None => self.fields.push_quoted("\\"),
+                },
+                '$' => {
+                    let at_start = self.current - 1 == start;
+                    self.empty_list = false;
+                    self.expand_parameter(env, true)?;
+                    empty_list = at_start && self.peek() == Some('"') && self.empty_list;
+                }
+                '`' => self.expand_backquoted(env, true)?,
+                _ => self.fields.push_quoted(&c.to_string()),
+            }
+        }
+
+        if empty_list {
+            self.fields.started = started;
+        }
+        Ok(())
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Shell-Parameter-Expansion
+    fn expand_parameter(
+        &mut self,
+        env: &mut dyn Environment,
+        quoted: bool,
+    ) -> Result<(), ExpansionError> {
+        let name = match self.peek() {
+            Some('{') => {
+                self.advance();
+                let mut name = String::new();
+                // braces nested in the braces, like `${a[${i}]}`
+                let mut depth = 0;
+                loop {
+                    match self.advance() {
+                        Some('}') if depth == 0 => break,
+                        Some(c) => {
+                            match c {
+                                '{' => depth += 1,
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                            name.push(c);
+                        }
+                        None => return Err(bad_substitution(&format!("${{{}", name))),
+                    }
+                }
+                return self.expand_braced(env, &name, quoted);
+            }
+            Some('(') => {
+                let end = scanner::command_substitution_end(&self.chars, self.current - 1);
+                let command: String = self.chars[self.current + 1..end - 1].iter().collect();
+                self.current = end;
+                return self.substitute(env, &command, quoted);
+            }
+            Some(c) if is_special_parameter(&c.to_string()) || c.is_ascii_digit() => {
+                self.advance();
+                c.to_string()
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(c) = self.peek() {
+                    if !c.is_ascii_alphanumeric() && c != '_' {
+                        break;
+                    }
+                    name.push(c);
+                    self.advance();
+                }
+                name
+            }
+            // a lone `$` is not an expansion
+            _ => {
+                match quoted {
+                    true => self.fields.push_quoted("$"),
+                    false => self.fields.push_literal("$"),
+                }
+                return Ok(());
+            }
+        };
+
+        match name.as_str() {
+            "@" | "*" => {
+                let parameters = env.positional_parameters();
+                self.expand_list(env, &parameters, name == "*", quoted);
+            }
+            _ => {
+                let value = match env.parameter(&name) {
+                    Some(value) => value,
+                    None => {
+                        unbound(env, &name)?;
+                        String::new()
+                    }
+                };
+                if quoted {
+                    self.fields.push_quoted(&value);
+                } else {
+                    self.fields.push_unquoted(&value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `` `command` ``, in which a backslash only escapes `$`, `` ` `` and `\`
+    fn expand_backquoted(
+        &mut self,
+        env: &mut dyn Environment,
+        quoted: bool,
+    ) -> Result<(), ExpansionError> {
+        let end = scanner::command_substitution_end(&self.chars, self.current - 1);
+        let mut command = String::new();
+        let mut chars = self.chars[self.current..end - 1].iter().peekable();
+        while let Some(&c) = chars.next() {
+            match (c, chars.peek()) {
+                ('\\', Some(&&next @ ('$' | '`' | '\\'))) => {
+                    command.push(next);
+                    chars.next();
+                }
+                _ => command.push(c),
+            }
+        }
+        self.current = end;
+        self.substitute(env, &command, quoted)
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Command-Substitution
+    fn substitute(
+        &mut self,
+        env: &mut dyn Environment,
+        command: &str,
+        quoted: bool,
+    ) -> Result<(), ExpansionError> {
+        let output = env.command_substitution(command)?;
+        match quoted {
+            true => self.fields.push_quoted(&output),
+            false => self.fields.push_unquoted(&output),
+        }
+        Ok(())
+    }
+
+    /// `${parameter}`, `${name[subscript]}`, `${#parameter}` for the length,
+    /// `${!name[@]}` for the keys of an array, `${parameter:offset:length}`
+    /// for a substring or a slice of an array and `${parameter:-word}` and
+    /// friends for unset or null parameters
+    fn expand_braced(
+        &mut self,
+        env: &mut dyn Environment,
+        contents: &str,
+        quoted: bool,
+    ) -> Result<(), ExpansionError> {
+        let Some(expansion) = Braced::parse(contents) else {
+            return Err(bad_substitution(&format!("${{{}}}", contents)));
+        };
+        let subscript = match expansion.subscript {
+            Some(subscript @ ("@" | "*")) => Some(subscript.to_string()),
+            Some(subscript) => Some(expand_word_to_string(subscript, env)?),
+            None => None,
+        };
+        let star = matches!(subscript.as_deref(), Some("*")) || expansion.name == "*";
+        let is_list = matches!(subscript.as_deref(), Some("@" | "*"))
+            || expansion.name == "@"
+            || expansion.name == "*";
+
+        if let Some(operator) = &expansion.operator {
+            let missing = match is_list {
+                true => {
+                    let values: Vec<String> = match &subscript {
+                        Some(_) => env
+                            .elements(expansion.name)
+                            .into_iter()
+                            .map(|(_, value)| value)
+                            .collect(),
+                        None => env.positional_parameters(),
+                    };
+                    values.is_empty() || (operator.colon && values.iter().all(String::is_empty))
+                }
+                false => {
+                    let value = match &subscript {
+                        Some(subscript) => env.element(expansion.name, subscript)?,
+                        None => env.parameter(expansion.name),
+                    };
+                    value.map_or(true, |value| operator.colon && value.is_empty())
+                }
+            };
+            match (operator.operator, missing) {
+                ('-', true) | ('+', false) => {
+                    return self.expand_operand(env, operator.word, quoted)
+                }
+                ('+', true) => return Ok(()),
+                ('=', true) => {
+                    if subscript.is_some() || !is_name(expansion.name) {
+                        return Err(ExpansionError {
+                            message: format!("${}: cannot assign in this way", expansion.name),
+                        });
+                    }
+                    let value = expand_word_to_string(operator.word, env)?;
+                    env.assign(expansion.name, value)?;
+                }
+                ('?', true) => {
+                    let message = match operator.word {
+                        "" => "parameter null or not set".to_string(),
+                        word => expand_word_to_string(word, env)?,
+                    };
+                    return Err(env.unset_error(format!("{}: {}", expansion.name, message)));
+                }
+                _ => {}
+            }
+        }
+
+        let value = match (expansion.prefix, is_list) {
+            (Some('#'), true) => {
+                let length = match &subscript {
+                    Some(_) => env.elements(expansion.name).len(),
+                    None => env.positional_parameters().len(),
+                };
+                length.to_string()
+            }
+            (Some('#'), false) => {
+                let value = self.value(env, expansion.name, subscript.as_deref())?;
+                value.chars().count().to_string()
+            }
+            (Some('!'), true) if subscript.is_some() => {
+                let keys: Vec<String> = env
+                    .elements(expansion.name)
+                    .into_iter()
+                    .map(|(key, _)| key)
+                    .collect();
+                self.expand_list(env, &keys, star, quoted);
+                return Ok(());
+            }
+            (Some(_), _) => return Err(bad_substitution(&format!("${{{}}}", contents))),
+            (None, true) => {
+                let mut values: Vec<(i64, String)> = match &subscript {
+                    Some(_) => env
+                        .elements(expansion.name)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, (key, value))| (key.parse().unwrap_or(i as i64), value))
+                        .collect(),
+                    // `${@:0}` starts with `$0`
+                    None => {
+                        let name = env.parameter("0").unwrap_or_default();
+                        let parameters = env.positional_parameters();
+                        let all = std::iter::once(name).chain(parameters);
+                        let mut values: Vec<(i64, String)> = (0..).zip(all).collect();
+                        if expansion.slice.is_none() {
+                            values.remove(0);
+                        }
+                        values
+                    }
+                };
+                if let Some((offset, length)) = expansion.slice {
+                    let end = values
+                        .last()
+                        .map_or(0, |(index, _)| index.saturating_add(1));
+                    let offset = evaluate_integer(offset, env)?;
+                    let offset = if offset < 0 {
+                        end.saturating_add(offset)
+                    } else {
+                        offset
+                    };
+                    values.retain(|&(index, _)| index >= offset);
+                    if let Some(length) = length {
+                        let length = evaluate_integer(length, env)?;
+                        if length < 0 {
+                            return Err(ExpansionError {
+                                message: format!("{}: substring expression < 0", length),
+                            });
+                        }
+                        values.truncate(length as usize);
+                    }
+                }
+                let values: Vec<String> = values.into_iter().map(|(_, value)| value).collect();
+                self.expand_list(env, &values, star, quoted);
+                return Ok(());
+            }
+            (None, false) => {
+                let value = self.value(env, expansion.name, subscript.as_deref())?;
+                match expansion.slice {
+                    Some((offset, length)) => substring(&value, offset, length, env)?,
+                    None => value,
+                }
+            }
+        };
+        if quoted {
+            self.fields.push_quoted(&value);
+        } else {
+            self.fields.push_unquoted(&value);
+        }
+        Ok(())
+    }
+
+    /// The word of `${parameter-word}`, expanded in place of the parameter.
+    /// Unquoted, its own quotes apply and the result is split into fields.
+    fn expand_operand(
+        &mut self,
+        env: &mut dyn Environment,
+        word: &str,
+        quoted: bool,
+    ) -> Result<(), ExpansionError> {
+        if quoted {
+            let value = expand_word_to_string(word, env)?;
+            self.fields.push_quoted(&value);
+            return Ok(());
+        }
+        let chars = std::mem::replace(&mut self.chars, word.chars().collect());
+        let current = std::mem::replace(&mut self.current, 0);
+        let split_literals = std::mem::replace(&mut self.fields.split_literals, true);
+        let result = self.expand(env);
+        self.chars = chars;
+        self.current = current;
+        self.fields.split_literals = split_literals;
+        result
+    }
+
+    /// The value of a parameter, or of an element of an array
+    fn value(
+        &mut self,
+        env: &mut dyn Environment,
+        name: &str,
+        subscript: Option<&str>,
+    ) -> Result<String, ExpansionError> {
+        let value = match subscript {
+            Some(subscript) => env.element(name, subscript)?,
+            None => env.parameter(name),
+        };
+        if value.is_none() {
+            match subscript {
+                Some(subscript) => env.unbound(&format!("{}[{}]", name, subscript))?,
+                None => unbound(env, name)?,
+            }
+        }
+        Ok(value.unwrap_or_default())
+    }
+
+    /// `$@` and `$*` expand to one field per positional parameter, except for
+    /// `"$*"` which joins them with the first character of IFS. Arrays expand
+    /// the same way.
+    fn expand_list(
+        &mut self,
+        env: &mut dyn Environment,
+        parameters: &[String],
+        star: bool,
+        quoted: bool,
+    ) {
+        self.empty_list = quoted && parameters.is_empty();
+        if quoted && star {
+            let separator = match env.parameter("IFS") {
+                Some(ifs) => ifs.chars().next().map(String::from).unwrap_or_default(),
+                None => " ".to_string(),
+            };
+            self.fields.push_quoted(&parameters.join(&separator));
+            return;
+        }
+
+        for (i, parameter) in parameters.iter().enumerate() {
+            if i > 0 {
+                if quoted || (self.fields.ifs.is_some() && self.fields.started) {
+                    self.fields.end_field();
+                } else if self.fields.ifs.is_none() {
+                    self.fields.push_quoted(" ");
+                }
+            }
+            if quoted {
+                self.fields.push_quoted(parameter);
+            } else {
+                self.fields.push_unquoted(parameter);
+            }
+        }
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.chars.get(self.current).copied();
+        if c.is_some() {
+            self.current += 1;
+        }
+        c
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Special-Parameters
+fn is_special_parameter(name: &str) -> bool {
+    matches!(name, "@" | "*" | "#" | "?" | "-" | "$" | "!" | "0")
+}
+
+/// The parts of `${...}`
+struct Braced<'a> {
+    /// `#` or `!`
+    prefix: Option<char>,
+    name: &'a str,
+    subscript: Option<&'a str>,
+    /// `:offset` or `:offset:length`
+    slice: Option<(&'a str, Option<&'a str>)>,
+    operator: Option<Operator<'a>>,
+}
+
+/// `-word`, `=word`, `+word` or `?word` after a parameter
+struct Operator<'a> {
+    /// a null parameter counts as unset, as in `${parameter:-word}`
+    colon: bool,
+    operator: char,
+    word: &'a str,
+}
+
+impl<'a> Braced<'a> {
+    fn parse(contents: &'a str) -> Option<Braced<'a>> {
+        let (prefix, rest) = match contents.chars().next() {
+            Some(c @ ('#' | '!')) if contents.len() > 1 => (Some(c), &contents[1..]),
+            _ => (None, contents),
+        };
+        let name_len = match rest.chars().next()? {
+            c if c.is_ascii_digit() => rest.chars().take_while(char::is_ascii_digit).count(),
+            c if is_special_parameter(&c.to_string()) => 1,
+            _ => rest
+                .chars()
+                .take_while(|&c| c.is_ascii_alphanumeric() || c == '_')
+                .count(),
+        };
+        let (name, mut rest) = rest.split_at(name_len);
+        if !is_name(name)
+            && !name.chars().all(|c| c.is_ascii_digit())
+            && !is_special_parameter(name)
+        {
+            return None;
+        }
+
+        let mut subscript = None;
+        if rest.starts_with('[') && is_name(name) {
+            let mut depth = 0;
+            let end = rest.char_indices().find(|&(_, c)| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })?;
+            subscript = Some(&rest[1..end.0]);
+            rest = &rest[end.0 + 1..];
+        }
+
+        let mut slice = None;
+        let mut operator = None;
+        if prefix.is_none() {
+            let (colon, after) = match rest.strip_prefix(':') {
+                Some(after) => (true, after),
+                None => (false, rest),
+            };
+            match after.chars().next() {
+                Some(c @ ('-' | '=' | '+' | '?')) => {
+                    operator = Some(Operator {
+                        colon,
+                        operator: c,
+                        word: &after[1..],
+                    });
+                }
+                _ if colon => {
+                    slice = match after.split_once(':') {
+                        Some((offset, length)) => Some((offset, Some(length))),
+                        None => Some((after, None)),
+                    };
+                }
+                _ if rest.is_empty() => {}
+                _ => return None,
+            }
+        } else if !rest.is_empty() {
+            return None;
+        }
+        Some(Braced {
+            prefix,
+            name,
+            subscript,
+            slice,
+            operator,
+        })
+    }
+}
+
+/// An offset or a length, which may also be the name of a variable holding
+/// a number
+fn evaluate_integer(text: &str, env: &mut dyn Environment) -> Result<i64, ExpansionError> {
+    let value = expand_word_to_string(text, env)?;
+    let value = value.trim();
+    match value.parse() {
+        Ok(n) => Ok(n),
+        Err(_) if value.is_empty() => Ok(0),
+        Err(_) if is_name(value) => Ok(env
+            .parameter(value)
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0)),
+        Err(_) => Err(ExpansionError {
+            message: format!("{}: syntax error: operand expected", value),
+        }),
+    }
+}
+
+/// `${parameter:offset:length}`, where a negative offset counts from the end
+/// and a negative length leaves that many characters off the end
+fn substring(
+    value: &str,
+    offset: &str,
+    length: Option<&str>,
+    env: &mut dyn Environment,
+) -> Result<String, ExpansionError> {
+    let chars: Vec<char> = value.chars().collect();
+    let len = chars.len() as i64;
+    let offset = evaluate_integer(offset, env)?;
+    let start = if offset < 0 {
+        len.saturating_add(offset)
+    } else {
+        offset
+    };
+    if start < 0 || start > len {
+        return Ok(String::new());
+    }
+    let end = match length {
+        Some(length) => match evaluate_integer(length, env)? {
+            length if length < 0 && len.saturating_add(length) < start => {
+                return Err(ExpansionError {
+                    message: format!("{}: substring expression < 0", length),
+                });
+            }
+            length if length < 0 => len + length,
+            length => start.saturating_add(length).min(len),
+        },
+        None => len,
+    };
+    Ok(chars[start as usize..end as usize].iter().collect())
+}
+
+/// Reports an unset parameter, positional parameters as `$1`
+fn unbound(env: &mut dyn Environment, name: &str) -> Result<(), ExpansionError> {
+    match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => env.unbound(&format!("${}", name)),
+        false => env.unbound(name),
+    }
+}
+
+fn bad_substitution(text: &str) -> ExpansionError {
+    ExpansionError {
+        message: format!("{}: bad substitution", text),
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        expand_here_document, expand_pattern, expand_regex, expand_word, expand_word_to_string,
+        Environment, ExpansionError,
+    };
+
+    struct TestEnvironment {
+        variables: HashMap<String, String>,
+        positional: Vec<String>,
+        nounset: bool,
+    }
+
+    impl Environment for TestEnvironment {
+        fn parameter(&mut self, name: &str) -> Option<String> {
+            if name == "0" {
+                return Some("rushell".to_string());
+            }
+            if let Ok(n) = name.parse::<usize>() {
+                return self.positional.get(n - 1).cloned();
+            }
+            if name == "#" {
+                return Some(self.positional.len().to_string());
+            }
+            self.variables.get(name).cloned()
+        }
+
+        fn positional_parameters(&self) -> Vec<String> {
+            self.positional.clone()
+        }
+
+        fn element(
+            &mut self,
+            name: &str,
+            subscript: &str,
+        ) -> Result<Option<String>, ExpansionError> {
+            let elements = self.elements(name);
+            let end = elements
+                .last()
+                .map_or(0, |(key, _)| key.parse::<i64>().unwrap() + 1);
+            let index = match subscript.parse::<i64>() {
+                Ok(index) if index < 0 => index + end,
+                Ok(index) => index,
+                Err(_) => {
+                    return Err(ExpansionError {
+                        message: "bad array subscript".to_string(),
+                    })
+                }
+            };
+            if index < 0 {
+                return Err(ExpansionError {
+                    message: "bad array subscript".to_string(),
+                });
+            }
+            let key = index.to_string();
+            Ok(elements
+                .into_iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v))
+        }
+
+        fn elements(&mut self, name: &str) -> Vec<(String, String)> {
+            match name {
+                "array" => vec![
+                    ("0".to_string(), "a b".to_string()),
+                    ("2".to_string(), "c".to_string()),
+                ],
+                "none" => vec![],
+                _ => self
+                    .parameter(name)
+                    .map(|v| ("0".to_string(), v))
+                    .into_iter()
+                    .collect(),
+            }
+        }
+
+        fn unbound(&mut self, name: &str) -> Result<(), ExpansionError> {
+            match self.nounset {
+                true => Err(ExpansionError {
+                    message: format!("{}: unbound variable", name),
+                }),
+                false => Ok(()),
+            }
+        }
+
+        fn assign(&mut self, name: &str, value: String) -> Result<(), ExpansionError> {
+            self.variables.insert(name.to_string(), value);
+            Ok(())
+        }
+
+        fn unset_error(&mut self, message: String) -> ExpansionError {
+            ExpansionError { message }
+        }
+
+        fn command_substitution(&mut self, command: &str) -> Result<String, ExpansionError> {
+            // enough of `echo` to check how the output is expanded
+            let words = expand_word(command.strip_prefix("echo ").unwrap_or(""), self)?;
+            Ok(words.join(" "))
+        }
+    }
+
+    fn env() -> TestEnvironment {
+        TestEnvironment {
+            variables: HashMap::from([
+                ("x".to_string(), "hello world".to_string()),
+                ("empty".to_string(), "".to_string()),
+            ]),
+            positional: vec!["a b".to_string(), "c".to_string()],
+            nounset: false,
+        }
+    }
+
+    fn test(word: &str, expected: &[&str]) {
+        let fields = expand_word(word, &mut env()).unwrap();
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn test_quote_removal() {
+        test("'a b'\"c d\"e\\ f", &["a bc de f"]);
+        test("$'a\\tb'\"$'x'\"", &["a\tb$'x'"]);
+        test("\"\"", &[""]);
+        test("''", &[""]);
+    }
+
+    #[test]
+    fn test_unquoted_variable_is_split() {
+        test("$x", &["hello", "world"]);
+        test("pre$x", &["prehello", "world"]);
+        test("${x}post", &["hello", "worldpost"]);
+        test("$empty", &[]);
+        test("$unset", &[]);
+    }
+
+    #[test]
+    fn test_quoted_variable_is_not_split() {
+        test("\"$x\"", &["hello world"]);
+        test("'$x'", &["$x"]);
+        test("\"$empty\"", &[""]);
+        test("\\$x", &["$x"]);
+    }
+
+    #[test]
+    fn test_positional_parameters() {
+        test("$1", &["a", "b"]);
+        test("\"$1\"", &["a b"]);
+        test("$#", &["2"]);
+        test("\"$@\"", &["a b", "c"]);
+        test("\"x$@y\"", &["xa b", "cy"]);
+        test("$@", &["a", "b", "c"]);
+        test("\"$*\"", &["a b c"]);
+
+        let mut env = env();
+        env.positional.clear();
+        assert_eq!(
+            expand_word("\"$@\"", &mut env).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(expand_word("\"x$@\"", &mut env).unwrap(), vec!["x"]);
+    }
+
+    #[test]
+    fn test_lone_dollar() {
+        test("$", &["$"]);
+        test("a$ b", &["a$ b"]);
+    }
+
+    #[test]
+    fn test_expand_to_string() {
+        assert_eq!(
+            expand_word_to_string("$x", &mut env()).unwrap(),
+            "hello world"
+        );
+        assert_eq!(expand_word_to_string("$@", &mut env()).unwrap(), "a b c");
+    }
+
+    #[test]
+    fn test_bad_substitution() {
+        assert!(expand_word("${a b}", &mut env()).is_err());
+    }
+
+    #[test]
+    fn test_here_document() {
+        assert_eq!(
+            expand_here_document("\"$x\" '$1' \\$x \\a\\\n$#\n", &mut env()).unwrap(),
+            "\"hello world\" 'a b' $x \\a2\n"
+        );
+    }
+
+    #[test]
+    fn test_pattern() {
+        assert_eq!(
+            expand_pattern("*.'*'\\?\"[\"", &mut env()).unwrap(),
+            "*.\\*\\?\\["
+        );
+        assert_eq!(expand_pattern("$x*", &mut env()).unwrap(), "hello world*");
+        assert_eq!(
+            expand_regex("^a.'b.'\"(\"", &mut env()).unwrap(),
+            "^a.b\\.\\("
+        );
+        assert_eq!(expand_regex("a$ \"$\"", &mut env()).unwrap(), "a$ \\$");
+    }
+
+    #[test]
+    fn test_array_elements() {
+        test("${array[0]}", &["a", "b"]);
+        test("\"${array[0]}\"", &["a b"]);
+        test("${array[1]}", &[]);
+        test("${array[-1]}", &["c"]);
+        test("${array[$#]}", &["c"]);
+        test("${x[0]}", &["hello", "world"]);
+        test("\"${array[@]}\"", &["a b", "c"]);
+        test("\"${array[*]}\"", &["a b c"]);
+        test("\"${none[@]}\"", &[]);
+        assert!(expand_word("${array[-5]}", &mut env()).is_err());
+    }
+
+    #[test]
+    fn test_unset_parameters() {
+        let mut env = env();
+        env.nounset = true;
+        let error =
+            |word: &str, env: &mut TestEnvironment| expand_word(word, env).unwrap_err().message;
+        assert_eq!(error("$unset", &mut env), "unset: unbound variable");
+        assert_eq!(error("${#unset}", &mut env), "unset: unbound variable");
+        assert_eq!(error("$3", &mut env), "$3: unbound variable");
+        assert_eq!(error("${array[1]}", &mut env), "array[1]: unbound variable");
+        assert_eq!(expand_word("\"$@\"", &mut env).unwrap(), vec!["a b", "c"]);
+        assert_eq!(
+            expand_word("${none[@]}", &mut env).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_lengths_keys_and_slices() {
+        test("${#array[@]}", &["2"]);
+        test("${#array[0]}", &["3"]);
+        test("${#x}", &["11"]);
+        test("${#@}", &["2"]);
+        test("${!array[@]}", &["0", "2"]);
+        test("\"${array[@]:1}\"", &["c"]);
+        test("\"${array[@]:0:1}\"", &["a b"]);
+        test("\"${@:1:1}\"", &["a b"]);
+        test("\"${@:0:2}\"", &["rushell", "a b"]);
+        test("${x:6}", &["world"]);
+        test("${x:1:3}", &["ell"]);
+        test("${x: -5:2}", &["wo"]);
+        test("${x:1:9223372036854775807}", &["ello", "world"]);
+        test("${x: -9223372036854775808:2}", &[]);
+        test("\"${array[@]:9223372036854775807:5}\"", &[]);
+        test("\"${array[@]:1:9223372036854775807}\"", &["c"]);
+        test("\"${array[@]: -9223372036854775807}\"", &["a b", "c"]);
+    }
+
+    #[test]
+    fn test_default_values() {
+        test("${unset:-a b}", &["a", "b"]);
+        test("\"${unset:-a b}\"", &["a b"]);
+        test("${unset:-'a b'}", &["a b"]);
+        test("${empty:-def}", &["def"]);
+        test("${empty-def}", &[]);
+        test("${x:-def}", &["hello", "world"]);
+        test("${unset:-$x}", &["hello", "world"]);
+        test("${x:+alt}", &["alt"]);
+        test("${empty+alt}", &["alt"]);
+        test("${empty:+alt}", &[]);
+        test("\"${array[@]:-none}\"", &["a b", "c"]);
+        test("${none[@]:-none}", &["none"]);
+        test("${x:1:3}", &["ell"]);
+
+        let mut env = env();
+        env.nounset = true;
+        assert_eq!(
+            expand_word("${unset-}", &mut env).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            expand_word("${unset:=v}$unset", &mut env).unwrap(),
+            vec!["vv"]
+        );
+        let error = expand_word("${empty:?}", &mut env).unwrap_err();
+        assert_eq!(error.message, "empty: parameter null or not set");
+        let error = expand_word("${unset2?no $x}", &mut env).unwrap_err();
+        assert_eq!(error.message, "unset2: no hello world");
+        let error = expand_word("${3:=v}", &mut env).unwrap_err();
+        assert_eq!(error.message, "$3: cannot assign in this way");
+        let error = expand_word("${#x:-def}", &mut env).unwrap_err();
+        assert_eq!(error.message, "${#x:-def}: bad substitution");
+    }
+
+    #[test]
+    fn test_command_substitution() {
+        assert_eq!(
+            expand_word("a$(echo $x)b", &mut env()).unwrap(),
+            vec!["ahello", "worldb"]
+        );
+        assert_eq!(
+            expand_word("\"$(echo $x)\"", &mut env()).unwrap(),
+            vec!["hello world"]
+        );
+        assert_eq!(
+            expand_word("`echo \\$x`", &mut env()).unwrap(),
+            vec!["hello", "world"]
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use crate::jobs::Jobs;
+
+pub struct FgCommand;
+
+impl FgCommand {
+    pub fn execute(args: &[String], jobs: &mut Jobs) -> i32 {
+        if !jobs.is_enabled() {
+            eprintln!("fg: no job control");
+            return 1;
+        }
+
+        let spec = args.get(1).map(String::as_str);
+        let job = match jobs.find(spec).and_then(|id| jobs.take(id)) {
+            Some(job) => job,
+            None => {
+                eprintln!("fg: {}: no such job", spec.unwrap_or("current"));
+                return 1;
+            }
+        };
+
+        println!("{}", job.command);
+        jobs.foreground(job, true)
+    }
+}
@@ -0,0 +1,602 @@
+use std::io::{stdout, Write};
+use std::mem::MaybeUninit;
+
+use libc::{c_int, pid_t, termios};
+
+use crate::signals;
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Job-Control
+pub struct Jobs {
+    /// job control is only enabled for interactive shells attached to a terminal
+    enabled: bool,
+    shell_pgid: pid_t,
+    shell_tmodes: Option<termios>,
+    jobs: Vec<Job>,
+    /// incremented whenever a job is stopped or put in the background, used to
+    /// find the current (`%+`) and previous (`%-`) jobs
+    sequence: u64,
+    /// statuses of processes that were reaped while waiting for another job,
+    /// before their own job was created. Only kept until the next job is added,
+    /// as the shell builds one pipeline at a time.
+    unclaimed: Vec<(pid_t, c_int)>,
+}
+
+pub struct Job {
+    pub id: usize,
+    pub pgid: pid_t,
+    pub processes: Vec<Process>,
+    pub command: String,
+    /// the status is that of the last command that failed, with
+    /// `set -o pipefail`
+    pub pipefail: bool,
+    /// terminal modes saved when the job was stopped
+    tmodes: Option<termios>,
+    notified: bool,
+    sequence: u64,
+}
+
+pub struct Process {
+    pub pid: pid_t,
+    pub state: ProcessState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProcessState {
+    Running,
+    Stopped(c_int),
+    Exited(i32),
+    Signaled(c_int),
+}
+
+impl Job {
+    pub fn new(pgid: pid_t, pids: Vec<pid_t>, command: String) -> Self {
+        Job {
+            id: 0,
+            pgid,
+            processes: pids
+                .into_iter()
+                .map(|pid| Process {
+                    pid,
+                    state: ProcessState::Running,
+                })
+                .collect(),
+            command,
+            pipefail: false,
+            tmodes: None,
+            notified: false,
+            sequence: 0,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.processes
+            .iter()
+            .all(|p| p.state != ProcessState::Running)
+            && !self.is_completed()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.processes
+            .iter()
+            .all(|p| matches!(p.state, ProcessState::Exited(_) | ProcessState::Signaled(_)))
+    }
+
+    /// exit status of a pipeline is the exit status of its last command
+    pub fn status(&self) -> i32 {
+        let mut statuses = self.processes.iter().map(|p| match p.state {
+            ProcessState::Exited(code) => code,
+            ProcessState::Signaled(signal) | ProcessState::Stopped(signal) => 128 + signal,
+            ProcessState::Running => 0,
+        });
+        match self.pipefail {
+            true => statuses.rfind(|&status| status != 0).unwrap_or(0),
+            false => statuses.next_back().unwrap_or(0),
+        }
+    }
+
+    fn state_description(&self) -> String {
+        if self.is_completed() {
+            match self.status() {
+                0 => "Done".to_string(),
+                code if code > 128 => signal_description(code - 128),
+                code => format!("Exit {}", code),
+            }
+        } else if self.is_stopped() {
+            "Stopped".to_string()
+        } else {
+            "Running".to_string()
+        }
+    }
+
+    fn mark_process_status(&mut self, pid: pid_t, status: c_int) -> bool {
+        if let Some(process) = self.processes.iter_mut().find(|p| p.pid == pid) {
+            process.state = if libc::WIFSTOPPED(status) {
+                ProcessState::Stopped(libc::WSTOPSIG(status))
+            } else if libc::WIFCONTINUED(status) {
+                ProcessState::Running
+            } else if libc::WIFSIGNALED(status) {
+                ProcessState::Signaled(libc::WTERMSIG(status))
+            } else {
+                ProcessState::Exited(libc::WEXITSTATUS(status))
+            };
+            self.notified = false;
+            return true;
+        }
+        false
+    }
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Jobs {
+            enabled: false,
+            shell_pgid: unsafe { libc::getpgrp() },
+            shell_tmodes: None,
+            jobs: Vec::new(),
+            sequence: 0,
+            unclaimed: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Put the shell in its own process group and take control of the terminal.
+    /// https://www.gnu.org/software/libc/manual/html_node/Initializing-the-Shell.html
+    pub fn init(&mut self) {
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0 {
+                return;
+            }
+
+            // loop until we are in the foreground
+            loop {
+                self.shell_pgid = libc::getpgrp();
+                if libc::tcgetpgrp(libc::STDIN_FILENO) == self.shell_pgid {
+                    break;
+                }
+                libc::kill(-self.shell_pgid, libc::SIGTTIN);
+            }
+
+            signals::init_job_control();
+
+            let pid = libc::getpid();
+            if pid != self.shell_pgid && libc::setpgid(pid, pid) < 0 {
+                eprintln!("rushell: couldn't put the shell in its own process group");
+                return;
+            }
+            self.shell_pgid = pid;
+            libc::tcsetpgrp(libc::STDIN_FILENO, self.shell_pgid);
+            self.shell_tmodes = get_tmodes();
+        }
+        self.enabled = true;
+    }
+
+    /// Called in a forked child before it runs its command. `pgid` is 0 for the
+    /// first process of a pipeline, which becomes the process group leader.
+    pub fn setup_child(&self, pgid: pid_t, foreground: bool) {
+        unsafe {
+            if self.enabled {
+                let pid = libc::getpid();
+                let pgid = if pgid == 0 { pid } else { pgid };
+                libc::setpgid(pid, pgid);
+                if foreground {
+                    libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+                }
+            }
+        }
+    }
+
+    /// Called in the parent after forking, to avoid racing with the child
+    pub fn setup_parent(&self, pid: pid_t, pgid: pid_t) {
+        if self.enabled {
+            unsafe {
+                libc::setpgid(pid, pgid);
+            }
+        }
+    }
+
+    /// Give the terminal to `job` and wait until it completes or is stopped.
+    /// Returns the exit status of the job.
+    pub fn foreground(&mut self, mut job: Job, resume: bool) -> i32 {
+        self.claim(&mut job);
+        if self.enabled {
+            unsafe {
+                libc::tcsetpgrp(libc::STDIN_FILENO, job.pgid);
+            }
+            if resume {
+                if let Some(tmodes) = &job.tmodes {
+                    set_tmodes(tmodes);
+                }
+            }
+        }
+        if resume {
+            unsafe {
+                libc::kill(-job.pgid, libc::SIGCONT);
+            }
+            for process in job.processes.iter_mut() {
+                if let ProcessState::Stopped(_) = process.state {
+                    process.state = ProcessState::Running;
+                }
+            }
+        }
+
+        self.wait_for_job(&mut job);
+
+        if self.enabled {
+            unsafe {
+                libc::tcsetpgrp(libc::STDIN_FILENO, self.shell_pgid);
+            }
+            job.tmodes = get_tmodes();
+            if let Some(tmodes) = &self.shell_tmodes {
+                set_tmodes(tmodes);
+            }
+        }
+
+        if self.enabled {
+            // the foreground job already received any Ctrl-C or Ctrl-\ meant for it
+            signals::take(libc::SIGINT);
+            signals::take(libc::SIGQUIT);
+        }
+        if let Some(ProcessState::Signaled(signal)) = job.processes.last().map(|p| p.state) {
+            match signal {
+                libc::SIGINT => println!(),
+                libc::SIGPIPE => {}
+                _ => eprintln!("{}", signal_description(signal)),
+            }
+        }
+
+        let status = job.status();
+        if job.is_stopped() {
+            let id = self.add(job);
+            println!();
+            self.print_job(id);
+        }
+        status
+    }
+
+    /// Add a job started with `&` to the job table and report it. Returns the
+    /// process id of its last process.
+    pub fn background(&mut self, mut job: Job) -> pid_t {
+        self.claim(&mut job);
+        let last_pid = job.processes.last().map(|p| p.pid).unwrap_or(job.pgid);
+        let id = self.add(job);
+        if self.enabled {
+            println!("[{}] {}", id, last_pid);
+        }
+        last_pid
+    }
+
+    /// Continue a stopped job in the background
+    pub fn resume_background(&mut self, id: usize) {
+        self.sequence += 1;
+        let sequence = self.sequence;
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            for process in job.processes.iter_mut() {
+                if let ProcessState::Stopped(_) = process.state {
+                    process.state = ProcessState::Running;
+                }
+            }
+            job.sequence = sequence;
+            unsafe {
+                libc::kill(-job.pgid, libc::SIGCONT);
+            }
+        }
+        if let Some(job) = self.get(id) {
+            println!("[{}]{} {} &", job.id, self.mark(id), job.command);
+        }
+    }
+
+    /// Remove a job from the table, so that it can be put in the foreground
+    pub fn take(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(index))
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    /// Resolve a job spec like `%1`, `%+`, `%-`, `%sleep` or `%?notes` to a
+    /// job id. `None` refers to the current job.
+    pub fn find(&self, spec: Option<&str>) -> Option<usize> {
+        let spec = spec.unwrap_or("%+");
+        let spec = spec.strip_prefix('%').unwrap_or(spec);
+        match spec {
+            "" | "+" | "%" => self.current(),
+            "-" => self.previous().or_else(|| self.current()),
+            _ => {
+                if let Ok(id) = spec.parse::<usize>() {
+                    return self.get(id).map(|job| job.id);
+                }
+                if let Some(text) = spec.strip_prefix('?') {
+                    return self
+                        .jobs
+                        .iter()
+                        .find(|job| job.command.contains(text))
+                        .map(|job| job.id);
+                }
+                self.jobs
+                    .iter()
+                    .find(|job| job.command.starts_with(spec))
+                    .map(|job| job.id)
+            }
+        }
+    }
+
+    pub fn has_stopped(&self) -> bool {
+        self.jobs.iter().any(|job| job.is_stopped())
+    }
+
+    pub fn ids(&self) -> Vec<usize> {
+        self.jobs.iter().map(|job| job.id).collect()
+    }
+
+    pub fn print_job(&self, id: usize) {
+        if let Some(job) = self.get(id) {
+            println!(
+                "[{}]{}  {:<24}{}",
+                job.id,
+                self.mark(id),
+                job.state_description(),
+                job.command
+            );
+        }
+    }
+
+    /// Reap children that changed state without blocking
+    pub fn update_status(&mut self) {
+        loop {
+            let mut status: c_int = 0;
+            let pid = unsafe {
+                libc::waitpid(
+                    -1,
+                    &mut status,
+                    libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED,
+                )
+            };
+            if pid <= 0 {
+                break;
+            }
+            self.mark_process_status(pid, status);
+        }
+    }
+
+    /// Report jobs that completed or stopped since the last notification, and
+    /// remove completed jobs from the table
+    pub fn notify(&mut self) {
+        self.update_status();
+        self.report();
+    }
+
+    /// `notify` for the statuses already reaped
+    fn report(&mut self) {
+        for id in self.ids() {
+            let job = self.get(id).unwrap();
+            if job.notified {
+                continue;
+            }
+            if job.is_completed() || job.is_stopped() {
+                if self.enabled {
+                    self.print_job(id);
+                }
+                if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+                    job.notified = true;
+                }
+            }
+        }
+        self.jobs.retain(|job| !job.is_completed());
+        stdout().flush().unwrap();
+    }
+
+    /// Wait for a child that is not part of a job, like a command
+    /// substitution, and return its exit status
+    pub fn wait_for_process(&mut self, pid: pid_t) -> i32 {
+        let mut job = Job::new(pid, vec![pid], String::new());
+        self.wait_for_job(&mut job);
+        job.status()
+    }
+
+    fn wait_for_job(&mut self, job: &mut Job) {
+        self.unclaimed
+            .retain(|&(pid, status)| !job.mark_process_status(pid, status));
+        while !job.is_stopped() && !job.is_completed() {
+            let mut status: c_int = 0;
+            let pid = unsafe { libc::waitpid(-1, &mut status, libc::WUNTRACED) };
+            if pid < 0 {
+                if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                    self.forward_signals(job);
+                    continue;
+                }
+                // no children left to wait for
+                break;
+            }
+            if !job.mark_process_status(pid, status) {
+                self.mark_process_status(pid, status);
+            }
+        }
+    }
+
+    /// Signals sent to the shell while it waits are meant for the foreground job.
+    /// Without job control the job shares the shell's process group and has
+    /// received them already.
+    fn forward_signals(&self, job: &Job) {
+        if !self.enabled {
+            return;
+        }
+        for signal in [libc::SIGINT, libc::SIGQUIT] {
+            if signals::take(signal) {
+                unsafe {
+                    libc::kill(-job.pgid, signal);
+                }
+            }
+        }
+    }
+
+    /// Give `job` the statuses of its processes that were reaped before it was
+    /// created, and forget the others, which belong to no job
+    fn claim(&mut self, job: &mut Job) {
+        for (pid, status) in self.unclaimed.drain(..) {
+            job.mark_process_status(pid, status);
+        }
+    }
+
+    fn mark_process_status(&mut self, pid: pid_t, status: c_int) {
+        for job in self.jobs.iter_mut() {
+            if job.mark_process_status(pid, status) {
+                return;
+            }
+        }
+        self.unclaimed.push((pid, status));
+    }
+
+    fn add(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+        self.sequence += 1;
+        job.sequence = self.sequence;
+        job.notified = true;
+        let id = job.id;
+        let index = self
+            .jobs
+            .iter()
+            .position(|other| other.id > id)
+            .unwrap_or(self.jobs.len());
+        self.jobs.insert(index, job);
+        id
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.jobs
+            .iter()
+            .max_by_key(|job| job.sequence)
+            .map(|job| job.id)
+    }
+
+    fn previous(&self) -> Option<usize> {
+        let current = self.current()?;
+        self.jobs
+            .iter()
+            .filter(|job| job.id != current)
+            .max_by_key(|job| job.sequence)
+            .map(|job| job.id)
+    }
+
+    fn mark(&self, id: usize) -> char {
+        if self.current() == Some(id) {
+            '+'
+        } else if self.previous() == Some(id) {
+            '-'
+        } else {
+            ' '
+        }
+    }
+}
+
+fn get_tmodes() -> Option<termios> {
+    let mut tmodes = MaybeUninit::<termios>::uninit();
+    unsafe {
+        if libc::tcgetattr(libc::STDIN_FILENO, tmodes.as_mut_ptr()) == 0 {
+            Some(tmodes.assume_init())
+        } else {
+            None
+        }
+    }
+}
+
+fn set_tmodes(tmodes: &termios) {
+    unsafe {
+        libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, tmodes);
+    }
+}
+
+pub fn signal_description(signal: c_int) -> String {
+    match signal {
+        libc::SIGHUP => "Hangup",
+        libc::SIGINT => "Interrupt",
+        libc::SIGQUIT => "Quit",
+        libc::SIGILL => "Illegal instruction",
+        libc::SIGABRT => "Aborted",
+        libc::SIGFPE => "Floating point exception",
+        libc::SIGKILL => "Killed",
+        libc::SIGSEGV => "Segmentation fault",
+        libc::SIGPIPE => "Broken pipe",
+        libc::SIGALRM => "Alarm clock",
+        libc::SIGTERM => "Terminated",
+        libc::SIGSTOP | libc::SIGTSTP => "Stopped",
+        _ => return format!("Signal {}", signal),
+    }
+    .to_string()
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{Job, Jobs, ProcessState};
+
+    fn jobs(commands: &[&str]) -> Jobs {
+        let mut jobs = Jobs::new();
+        for (pid, command) in (1000..).zip(commands) {
+            jobs.add(Job::new(pid, vec![pid], command.to_string()));
+        }
+        jobs
+    }
+
+    #[test]
+    fn test_find() {
+        let jobs = jobs(&["sleep 10", "vim notes", "sleep 20"]);
+        assert_eq!(jobs.find(Some("%1")), Some(1));
+        assert_eq!(jobs.find(Some("2")), Some(2));
+        assert_eq!(jobs.find(Some("%4")), None);
+        assert_eq!(jobs.find(Some("%%")), Some(3));
+        assert_eq!(jobs.find(Some("%+")), Some(3));
+        assert_eq!(jobs.find(None), Some(3));
+        assert_eq!(jobs.find(Some("%-")), Some(2));
+        assert_eq!(jobs.find(Some("%sleep")), Some(1));
+        assert_eq!(jobs.find(Some("%vim")), Some(2));
+        assert_eq!(jobs.find(Some("%notes")), None);
+        assert_eq!(jobs.find(Some("%?notes")), Some(2));
+        assert_eq!(jobs.find(Some("%?20")), Some(3));
+        assert_eq!(jobs.find(Some("%?nope")), None);
+    }
+
+    #[test]
+    fn test_previous_job_without_others() {
+        let jobs = jobs(&["sleep 10"]);
+        assert_eq!(jobs.find(Some("%-")), Some(1));
+        assert_eq!(Jobs::new().find(None), None);
+    }
+
+    #[test]
+    fn test_remove_finished_jobs() {
+        let mut jobs = jobs(&["sleep 10", "false", "sleep 20"]);
+        // `false` exits with status 1
+        jobs.mark_process_status(1001, 1 << 8);
+        assert!(jobs.get(2).unwrap().is_completed());
+        assert_eq!(jobs.get(2).unwrap().status(), 1);
+        jobs.report();
+        assert_eq!(jobs.ids(), [1, 3]);
+        // new jobs take the number after the highest one left
+        jobs.add(Job::new(1003, vec![1003], "cat".to_string()));
+        assert_eq!(jobs.ids(), [1, 3, 4]);
+        assert_eq!(jobs.find(Some("%-")), Some(3));
+    }
+
+    #[test]
+    fn test_unclaimed_statuses() {
+        let mut jobs = jobs(&["sleep 10"]);
+        // the first process of a pipeline exited before the pipeline was
+        // complete, and a process that is not part of any job
+        jobs.mark_process_status(2000, 0);
+        jobs.mark_process_status(3000, 0);
+        assert_eq!(jobs.unclaimed.len(), 2);
+        jobs.background(Job::new(2000, vec![2000, 2001], "a | b".to_string()));
+        assert!(jobs.unclaimed.is_empty());
+        let job = jobs.get(2).unwrap();
+        assert_eq!(job.processes[0].state, ProcessState::Exited(0));
+        assert_eq!(job.processes[1].state, ProcessState::Running);
+    }
+}
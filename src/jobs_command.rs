@@ -0,0 +1,27 @@
+use crate::jobs::Jobs;
+
+pub struct JobsCommand;
+
+impl JobsCommand {
+    pub fn execute(args: &[String], jobs: &mut Jobs) -> i32 {
+        jobs.update_status();
+        if args.len() == 1 {
+            for id in jobs.ids() {
+                jobs.print_job(id);
+            }
+            return 0;
+        }
+
+        let mut return_code: i32 = 0;
+        for spec in args.iter().skip(1) {
+            match jobs.find(Some(spec)) {
+                Some(id) => jobs.print_job(id),
+                None => {
+                    eprintln!("jobs: {}: no such job", spec);
+                    return_code = 1;
+                }
+            }
+        }
+        return_code
+    }
+}
@@ -0,0 +1,531 @@
+use std::io::{self, ErrorKind};
+
+use libc::{c_int, termios};
+use unicode_width::UnicodeWidthChar;
+
+use crate::line_reader::{read_byte, LineReader};
+use crate::prompt::{END_IGNORE, START_IGNORE};
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Command-Line-Editing
+///
+/// Reads lines from a terminal in raw mode, with the emacs key bindings of
+/// readline for moving the cursor and killing and yanking text
+pub struct LineEditor {
+    fd: c_int,
+    /// the text removed by the last kill commands, inserted again by Ctrl-Y
+    kill_buffer: String,
+}
+
+/// What a key does
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Insert(char),
+    Enter,
+    Interrupt,
+    /// Ctrl-D: end of input on an empty line, otherwise like Delete
+    EndOfInput,
+    Start,
+    End,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Backspace,
+    Delete,
+    KillToEnd,
+    KillToStart,
+    KillWordBefore,
+    KillWordAfter,
+    /// Ctrl-W, which kills up to the previous blank
+    KillBigWordBefore,
+    Yank,
+    Transpose,
+    ClearScreen,
+    Ignore,
+}
+
+impl Key {
+    fn is_kill(self) -> bool {
+        matches!(
+            self,
+            Key::KillToEnd
+                | Key::KillToStart
+                | Key::KillWordBefore
+                | Key::KillWordAfter
+                | Key::KillBigWordBefore
+        )
+    }
+}
+
+/// The text being edited and the position of the cursor in it
+#[derive(Debug, Default)]
+struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Line {
+    fn insert(&mut self, text: &str) {
+        for c in text.chars() {
+            self.chars.insert(self.cursor, c);
+            self.cursor += 1;
+        }
+    }
+
+    /// Removes the characters between the cursor and `position`, leaving the
+    /// cursor at the start of the removed range. Returns them.
+    fn remove_to(&mut self, position: usize) -> String {
+        let (start, end) = match position < self.cursor {
+            true => (position, self.cursor),
+            false => (self.cursor, position),
+        };
+        self.cursor = start;
+        self.chars.drain(start..end).collect()
+    }
+
+    /// The start of the word before the cursor, words being made of letters
+    /// and digits
+    fn word_start(&self) -> usize {
+        let mut position = self.cursor;
+        while position > 0 && !self.chars[position - 1].is_alphanumeric() {
+            position -= 1;
+        }
+        while position > 0 && self.chars[position - 1].is_alphanumeric() {
+            position -= 1;
+        }
+        position
+    }
+
+    /// The end of the word after the cursor
+    fn word_end(&self) -> usize {
+        let mut position = self.cursor;
+        while position < self.chars.len() && !self.chars[position].is_alphanumeric() {
+            position += 1;
+        }
+        while position < self.chars.len() && self.chars[position].is_alphanumeric() {
+            position += 1;
+        }
+        position
+    }
+
+    /// The start of the blank separated word before the cursor
+    fn big_word_start(&self) -> usize {
+        let mut position = self.cursor;
+        while position > 0 && self.chars[position - 1].is_whitespace() {
+            position -= 1;
+        }
+        while position > 0 && !self.chars[position - 1].is_whitespace() {
+            position -= 1;
+        }
+        position
+    }
+
+    /// Ctrl-T swaps the characters around the cursor, or the last two at the
+    /// end of the line
+    fn transpose(&mut self) {
+        if self.chars.len() < 2 || self.cursor == 0 {
+            return;
+        }
+        if self.cursor == self.chars.len() {
+            self.cursor -= 1;
+        }
+        self.chars.swap(self.cursor - 1, self.cursor);
+        self.cursor += 1;
+    }
+}
+
+/// Puts the terminal back in the mode it was in when dropped
+struct RawMode {
+    fd: c_int,
+    saved: termios,
+}
+
+impl RawMode {
+    /// Turns off line buffering, echo and the keys that send signals, which
+    /// the editor handles itself. `None` if `fd` is not a terminal.
+    fn enable(fd: c_int) -> Option<RawMode> {
+        unsafe {
+            let mut saved: termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut saved) < 0 {
+                return None;
+            }
+            let mut raw = saved;
+            raw.c_iflag &= !(libc::ICRNL | libc::INLCR | libc::IXON | libc::ISTRIP);
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(fd, libc::TCSADRAIN, &raw) < 0 {
+                return None;
+            }
+            Some(RawMode { fd, saved })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSADRAIN, &self.saved);
+        }
+    }
+}
+
+impl LineEditor {
+    pub fn new(fd: c_int) -> Self {
+        LineEditor {
+            fd,
+            kill_buffer: String::new(),
+        }
+    }
+
+    /// Shows `prompt` and appends the line typed after it to `buf`, newline
+    /// included. Returns the number of bytes read, 0 for Ctrl-D on an empty
+    /// line. Ctrl-C discards the line and returns an `Interrupted` error.
+    pub fn read_line(&mut self, prompt: &str, buf: &mut String) -> io::Result<usize> {
+        let Some(_raw_mode) = RawMode::enable(self.fd) else {
+            write(&prompt.replace([START_IGNORE, END_IGNORE], ""));
+            return LineReader::read_line_from_fd(self.fd, buf);
+        };
+
+        let mut screen = Screen::new(prompt);
+        let mut line = Line::default();
+        let mut last_key = Key::Ignore;
+        screen.start();
+        loop {
+            let Some(key) = self.read_key()? else {
+                // the terminal went away
+                return Ok(0);
+            };
+            match key {
+                Key::Insert(c) => line.insert(&c.to_string()),
+                Key::Enter => {
+                    line.cursor = line.chars.len();
+                    screen.refresh(&line);
+                    write("\r\n");
+                    let text: String = line.chars.iter().chain(['\n'].iter()).collect();
+                    buf.push_str(&text);
+                    return Ok(text.len());
+                }
+                Key::Interrupt => {
+                    line.cursor = line.chars.len();
+                    screen.refresh(&line);
+                    write("^C");
+                    return Err(io::Error::from(ErrorKind::Interrupted));
+                }
+                Key::EndOfInput if line.chars.is_empty() => return Ok(0),
+                Key::EndOfInput | Key::Delete => {
+                    if line.cursor < line.chars.len() {
+                        line.chars.remove(line.cursor);
+                    }
+                }
+                Key::Start => line.cursor = 0,
+                Key::End => line.cursor = line.chars.len(),
+                Key::Left => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right => line.cursor = (line.cursor + 1).min(line.chars.len()),
+                Key::WordLeft => line.cursor = line.word_start(),
+                Key::WordRight => line.cursor = line.word_end(),
+                Key::Backspace => {
+                    if line.cursor > 0 {
+                        line.cursor -= 1;
+                        line.chars.remove(line.cursor);
+                    }
+                }
+                Key::KillToEnd => {
+                    let killed = line.remove_to(line.chars.len());
+                    self.kill(killed, last_key.is_kill(), false);
+                }
+                Key::KillToStart => {
+                    let killed = line.remove_to(0);
+                    self.kill(killed, last_key.is_kill(), true);
+                }
+                Key::KillWordBefore => {
+                    let killed = line.remove_to(line.word_start());
+                    self.kill(killed, last_key.is_kill(), true);
+                }
+                Key::KillWordAfter => {
+                    let killed = line.remove_to(line.word_end());
+                    self.kill(killed, last_key.is_kill(), false);
+                }
+                Key::KillBigWordBefore => {
+                    let killed = line.remove_to(line.big_word_start());
+                    self.kill(killed, last_key.is_kill(), true);
+                }
+                Key::Yank => line.insert(&self.kill_buffer),
+                Key::Transpose => line.transpose(),
+                Key::ClearScreen => {
+                    write("\x1b[H\x1b[2J");
+                    screen.start();
+                }
+                Key::Ignore => {}
+            }
+            last_key = key;
+            screen.refresh(&line);
+        }
+    }
+
+    /// Consecutive kills add up, so that they are yanked together
+    fn kill(&mut self, text: String, append: bool, backward: bool) {
+        match (append, backward) {
+            (false, _) => self.kill_buffer = text,
+            (true, false) => self.kill_buffer.push_str(&text),
+            (true, true) => self.kill_buffer.insert_str(0, &text),
+        }
+    }
+
+    /// Reads the bytes of one key, `None` at the end of input
+    fn read_key(&self) -> io::Result<Option<Key>> {
+        let Some(byte) = read_byte(self.fd)? else {
+            return Ok(None);
+        };
+        let key = match byte {
+            0x01 => Key::Start,
+            0x02 => Key::Left,
+            0x03 => Key::Interrupt,
+            0x04 => Key::EndOfInput,
+            0x05 => Key::End,
+            0x06 => Key::Right,
+            0x08 | 0x7f => Key::Backspace,
+            0x0b => Key::KillToEnd,
+            0x0c => Key::ClearScreen,
+            b'\r' | b'\n' => Key::Enter,
+            0x14 => Key::Transpose,
+            0x15 => Key::KillToStart,
+            0x17 => Key::KillBigWordBefore,
+            0x19 => Key::Yank,
+            0x1b => self.read_escape_sequence()?,
+            byte if byte < 0x20 => Key::Ignore,
+            byte => self.read_char(byte)?.map_or(Key::Ignore, Key::Insert),
+        };
+        Ok(Some(key))
+    }
+
+    /// Decodes a UTF-8 character starting with `first`
+    fn read_char(&self, first: u8) -> io::Result<Option<char>> {
+        let length = match first {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Ok(None),
+        };
+        let mut bytes = vec![first];
+        while bytes.len() < length {
+            match read_byte(self.fd)? {
+                Some(byte) => bytes.push(byte),
+                None => return Ok(None),
+            }
+        }
+        Ok(std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.chars().next()))
+    }
+
+    /// The keys sending `ESC` and more bytes: Alt and a letter, the arrows
+    /// and the editing keys
+    fn read_escape_sequence(&self) -> io::Result<Key> {
+        let key = match read_byte(self.fd)? {
+            Some(b'b' | b'B') => Key::WordLeft,
+            Some(b'f' | b'F') => Key::WordRight,
+            Some(b'd' | b'D') => Key::KillWordAfter,
+            Some(0x08 | 0x7f) => Key::KillWordBefore,
+            Some(b'O') => match read_byte(self.fd)? {
+                Some(b'C') => Key::Right,
+                Some(b'D') => Key::Left,
+                Some(b'H') => Key::Start,
+                Some(b'F') => Key::End,
+                _ => Key::Ignore,
+            },
+            Some(b'[') => {
+                // parameters, then a final byte between `@` and `~`
+                let mut sequence = String::new();
+                while let Some(byte) = read_byte(self.fd)? {
+                    sequence.push(byte as char);
+                    if (0x40..=0x7e).contains(&byte) {
+                        break;
+                    }
+                }
+                match sequence.as_str() {
+                    "C" => Key::Right,
+                    "D" => Key::Left,
+                    "H" | "1~" | "7~" => Key::Start,
+                    "F" | "4~" | "8~" => Key::End,
+                    "3~" => Key::Delete,
+                    // with Ctrl or Alt
+                    "1;5C" | "1;3C" => Key::WordRight,
+                    "1;5D" | "1;3D" => Key::WordLeft,
+                    _ => Key::Ignore,
+                }
+            }
+            _ => Key::Ignore,
+        };
+        Ok(key)
+    }
+}
+
+/// Where the prompt and the line are on the screen, so that the line can be
+/// drawn again after each key
+struct Screen<'a> {
+    prompt: &'a str,
+    /// row of the cursor, counted from the row where the last line of the
+    /// prompt starts
+    cursor_row: usize,
+}
+
+impl<'a> Screen<'a> {
+    fn new(prompt: &'a str) -> Self {
+        Screen {
+            prompt,
+            cursor_row: 0,
+        }
+    }
+
+    /// Prints the whole prompt, which may span several lines
+    fn start(&mut self) {
+        write(&self.prompt.replace([START_IGNORE, END_IGNORE], ""));
+        let (row, _) = self.prompt_end(columns());
+        self.cursor_row = row;
+    }
+
+    /// Draws the last line of the prompt and `line` again, then puts the
+    /// cursor where it belongs
+    fn refresh(&mut self, line: &Line) {
+        let columns = columns();
+        let last_line = self.prompt.rsplit('\n').next().unwrap_or_default();
+        let mut output = String::new();
+        if self.cursor_row > 0 {
+            output.push_str(&format!("\x1b[{}A", self.cursor_row));
+        }
+        output.push('\r');
+        output.push_str(&last_line.replace([START_IGNORE, END_IGNORE], ""));
+        output.extend(&line.chars);
+
+        let start = self.prompt_end(columns);
+        let end = position(start, &line.chars, columns);
+        if end.1 == 0 && end.0 > 0 {
+            // the cursor stays on the last column until something is printed
+            output.push_str("\r\n");
+        }
+        output.push_str("\x1b[J");
+        let cursor = position(start, &line.chars[..line.cursor], columns);
+        if end.0 > cursor.0 {
+            output.push_str(&format!("\x1b[{}A", end.0 - cursor.0));
+        }
+        output.push('\r');
+        if cursor.1 > 0 {
+            output.push_str(&format!("\x1b[{}C", cursor.1));
+        }
+        self.cursor_row = cursor.0;
+        write(&output);
+    }
+
+    /// The position after the last line of the prompt, without the
+    /// characters between `\[` and `\]`
+    fn prompt_end(&self, columns: usize) -> (usize, usize) {
+        let last_line = self.prompt.rsplit('\n').next().unwrap_or_default();
+        let mut visible = Vec::new();
+        let mut ignoring = false;
+        for c in last_line.chars() {
+            match c {
+                START_IGNORE => ignoring = true,
+                END_IGNORE => ignoring = false,
+                c if !ignoring => visible.push(c),
+                _ => {}
+            }
+        }
+        position((0, 0), &visible, columns)
+    }
+}
+
+/// The row and column after printing `chars` from `start` on a terminal with
+/// `columns` columns. A wide character that does not fit at the end of a row
+/// goes to the next one.
+fn position(start: (usize, usize), chars: &[char], columns: usize) -> (usize, usize) {
+    let (mut row, mut column) = start;
+    for c in chars {
+        let width = c.width().unwrap_or(0);
+        if column + width > columns {
+            row += 1;
+            column = 0;
+        }
+        column += width;
+        if column >= columns {
+            row += 1;
+            column = 0;
+        }
+    }
+    (row, column)
+}
+
+/// The width of the terminal on standard output
+fn columns() -> usize {
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        match libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) {
+            0 if size.ws_col > 0 => size.ws_col as usize,
+            _ => 80,
+        }
+    }
+}
+
+/// Writes to standard output right away, without going through the buffer
+/// of `print!`
+fn write(text: &str) {
+    let mut bytes = text.as_bytes();
+    while !bytes.is_empty() {
+        let n = unsafe { libc::write(libc::STDOUT_FILENO, bytes.as_ptr().cast(), bytes.len()) };
+        if n <= 0 {
+            if io::Error::last_os_error().kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        bytes = &bytes[n as usize..];
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{position, Line};
+
+    fn line(text: &str, cursor: usize) -> Line {
+        Line {
+            chars: text.chars().collect(),
+            cursor,
+        }
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let mut line = line("echo foo-bar  baz", 17);
+        assert_eq!(line.word_start(), 14);
+        line.cursor = 14;
+        assert_eq!(line.word_start(), 9);
+        assert_eq!(line.big_word_start(), 5);
+        line.cursor = 4;
+        assert_eq!(line.word_end(), 8);
+        line.cursor = 0;
+        assert_eq!(line.word_end(), 4);
+    }
+
+    #[test]
+    fn test_kills() {
+        let mut line = line("échö wörld", 4);
+        assert_eq!(line.remove_to(10), " wörld");
+        assert_eq!(line.remove_to(line.word_start()), "échö");
+        assert!(line.chars.is_empty());
+        line.insert("ab");
+        line.transpose();
+        assert_eq!(line.chars, ['b', 'a']);
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let chars: Vec<char> = "日本語".chars().collect();
+        assert_eq!(position((0, 0), &chars, 80), (0, 6));
+        assert_eq!(position((0, 75), &chars, 80), (1, 2));
+        assert_eq!(position((0, 74), &chars, 80), (1, 0));
+        assert_eq!(position((0, 2), &['e', '\u{301}'], 80), (0, 3));
+    }
+}
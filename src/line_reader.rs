@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io::{self, stdout, ErrorKind, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+use libc::c_int;
+
+use crate::line_editor::LineEditor;
+use crate::prompt::{END_IGNORE, START_IGNORE};
+use crate::signals;
+
+/// Reads the shell's input line by line, from a file descriptor, a script file
+/// or a command string
+pub struct LineReader {
+    source: Source,
+}
+
+enum Source {
+    Fd(c_int),
+    Terminal(LineEditor),
+    File(File),
+    String { bytes: Vec<u8>, position: usize },
+}
+
+impl LineReader {
+    pub fn new(fd: c_int) -> Self {
+        LineReader {
+            source: Source::Fd(fd),
+        }
+    }
+
+    /// Lines are edited with the line editor when `fd` and standard output
+    /// are terminals
+    pub fn terminal(fd: c_int) -> Self {
+        let terminal = unsafe { libc::isatty(fd) == 1 && libc::isatty(libc::STDOUT_FILENO) == 1 };
+        LineReader {
+            source: match terminal {
+                true => Source::Terminal(LineEditor::new(fd)),
+                false => Source::Fd(fd),
+            },
+        }
+    }
+
+    /// The file is moved out of the way of the descriptors scripts use, so
+    /// that `exec 3>file` does not replace the script being read
+    pub fn from_file(file: File) -> Self {
+        let fd = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 10) };
+        let file = match fd {
+            -1 => file,
+            fd => unsafe { File::from_raw_fd(fd) },
+        };
+        LineReader {
+            source: Source::File(file),
+        }
+    }
+
+    pub fn from_string(source: String) -> Self {
+        LineReader {
+            source: Source::String {
+                bytes: source.into_bytes(),
+                position: 0,
+            },
+        }
+    }
+
+    /// Like `BufRead::read_line`, but Ctrl-C discards the line being read and
+    /// returns an `Interrupted` error instead of retrying. `prompt` is shown
+    /// first.
+    pub fn read_line(&mut self, prompt: &str, buf: &mut String) -> io::Result<usize> {
+        if let Source::Terminal(editor) = &mut self.source {
+            return editor.read_line(prompt, buf);
+        }
+        if !prompt.is_empty() {
+            print!("{}", prompt.replace([START_IGNORE, END_IGNORE], ""));
+            stdout().flush()?;
+        }
+        let fd = match &mut self.source {
+            Source::Fd(fd) => *fd,
+            Source::Terminal(_) => unreachable!(),
+            Source::File(file) => file.as_raw_fd(),
+            Source::String { bytes, position } => {
+                let rest = &bytes[*position..];
+                let len = match rest.iter().position(|&b| b == b'\n') {
+                    Some(index) => index + 1,
+                    None => rest.len(),
+                };
+                buf.push_str(&String::from_utf8_lossy(&rest[..len]));
+                *position += len;
+                return Ok(len);
+            }
+        };
+        LineReader::read_line_from_fd(fd, buf)
+    }
+
+    /// Reads one byte at a time, so that nothing past the end of the line is
+    /// consumed from input that is shared with commands
+    pub fn read_line_from_fd(fd: c_int, buf: &mut String) -> io::Result<usize> {
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(byte) = read_byte(fd)? {
+            bytes.push(byte);
+            if byte == b'\n' {
+                break;
+            }
+        }
+        buf.push_str(&String::from_utf8_lossy(&bytes));
+        Ok(bytes.len())
+    }
+}
+
+/// Reads a single byte from `fd`, `None` at the end of input. Like the line
+/// reader, Ctrl-C returns an `Interrupted` error instead of retrying.
+pub fn read_byte(fd: c_int) -> io::Result<Option<u8>> {
+    loop {
+        let mut byte: u8 = 0;
+        let n = unsafe { libc::read(fd, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        if n < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() != ErrorKind::Interrupted || signals::is_pending(libc::SIGINT) {
+                return Err(error);
+            }
+            continue;
+        }
+        return Ok((n == 1).then_some(byte));
+    }
+}
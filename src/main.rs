@@ -1,15 +1,126 @@
+// job control, signals and redirections are built on POSIX process groups,
+// fork(2) and exec(2)
+#[cfg(not(unix))]
+compile_error!("rushell only runs on Unix-like systems");
+
+mod alias_command;
+mod aliases;
+mod arithmetic;
+mod ast;
+mod bg_command;
+mod break_command;
+mod builtin_command;
 mod cd_command;
+mod command_command;
+mod continue_command;
+mod declare_command;
 mod echo_command;
+mod escapes;
+mod eval_command;
+mod exec_command;
 mod exit_command;
+mod expansion;
+mod fg_command;
+mod jobs;
+mod jobs_command;
+mod line_editor;
+mod line_reader;
+mod options;
+mod parser;
+mod pattern;
+mod printf_command;
+mod prompt;
 mod pwd_command;
+mod read_command;
+mod redirection;
+mod regex;
+mod return_command;
 mod scanner;
+mod set_command;
 mod shell;
+mod shopt_command;
+mod signals;
+mod source_command;
+mod test_command;
 mod token;
+mod trap_command;
+mod traps;
 mod type_command;
+mod unalias_command;
+mod unset_command;
 mod utils;
-use shell::Shell;
+mod variables;
+use shell::{Shell, StartupFiles};
+use std::{env, process::exit};
 
 fn main() {
-    let shell = Shell::new();
-    shell.main();
+    let mut args = env::args();
+    let name = args.next().unwrap_or_else(|| "rushell".to_string());
+    let args: Vec<String> = args.collect();
+
+    let mut command_string = false;
+    let mut read_stdin = false;
+    let mut interactive = false;
+    let mut posix = false;
+    // login programs start login shells with a name like `-rushell`
+    let mut startup = StartupFiles {
+        login: name.starts_with('-'),
+        ..Default::default()
+    };
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-c" => command_string = true,
+            "-s" => read_stdin = true,
+            "-i" => interactive = true,
+            "-l" | "--login" => startup.login = true,
+            "--noprofile" => startup.no_profile = true,
+            "--norc" => startup.no_rc = true,
+            "--posix" => posix = true,
+            "--rcfile" => {
+                i += 1;
+                let Some(file) = args.get(i) else {
+                    eprintln!("rushell: --rcfile: option requires an argument");
+                    exit(2);
+                };
+                startup.rc_file = Some(file.clone());
+            }
+            "--" => {
+                i += 1;
+                break;
+            }
+            arg if arg.starts_with('-') && arg.len() > 1 => {
+                eprintln!("rushell: {}: invalid option", arg);
+                eprintln!(
+                    "Usage: rushell [-ils] [--login] [--noprofile] [--norc] [--rcfile file] \
+                     [--posix] [-c command_string [name]] [script] [args...]"
+                );
+                exit(2);
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+    let operands = &args[i..];
+
+    // regular expressions match the characters of the user's locale
+    unsafe { libc::setlocale(libc::LC_CTYPE, c"".as_ptr()) };
+
+    let mut shell = Shell::new();
+    shell.options().posix = posix;
+    if command_string {
+        let Some(source) = operands.first() else {
+            eprintln!("rushell: -c: option requires an argument");
+            exit(2);
+        };
+        let name = operands.get(1).cloned().unwrap_or(name);
+        shell.set_arguments(name, operands.iter().skip(2).cloned().collect());
+        shell.run_command_string(source.clone(), &startup);
+    } else if !read_stdin && !operands.is_empty() {
+        shell.set_arguments(operands[0].clone(), operands[1..].to_vec());
+        shell.run_script(&operands[0], &startup);
+    } else {
+        shell.set_arguments(name, operands.to_vec());
+        shell.main(interactive, &startup);
+    }
 }
@@ -0,0 +1,218 @@
+/// The options of `set -o` and `shopt`
+///
+/// https://www.gnu.org/software/bash/manual/bash.html#The-Set-Builtin
+/// https://www.gnu.org/software/bash/manual/bash.html#The-Shopt-Builtin
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// `-e`: exit when a command fails outside of a condition
+    pub errexit: bool,
+    /// an interactive shell ignores Ctrl-D, like `IGNOREEOF=10`
+    pub ignoreeof: bool,
+    /// `-C`: `>` does not overwrite existing files
+    pub noclobber: bool,
+    /// `-n`: read commands without running them
+    pub noexec: bool,
+    /// `-f`: no pathname expansion, which has no effect as the shell does not
+    /// expand pathnames yet
+    pub noglob: bool,
+    /// `-u`: expanding an unset parameter is an error
+    pub nounset: bool,
+    /// the status of a pipeline is that of the last command to fail
+    pub pipefail: bool,
+    /// follow POSIX where bash differs, for now only in the startup files
+    pub posix: bool,
+    /// `-x`: print commands after expanding them
+    pub xtrace: bool,
+
+    /// a command that names a directory changes to it, in interactive shells
+    pub autocd: bool,
+    /// `cd` corrects small spelling mistakes in directory names
+    pub cdspell: bool,
+    /// pathname expansion includes names starting with `.`, accepted without
+    /// effect until the shell expands pathnames
+    pub dotglob: bool,
+    /// aliases are expanded, on by default in interactive shells
+    pub expand_aliases: bool,
+    /// the extended patterns `?(...)`, `*(...)`, `+(...)`, `@(...)` and
+    /// `!(...)` are recognized in words
+    pub extglob: bool,
+    /// `**` in pathname expansion matches directories recursively, accepted
+    /// without effect until the shell expands pathnames
+    pub globstar: bool,
+    /// the history is appended to the history file instead of replacing it,
+    /// accepted without effect until the shell keeps a history file
+    pub histappend: bool,
+    /// without job control, the last command of a pipeline runs in the shell
+    pub lastpipe: bool,
+    /// patterns that match no files expand to nothing, accepted without
+    /// effect until the shell expands pathnames
+    pub nullglob: bool,
+    /// `echo` interprets backslash escapes without `-e`, like the XSI echo
+    pub xpg_echo: bool,
+}
+
+/// Names of the options for `set -o`, with their single letter options
+const SET_OPTIONS: [(&str, Option<char>); 9] = [
+    ("errexit", Some('e')),
+    ("ignoreeof", None),
+    ("noclobber", Some('C')),
+    ("noexec", Some('n')),
+    ("noglob", Some('f')),
+    ("nounset", Some('u')),
+    ("pipefail", None),
+    ("posix", None),
+    ("xtrace", Some('x')),
+];
+
+/// Names of the options for `shopt`
+const SHOPT_OPTIONS: [&str; 10] = [
+    "autocd",
+    "cdspell",
+    "dotglob",
+    "expand_aliases",
+    "extglob",
+    "globstar",
+    "histappend",
+    "lastpipe",
+    "nullglob",
+    "xpg_echo",
+];
+
+impl Options {
+    /// The names of the options of `set -o` and whether they are on
+    pub fn list(&self) -> Vec<(&'static str, bool)> {
+        SET_OPTIONS
+            .iter()
+            .map(|(name, _)| (*name, self.get(name).unwrap_or(false)))
+            .collect()
+    }
+
+    /// The names of the options of `shopt` and whether they are on
+    pub fn shopt_list(&self) -> Vec<(&'static str, bool)> {
+        SHOPT_OPTIONS
+            .iter()
+            .map(|name| (*name, self.get(name).unwrap_or(false)))
+            .collect()
+    }
+
+    /// Whether the option of `set -o` or `shopt` called `name` is on
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let value = match name {
+            "errexit" => self.errexit,
+            "ignoreeof" => self.ignoreeof,
+            "noclobber" => self.noclobber,
+            "noexec" => self.noexec,
+            "noglob" => self.noglob,
+            "nounset" => self.nounset,
+            "pipefail" => self.pipefail,
+            "posix" => self.posix,
+            "xtrace" => self.xtrace,
+            "autocd" => self.autocd,
+            "cdspell" => self.cdspell,
+            "dotglob" => self.dotglob,
+            "expand_aliases" => self.expand_aliases,
+            "extglob" => self.extglob,
+            "globstar" => self.globstar,
+            "histappend" => self.histappend,
+            "lastpipe" => self.lastpipe,
+            "nullglob" => self.nullglob,
+            "xpg_echo" => self.xpg_echo,
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// `set -o name` and `set +o name`
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), String> {
+        match self.option(name) {
+            Some(option) if !SHOPT_OPTIONS.contains(&name) => *option = value,
+            _ => return Err(format!("{}: invalid option name", name)),
+        }
+        Ok(())
+    }
+
+    /// `shopt -s name` and `shopt -u name`
+    pub fn shopt(&mut self, name: &str, value: bool) -> Result<(), String> {
+        match self.option(name) {
+            Some(option) if SHOPT_OPTIONS.contains(&name) => *option = value,
+            _ => return Err(format!("{}: invalid shell option name", name)),
+        }
+        Ok(())
+    }
+
+    fn option(&mut self, name: &str) -> Option<&mut bool> {
+        let option = match name {
+            "errexit" => &mut self.errexit,
+            "ignoreeof" => &mut self.ignoreeof,
+            "noclobber" => &mut self.noclobber,
+            "noexec" => &mut self.noexec,
+            "noglob" => &mut self.noglob,
+            "nounset" => &mut self.nounset,
+            "pipefail" => &mut self.pipefail,
+            "posix" => &mut self.posix,
+            "xtrace" => &mut self.xtrace,
+            "autocd" => &mut self.autocd,
+            "cdspell" => &mut self.cdspell,
+            "dotglob" => &mut self.dotglob,
+            "expand_aliases" => &mut self.expand_aliases,
+            "extglob" => &mut self.extglob,
+            "globstar" => &mut self.globstar,
+            "histappend" => &mut self.histappend,
+            "lastpipe" => &mut self.lastpipe,
+            "nullglob" => &mut self.nullglob,
+            "xpg_echo" => &mut self.xpg_echo,
+            _ => return None,
+        };
+        Some(option)
+    }
+
+    /// The name of the option set with `-letter`
+    pub fn name(letter: char) -> Option<&'static str> {
+        SET_OPTIONS
+            .iter()
+            .find(|(_, option)| *option == Some(letter))
+            .map(|(name, _)| *name)
+    }
+
+    /// The letters of the options that are on, for `$-`
+    pub fn flags(&self) -> String {
+        SET_OPTIONS
+            .iter()
+            .filter(|(name, _)| self.get(name) == Some(true))
+            .filter_map(|(_, letter)| *letter)
+            .collect()
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::Options;
+
+    #[test]
+    fn test_set_options() {
+        let mut options = Options::default();
+        options.set(Options::name('e').unwrap(), true).unwrap();
+        options.set("pipefail", true).unwrap();
+        options.set("noclobber", true).unwrap();
+        options.set(Options::name('f').unwrap(), true).unwrap();
+        assert_eq!(options.flags(), "eCf");
+        assert!(options.list().contains(&("noglob", true)));
+        assert!(options.errexit && options.pipefail);
+        assert!(options.set("nonsense", true).is_err());
+        assert_eq!(Options::name('k'), None);
+    }
+
+    #[test]
+    fn test_shopt_options() {
+        let mut options = Options::default();
+        options.shopt("lastpipe", true).unwrap();
+        assert_eq!(options.get("lastpipe"), Some(true));
+        options.shopt("nullglob", true).unwrap();
+        assert!(options.shopt_list().contains(&("nullglob", true)));
+        assert!(options.shopt("errexit", true).is_err());
+        assert!(options.set("lastpipe", true).is_err());
+        assert_eq!(options.flags(), "");
+    }
+}
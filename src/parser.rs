@@ -0,0 +1,825 @@
+use std::fmt::Display;
+
+use crate::ast::{
+    AndOr, Assignment, AssignmentValue, Command, CommandList, CompoundCommand,
+    ConditionalExpression, Connector, ForCommand, FunctionDefinition, IfCommand, ListItem,
+    LoopCommand, Pipeline, Redirection, RedirectionOperator, SimpleCommand,
+};
+use crate::scanner::{Scanner, ScannerError};
+use crate::test_command::{is_binary_operator, is_unary_operator};
+use crate::token::{Token, TokenType};
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParserError {
+    pub message: String,
+    /// the tokens ended before the command was complete, more input may
+    /// complete it
+    pub incomplete: bool,
+}
+
+impl From<ScannerError> for ParserError {
+    fn from(error: ScannerError) -> Self {
+        ParserError {
+            message: error.message,
+            incomplete: error.incomplete,
+        }
+    }
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Reserved-Words
+const RESERVED_WORDS: [&str; 18] = [
+    "if", "then", "elif", "else", "fi", "while", "until", "do", "done", "for", "select", "in", "{",
+    "}", "!", "function", "[[", "]]",
+];
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Parser { tokens, current: 0 }
+    }
+
+    /// program ::= list
+    pub fn parse(&mut self) -> Result<CommandList, ParserError> {
+        let list = self.list(&[])?;
+        if !self.is_at_end() {
+            return Err(self.unexpected_token());
+        }
+        Ok(list)
+    }
+
+    /// list ::= and_or ( ( ';' | '&' | newline ) and_or )* ( ';' | '&' | newline )?
+    ///
+    /// The list ends before one of the reserved words in `terminators`, a `)`
+    /// or the end of the input.
+    fn list(&mut self, terminators: &[&str]) -> Result<CommandList, ParserError> {
+        let mut items: Vec<ListItem> = Vec::new();
+
+        self.skip_newlines();
+        while !self.is_at_end()
+            && self.peek().type_ != TokenType::RightParen
+            && !terminators
+                .iter()
+                .any(|word| self.check_reserved_word(word))
+        {
+            let and_or = self.and_or()?;
+            let background = if self.match_type(TokenType::Ampersand) {
+                true
+            } else {
+                if !self.match_type(TokenType::Semicolon)
+                    && !self.match_type(TokenType::Newline)
+                    && !self.is_at_end()
+                    && self.peek().type_ != TokenType::RightParen
+                    && !terminators
+                        .iter()
+                        .any(|word| self.check_reserved_word(word))
+                {
+                    return Err(self.unexpected_token());
+                }
+                false
+            };
+            items.push(ListItem { and_or, background });
+            self.skip_newlines();
+        }
+
+        Ok(CommandList { items })
+    }
+
+    /// A list that is part of a compound command, which must not be empty
+    fn compound_list(&mut self, terminators: &[&str]) -> Result<CommandList, ParserError> {
+        let list = self.list(terminators)?;
+        if list.items.is_empty() {
+            return Err(self.unexpected_token());
+        }
+        Ok(list)
+    }
+
+    /// and_or ::= pipeline ( ( '&&' | '||' ) pipeline )*
+    fn and_or(&mut self) -> Result<AndOr, ParserError> {
+        let first = self.pipeline()?;
+        let mut rest: Vec<(Connector, Pipeline)> = Vec::new();
+
+        loop {
+            let connector = if self.match_type(TokenType::AndIf) {
+                Connector::And
+            } else if self.match_type(TokenType::OrIf) {
+                Connector::Or
+            } else {
+                break;
+            };
+            // a command may continue on the next line after `&&` and `||`
+            self.skip_newlines();
+            rest.push((connector, self.pipeline()?));
+        }
+
+        Ok(AndOr { first, rest })
+    }
+
+    /// pipeline ::= '!'? command ( '|' command )*
+    fn pipeline(&mut self) -> Result<Pipeline, ParserError> {
+        let start = self.current;
+        let negated = self.match_reserved_word("!");
+        let mut commands = vec![self.command()?];
+        while self.match_type(TokenType::Pipe) {
+            self.skip_newlines();
+            commands.push(self.command()?);
+        }
+
+        let text = self.text(start);
+        Ok(Pipeline {
+            commands,
+            negated,
+            text,
+        })
+    }
+
+    /// command ::= compound_command redirection* | function_definition | simple_command
+    fn command(&mut self) -> Result<Command, ParserError> {
+        if let Some(command) = self.compound_command()? {
+            let redirections = self.redirections()?;
+            return Ok(Command::Compound(command, redirections));
+        }
+        if self.check_reserved_word("function") || self.check_function_definition() {
+            return Ok(Command::FunctionDefinition(self.function_definition()?));
+        }
+        if self.peek().type_ == TokenType::String && Parser::is_keyword(self.peek()) {
+            return Err(self.unexpected_token());
+        }
+        Ok(Command::Simple(self.simple_command()?))
+    }
+
+    /// compound_command ::= brace_group | subshell | if | while | until | for | select | conditional
+    fn compound_command(&mut self) -> Result<Option<CompoundCommand>, ParserError> {
+        let command = if self.match_reserved_word("{") {
+            let list = self.compound_list(&["}"])?;
+            self.consume_reserved_word("}")?;
+            CompoundCommand::BraceGroup(list)
+        } else if self.match_type(TokenType::LeftParen) {
+            let list = self.compound_list(&[])?;
+            self.consume(TokenType::RightParen)?;
+            CompoundCommand::Subshell(list)
+        } else if self.match_reserved_word("if") {
+            CompoundCommand::If(self.if_command()?)
+        } else if self.match_reserved_word("while") {
+            CompoundCommand::Loop(self.loop_command(false)?)
+        } else if self.match_reserved_word("until") {
+            CompoundCommand::Loop(self.loop_command(true)?)
+        } else if self.match_reserved_word("for") {
+            CompoundCommand::For(self.for_command()?)
+        } else if self.match_reserved_word("select") {
+            CompoundCommand::Select(self.for_command()?)
+        } else if self.match_reserved_word("[[") {
+            let expression = self.conditional_or()?;
+            self.skip_newlines();
+            self.consume_reserved_word("]]")?;
+            CompoundCommand::Conditional(expression)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(command))
+    }
+
+    /// if ::= 'if' list 'then' list ( 'elif' list 'then' list )* ( 'else' list )? 'fi'
+    fn if_command(&mut self) -> Result<IfCommand, ParserError> {
+        let mut branches: Vec<(CommandList, CommandList)> = Vec::new();
+        loop {
+            let condition = self.compound_list(&["then"])?;
+            self.consume_reserved_word("then")?;
+            let body = self.compound_list(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+            if !self.match_reserved_word("elif") {
+                break;
+            }
+        }
+        let else_branch = match self.match_reserved_word("else") {
+            true => Some(self.compound_list(&["fi"])?),
+            false => None,
+        };
+        self.consume_reserved_word("fi")?;
+        Ok(IfCommand {
+            branches,
+            else_branch,
+        })
+    }
+
+    /// while ::= 'while' list do_group
+    /// until ::= 'until' list do_group
+    fn loop_command(&mut self, until: bool) -> Result<LoopCommand, ParserError> {
+        let condition = self.compound_list(&["do"])?;
+        let body = self.do_group()?;
+        Ok(LoopCommand {
+            until,
+            condition,
+            body,
+        })
+    }
+
+    /// for ::= 'for' name ( newline* 'in' word* )? ( ';' | newline )? newline* do_group
+    /// select ::= 'select' name ( newline* 'in' word* )? ( ';' | newline )? newline* do_group
+    fn for_command(&mut self) -> Result<ForCommand, ParserError> {
+        let name = match self.peek() {
+            token if token.type_ == TokenType::String && is_name(&token.raw) => token.raw.clone(),
+            _ => return Err(self.unexpected_token()),
+        };
+        self.advance();
+
+        self.skip_newlines();
+        let words = if self.match_reserved_word("in") {
+            let mut words: Vec<String> = Vec::new();
+            while self.peek().type_ == TokenType::String {
+                words.push(self.advance().raw.clone());
+            }
+            if !self.match_type(TokenType::Semicolon) && !self.match_type(TokenType::Newline) {
+                return Err(self.unexpected_token());
+            }
+            Some(words)
+        } else {
+            self.match_type(TokenType::Semicolon);
+            None
+        };
+        self.skip_newlines();
+        let body = self.do_group()?;
+        Ok(ForCommand { name, words, body })
+    }
+
+    /// do_group ::= 'do' list 'done'
+    fn do_group(&mut self) -> Result<CommandList, ParserError> {
+        self.consume_reserved_word("do")?;
+        let body = self.compound_list(&["done"])?;
+        self.consume_reserved_word("done")?;
+        Ok(body)
+    }
+
+    /// conditional ::= '[[' conditional_or ']]'
+    ///
+    /// conditional_or ::= conditional_and ( '||' conditional_and )*
+    fn conditional_or(&mut self) -> Result<ConditionalExpression, ParserError> {
+        let mut expression = self.conditional_and()?;
+        loop {
+            self.skip_newlines();
+            if !self.match_type(TokenType::OrIf) {
+                return Ok(expression);
+            }
+            let right = self.conditional_and()?;
+            expression = ConditionalExpression::Or(Box::new(expression), Box::new(right));
+        }
+    }
+
+    /// conditional_and ::= conditional_not ( '&&' conditional_not )*
+    fn conditional_and(&mut self) -> Result<ConditionalExpression, ParserError> {
+        let mut expression = self.conditional_not()?;
+        loop {
+            self.skip_newlines();
+            if !self.match_type(TokenType::AndIf) {
+                return Ok(expression);
+            }
+            let right = self.conditional_not()?;
+            expression = ConditionalExpression::And(Box::new(expression), Box::new(right));
+        }
+    }
+
+    /// conditional_not ::= '!' conditional_not | conditional_primary
+    fn conditional_not(&mut self) -> Result<ConditionalExpression, ParserError> {
+        self.skip_newlines();
+        if self.match_reserved_word("!") {
+            let expression = self.conditional_not()?;
+            return Ok(ConditionalExpression::Not(Box::new(expression)));
+        }
+        self.conditional_primary()
+    }
+
+    /// conditional_primary ::= '(' conditional_or ')' | unary_operator word
+    ///                       | word binary_operator word | word
+    fn conditional_primary(&mut self) -> Result<ConditionalExpression, ParserError> {
+        if self.match_type(TokenType::LeftParen) {
+            let expression = self.conditional_or()?;
+            self.skip_newlines();
+            self.consume(TokenType::RightParen)?;
+            return Ok(expression);
+        }
+        let word = self.conditional_word()?;
+        if let Some(operator) = self.match_conditional_binary_operator() {
+            let right = self.conditional_word()?;
+            return Ok(ConditionalExpression::Binary(word, operator, right));
+        }
+        if is_unary_operator(&word) && self.check_conditional_word() {
+            let operand = self.conditional_word()?;
+            return Ok(ConditionalExpression::Unary(word, operand));
+        }
+        Ok(ConditionalExpression::Word(word))
+    }
+
+    /// Inside `[[ ]]`, a number before `<` or `>` is a word rather than a file
+    /// descriptor
+    fn check_conditional_word(&self) -> bool {
+        matches!(self.peek().type_, TokenType::String | TokenType::IoNumber)
+            && !self.check_reserved_word("]]")
+    }
+
+    fn conditional_word(&mut self) -> Result<String, ParserError> {
+        if !self.check_conditional_word() {
+            return Err(self.unexpected_token());
+        }
+        Ok(self.advance().raw.clone())
+    }
+
+    /// `<` and `>` compare strings inside `[[ ]]`, and `=~` matches a regular
+    /// expression
+    fn match_conditional_binary_operator(&mut self) -> Option<String> {
+        let token = self.peek();
+        let is_operator = match token.type_ {
+            TokenType::String => {
+                token.raw == token.lexeme && (is_binary_operator(&token.raw) || token.raw == "=~")
+            }
+            TokenType::Redirection => token.lexeme == "<" || token.lexeme == ">",
+            _ => false,
+        };
+        is_operator.then(|| self.advance().lexeme.clone())
+    }
+
+    /// `name ()` at the start of a command
+    fn check_function_definition(&self) -> bool {
+        let token = self.peek();
+        token.type_ == TokenType::String
+            && is_name(&token.raw)
+            && !Parser::is_keyword(token)
+            && self.tokens.get(self.current + 1).map(|t| t.type_) == Some(TokenType::LeftParen)
+    }
+
+    /// function_definition ::= name '(' ')' newline* compound_command redirection*
+    ///                       | 'function' name ( '(' ')' )? newline* compound_command redirection*
+    fn function_definition(&mut self) -> Result<FunctionDefinition, ParserError> {
+        let start = self.current;
+        let keyword = self.match_reserved_word("function");
+        let name = match self.peek() {
+            token if token.type_ == TokenType::String && is_name(&token.raw) => token.raw.clone(),
+            _ => return Err(self.unexpected_token()),
+        };
+        self.advance();
+        if !keyword || self.peek().type_ == TokenType::LeftParen {
+            self.consume(TokenType::LeftParen)?;
+            self.consume(TokenType::RightParen)?;
+        }
+        self.skip_newlines();
+        let body = match self.compound_command()? {
+            Some(body) => body,
+            None => return Err(self.unexpected_token()),
+        };
+        let redirections = self.redirections()?;
+        let text = self.text(start);
+        Ok(FunctionDefinition {
+            name,
+            body,
+            redirections,
+            text,
+        })
+    }
+
+    /// simple_command ::= ( assignment | redirection )* ( word | redirection )*
+    fn simple_command(&mut self) -> Result<SimpleCommand, ParserError> {
+        let mut assignments: Vec<Assignment> = Vec::new();
+        let mut words: Vec<String> = Vec::new();
+        let mut redirections: Vec<Redirection> = Vec::new();
+        loop {
+            match self.peek().type_ {
+                TokenType::String => {
+                    let raw = self.advance().raw.clone();
+                    match Parser::assignment(&raw) {
+                        Some(assignment) if words.is_empty() => assignments.push(assignment),
+                        _ => words.push(raw),
+                    }
+                }
+                TokenType::IoNumber | TokenType::Redirection => {
+                    redirections.push(self.redirection()?);
+                }
+                _ => break,
+            }
+        }
+        if assignments.is_empty() && words.is_empty() && redirections.is_empty() {
+            return Err(self.unexpected_token());
+        }
+        Ok(SimpleCommand {
+            assignments,
+            words,
+            redirections,
+        })
+    }
+
+    fn redirections(&mut self) -> Result<Vec<Redirection>, ParserError> {
+        let mut redirections: Vec<Redirection> = Vec::new();
+        while matches!(
+            self.peek().type_,
+            TokenType::IoNumber | TokenType::Redirection
+        ) {
+            redirections.push(self.redirection()?);
+        }
+        Ok(redirections)
+    }
+
+    /// redirection ::= io_number? redirection_operator word
+    fn redirection(&mut self) -> Result<Redirection, ParserError> {
+        let fd = match self.peek().type_ {
+            TokenType::IoNumber => match self.advance().lexeme.parse::<i32>() {
+                Ok(fd) => Some(fd),
+                Err(_) => {
+                    return Err(ParserError {
+                        message: format!(
+                            "{}: file descriptor out of range",
+                            self.previous().lexeme
+                        ),
+                        incomplete: false,
+                    })
+                }
+            },
+            _ => None,
+        };
+        if self.peek().type_ != TokenType::Redirection {
+            return Err(self.unexpected_token());
+        }
+        let operator_token = self.advance().clone();
+        if self.peek().type_ != TokenType::String {
+            return Err(self.unexpected_token());
+        }
+        let word = self.advance();
+
+        let mut operator = RedirectionOperator::from_lexeme(&operator_token.lexeme).unwrap();
+        let target = match operator_token.here_document {
+            Some(body) => {
+                // quoting any part of the delimiter disables expansion of the body
+                let quoted = word.raw.contains(['\'', '"', '\\']);
+                operator = RedirectionOperator::HereDocument { quoted };
+                body
+            }
+            None => word.raw.clone(),
+        };
+        Ok(Redirection {
+            fd,
+            operator,
+            target,
+        })
+    }
+
+    /// A word of the form `name=value`, `name+=value`, `name[subscript]=value`
+    /// or `name=(words...)`, where name is not quoted
+    pub fn assignment(raw: &str) -> Option<Assignment> {
+        let name_len = raw
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(raw.len());
+        let (name, mut rest) = raw.split_at(name_len);
+        if !is_name(name) {
+            return None;
+        }
+        let mut subscript = None;
+        if rest.starts_with('[') {
+            let end = rest.find("]=").or_else(|| rest.find("]+="))?;
+            subscript = Some(rest[1..end].to_string());
+            rest = &rest[end + 1..];
+        }
+        let append = rest.starts_with("+=");
+        let value = rest.strip_prefix(if append { "+=" } else { "=" })?;
+
+        let value = match value.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+            Some(words) => {
+                let tokens = Scanner::new(words.to_string()).scan_tokens().ok()?;
+                let words = tokens
+                    .into_iter()
+                    .filter(|token| matches!(token.type_, TokenType::String | TokenType::IoNumber))
+                    .map(|token| token.raw)
+                    .collect();
+                AssignmentValue::Array(words)
+            }
+            _ => AssignmentValue::Scalar(value.to_string()),
+        };
+        Some(Assignment {
+            name: name.to_string(),
+            subscript,
+            append,
+            value,
+        })
+    }
+
+    /// Source text of the tokens from `start` to the current one, on a single
+    /// line
+    fn text(&self, start: usize) -> String {
+        let mut text = String::new();
+        let mut previous: Option<&Token> = None;
+        for token in &self.tokens[start..self.current] {
+            let word = match token.type_ {
+                // newlines are kept as `;` unless a list may start after the
+                // previous token anyway
+                TokenType::Newline => match previous {
+                    None => continue,
+                    Some(previous) if previous.type_ == TokenType::String => {
+                        match previous.raw.as_str() {
+                            "{" | "then" | "do" | "else" => continue,
+                            _ => ";",
+                        }
+                    }
+                    Some(previous) if previous.type_ == TokenType::RightParen => ";",
+                    Some(_) => continue,
+                },
+                _ => token.raw.as_str(),
+            };
+            let attached = word == ";"
+                || (token.type_ == TokenType::RightParen
+                    && previous.is_some_and(|p| p.type_ == TokenType::LeftParen))
+                || previous.is_some_and(|p| p.type_ == TokenType::IoNumber);
+            if !text.is_empty() && !attached {
+                text.push(' ');
+            }
+            text.push_str(word);
+            previous = Some(token);
+        }
+        text.trim_end_matches(';').to_string()
+    }
+
+    /// An unquoted reserved word, which is only recognized at the start of a
+    /// command or where the grammar expects it
+    fn is_keyword(token: &Token) -> bool {
+        token.raw == token.lexeme && is_reserved_word(&token.raw)
+    }
+
+    fn check_reserved_word(&self, word: &str) -> bool {
+        let token = self.peek();
+        token.type_ == TokenType::String && token.raw == word && token.lexeme == word
+    }
+
+    fn match_reserved_word(&mut self, word: &str) -> bool {
+        if self.check_reserved_word(word) {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    fn consume_reserved_word(&mut self, word: &str) -> Result<(), ParserError> {
+        match self.match_reserved_word(word) {
+            true => Ok(()),
+            false => Err(self.unexpected_token()),
+        }
+    }
+
+    fn consume(&mut self, type_: TokenType) -> Result<(), ParserError> {
+        match self.match_type(type_) {
+            true => Ok(()),
+            false => Err(self.unexpected_token()),
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.match_type(TokenType::Newline) {}
+    }
+
+    fn unexpected_token(&self) -> ParserError {
+        let token = self.peek();
+        if token.type_ == TokenType::Eof {
+            return ParserError {
+                message: "syntax error: unexpected end of file".to_string(),
+                incomplete: true,
+            };
+        }
+        let lexeme = match token.type_ {
+            TokenType::Newline => "newline",
+            _ => token.lexeme.as_str(),
+        };
+        ParserError {
+            message: format!("syntax error near unexpected token `{}'", lexeme),
+            incomplete: false,
+        }
+    }
+
+    fn match_type(&mut self, type_: TokenType) -> bool {
+        if self.peek().type_ == type_ {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        &self.tokens[self.current - 1]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().type_ == TokenType::Eof
+    }
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#index-name
+pub fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+pub fn is_reserved_word(s: &str) -> bool {
+    RESERVED_WORDS.contains(&s)
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{Parser, ParserError};
+    use crate::ast::{
+        AssignmentValue, Command, CommandList, CompoundCommand, ConditionalExpression,
+        RedirectionOperator,
+    };
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Result<CommandList, ParserError> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens()?;
+        Parser::new(tokens).parse()
+    }
+
+    fn first_command(list: &CommandList) -> &Command {
+        &list.items[0].and_or.first.commands[0]
+    }
+
+    #[test]
+    fn test_compound_commands() {
+        let list = parse("if a; then b; elif c\nthen d; else e; fi").unwrap();
+        match first_command(&list) {
+            Command::Compound(CompoundCommand::If(command), _) => {
+                assert_eq!(command.branches.len(), 2);
+                assert!(command.else_branch.is_some());
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+
+        let list = parse("for x in a b\ndo echo $x; done | cat").unwrap();
+        match first_command(&list) {
+            Command::Compound(CompoundCommand::For(command), _) => {
+                assert_eq!(command.name, "x");
+                assert_eq!(command.words, Some(vec!["a".to_string(), "b".to_string()]));
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert_eq!(
+            list.items[0].and_or.first.text,
+            "for x in a b; do echo $x; done | cat"
+        );
+
+        let list = parse("select x\ndo break; done").unwrap();
+        match first_command(&list) {
+            Command::Compound(CompoundCommand::Select(command), _) => {
+                assert_eq!(command.name, "x");
+                assert_eq!(command.words, None);
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+
+        assert!(parse("while a; do b; done; until a; do b; done").is_ok());
+        assert!(parse("{ a; b; } && (c; d)").is_ok());
+    }
+
+    #[test]
+    fn test_conditional_command() {
+        let list = parse("[[ ! -f $x && ( 1 < 2 || $y == a* ) ]] > out").unwrap();
+        match first_command(&list) {
+            Command::Compound(CompoundCommand::Conditional(expression), redirections) => {
+                assert_eq!(redirections.len(), 1);
+                let ConditionalExpression::And(left, right) = expression else {
+                    panic!("unexpected expression {:?}", expression);
+                };
+                assert!(matches!(**left, ConditionalExpression::Not(_)));
+                assert!(matches!(**right, ConditionalExpression::Or(_, _)));
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+
+        assert!(parse("[[ -n ]]").is_ok());
+        assert!(parse("[[ $v =~ ^([0-9]+)\\.(x|y)$ ]]").is_ok());
+        assert!(parse("[[ ]]").is_err());
+        assert!(parse("[[ a b ]]").is_err());
+        assert!(parse("[[ a &&").unwrap_err().incomplete);
+    }
+
+    #[test]
+    fn test_assignments() {
+        let assignment = Parser::assignment("a[$i]+=x=y").unwrap();
+        assert_eq!(assignment.name, "a");
+        assert_eq!(assignment.subscript.as_deref(), Some("$i"));
+        assert!(assignment.append);
+        assert_eq!(assignment.value, AssignmentValue::Scalar("x=y".to_string()));
+
+        let assignment = Parser::assignment("a=(x 'y z' [k]=\"v w\"\n# c\n)").unwrap();
+        assert_eq!(
+            assignment.value,
+            AssignmentValue::Array(vec![
+                "x".to_string(),
+                "'y z'".to_string(),
+                "[k]=\"v w\"".to_string()
+            ])
+        );
+
+        assert!(Parser::assignment("1a=x").is_none());
+        assert!(Parser::assignment("a[0]").is_none());
+        assert!(parse("a=(b c) d[1]=e f").is_ok());
+        assert!(parse("a=(b").is_err());
+    }
+
+    #[test]
+    fn test_function_definition() {
+        let list = parse("f() { echo $1; }\nfunction g\n{ a; }").unwrap();
+        match first_command(&list) {
+            Command::FunctionDefinition(definition) => {
+                assert_eq!(definition.name, "f");
+                assert_eq!(definition.text, "f () { echo $1; }");
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert_eq!(list.items.len(), 2);
+    }
+
+    #[test]
+    fn test_reserved_words_only_at_command_start() {
+        let list = parse("echo if then fi {").unwrap();
+        match first_command(&list) {
+            Command::Simple(command) => assert_eq!(command.words.len(), 5),
+            command => panic!("unexpected command {:?}", command),
+        }
+        assert!(parse("'if' a").is_ok());
+    }
+
+    #[test]
+    fn test_incomplete_commands() {
+        for source in [
+            "if a; then",
+            "while a; do b",
+            "a |",
+            "a &&",
+            "{ a;",
+            "f() {",
+            "(a",
+        ] {
+            let error = parse(source).unwrap_err();
+            assert!(error.incomplete, "{}", source);
+        }
+        for source in ["fi", "if a; fi", "{ }", "a )", "for 1 in a; do b; done"] {
+            let error = parse(source).unwrap_err();
+            assert!(!error.incomplete, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_redirections() {
+        let list = parse("2>err <in cmd >>out arg 3<&- <<<word").unwrap();
+        let Command::Simple(command) = first_command(&list) else {
+            panic!("expected a simple command");
+        };
+        assert_eq!(command.words, vec!["cmd", "arg"]);
+        let redirections: Vec<_> = command
+            .redirections
+            .iter()
+            .map(|r| (r.fd, r.operator, r.target.as_str()))
+            .collect();
+        assert_eq!(
+            redirections,
+            vec![
+                (Some(2), RedirectionOperator::Output, "err"),
+                (None, RedirectionOperator::Input, "in"),
+                (None, RedirectionOperator::Append, "out"),
+                (Some(3), RedirectionOperator::DuplicateInput, "-"),
+                (None, RedirectionOperator::HereString, "word"),
+            ]
+        );
+
+        let list = parse("{ a; } >out <<\"E\"\nbody\nE\n").unwrap();
+        let Command::Compound(_, redirections) = first_command(&list) else {
+            panic!("expected a compound command");
+        };
+        assert_eq!(
+            redirections[1].operator,
+            RedirectionOperator::HereDocument { quoted: true }
+        );
+        assert_eq!(redirections[1].target, "body\n");
+
+        assert!(!parse("cat >").unwrap_err().message.is_empty());
+        assert!(!parse("cat > ;").unwrap_err().incomplete);
+    }
+}
@@ -0,0 +1,247 @@
+/// https://www.gnu.org/software/bash/manual/bash.html#Pattern-Matching
+///
+/// Whether the whole of `text` matches `pattern`. `*` matches any string, `?`
+/// any character and `[...]` any character in the set. A backslash makes the
+/// next character match itself. Extended patterns like `@(a|b)` are always
+/// recognized, as they are in `[[ ]]`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    if pattern
+        .windows(2)
+        .any(|pair| is_pattern_list_operator(pair[0]) && pair[1] == '(')
+    {
+        return matches_extended(&pattern, &text);
+    }
+
+    let (mut p, mut t) = (0, 0);
+    // where to resume after the last `*` if the rest fails to match
+    let mut backtrack: Option<(usize, usize)> = None;
+    loop {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            backtrack = Some((p, t));
+            continue;
+        }
+        if p == pattern.len() && t == text.len() {
+            return true;
+        }
+        if p < pattern.len() && t < text.len() {
+            let (matched, next) = match_character(&pattern, p, text[t]);
+            if matched {
+                p = next;
+                t += 1;
+                continue;
+            }
+        }
+        match backtrack {
+            Some((star_p, star_t)) if star_t < text.len() => {
+                backtrack = Some((star_p, star_t + 1));
+                p = star_p;
+                t = star_t + 1;
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Extended patterns `?(list)`, `*(list)`, `+(list)`, `@(list)` and `!(list)`
+/// match zero or one, zero or more, one or more, exactly one, or anything but
+/// one of the `|`-separated patterns in `list`. They need backtracking over
+/// where each one ends, which this does recursively.
+fn matches_extended(pattern: &[char], text: &[char]) -> bool {
+    let Some(&first) = pattern.first() else {
+        return text.is_empty();
+    };
+    if let Some((list, rest)) = pattern_list(pattern) {
+        let one = |text: &[char]| list.iter().any(|pattern| matches_extended(pattern, text));
+        // whether the list matches up to `i` and the rest of the pattern after
+        let split = |i: usize, matched: bool| {
+            one(&text[..i]) == matched && matches_extended(rest, &text[i..])
+        };
+        return match first {
+            '?' => (0..=text.len()).any(|i| split(i, true)) || matches_extended(rest, text),
+            '@' => (0..=text.len()).any(|i| split(i, true)),
+            '!' => (0..=text.len()).any(|i| split(i, false)),
+            _ => {
+                // after matching once, `*(list)` and `+(list)` may match again
+                let mut again = pattern.to_vec();
+                again[0] = '*';
+                (first == '*' && matches_extended(rest, text))
+                    || (1..=text.len())
+                        .any(|i| one(&text[..i]) && matches_extended(&again, &text[i..]))
+            }
+        };
+    }
+    match (first, text.first()) {
+        ('*', _) => {
+            matches_extended(&pattern[1..], text)
+                || (!text.is_empty() && matches_extended(pattern, &text[1..]))
+        }
+        (_, Some(&c)) => {
+            let (matched, next) = match_character(pattern, 0, c);
+            matched && matches_extended(&pattern[next..], &text[1..])
+        }
+        (_, None) => false,
+    }
+}
+
+fn is_pattern_list_operator(c: char) -> bool {
+    matches!(c, '?' | '*' | '+' | '@' | '!')
+}
+
+/// The patterns of an extended pattern at the start of `pattern`, and the
+/// rest of `pattern` after it
+fn pattern_list(pattern: &[char]) -> Option<(Vec<&[char]>, &[char])> {
+    if pattern.len() < 2 || !is_pattern_list_operator(pattern[0]) || pattern[1] != '(' {
+        return None;
+    }
+    let mut list = Vec::new();
+    let mut start = 2;
+    let mut depth = 1;
+    let mut i = 2;
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => i += 1,
+            '(' => depth += 1,
+            ')' if depth == 1 => {
+                list.push(&pattern[start..i]);
+                return Some((list, &pattern[i + 1..]));
+            }
+            ')' => depth -= 1,
+            '|' if depth == 1 => {
+                list.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Whether `c` matches the pattern element at `p`, and where the next element
+/// starts
+fn match_character(pattern: &[char], p: usize, c: char) -> (bool, usize) {
+    match pattern[p] {
+        '?' => (true, p + 1),
+        '\\' if p + 1 < pattern.len() => (pattern[p + 1] == c, p + 2),
+        // an unterminated bracket is an ordinary character
+        '[' => bracket_expression(pattern, p, c).unwrap_or((c == '[', p + 1)),
+        other => (other == c, p + 1),
+    }
+}
+
+/// `[abc]`, `[a-z]`, `[!a]`, `[^a]` and `[[:class:]]`, `None` if there is no
+/// closing bracket
+fn bracket_expression(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let mut low = *pattern.get(i)?;
+        if low == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if low == '[' && pattern.get(i + 1) == Some(&':') {
+            let rest: String = pattern[i + 2..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                let class = &rest[..end];
+                matched |= matches_class(class, c);
+                i += 2 + class.chars().count() + 2;
+                continue;
+            }
+        }
+        if low == '\\' && i + 1 < pattern.len() {
+            i += 1;
+            low = pattern[i];
+        }
+        i += 1;
+        let mut high = low;
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).is_some_and(|&c| c != ']') {
+            high = pattern[i + 1];
+            i += 2;
+            if high == '\\' && i < pattern.len() {
+                high = pattern[i];
+                i += 1;
+            }
+        }
+        matched |= low <= c && c <= high;
+    }
+}
+
+fn matches_class(class: &str, c: char) -> bool {
+    match class {
+        "alnum" => c.is_alphanumeric(),
+        "alpha" => c.is_alphabetic(),
+        "blank" => c == ' ' || c == '\t',
+        "cntrl" => c.is_control(),
+        "digit" => c.is_ascii_digit(),
+        "graph" => c.is_ascii_graphic(),
+        "lower" => c.is_lowercase(),
+        "print" => c.is_ascii_graphic() || c == ' ',
+        "punct" => c.is_ascii_punctuation(),
+        "space" => c.is_whitespace(),
+        "upper" => c.is_uppercase(),
+        "xdigit" => c.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("*", ""));
+        assert!(matches("a*c", "abbbc"));
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "main.rs.bak"));
+        assert!(matches("?b?", "abc"));
+        assert!(!matches("?", ""));
+        assert!(matches("*a*b*", "xxaxxbxx"));
+    }
+
+    #[test]
+    fn test_bracket_expressions() {
+        assert!(matches("[abc]", "b"));
+        assert!(!matches("[!abc]", "b"));
+        assert!(matches("[^abc]", "d"));
+        assert!(matches("[a-z]x", "qx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[[:digit:]][[:upper:]]", "1A"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("[", "["));
+    }
+
+    #[test]
+    fn test_extended_patterns() {
+        assert!(matches("@(foo|bar)", "bar"));
+        assert!(!matches("@(foo|bar)", "foobar"));
+        assert!(matches("?(x)y", "y"));
+        assert!(matches("*(ab)", "ababab"));
+        assert!(matches("*(ab)", ""));
+        assert!(!matches("+(ab)", ""));
+        assert!(matches("+(a|b)c", "abbac"));
+        assert!(matches("!(*.rs)", "main.c"));
+        assert!(!matches("!(*.rs)", "main.rs"));
+        assert!(matches("*.@(rs|toml)", "Cargo.toml"));
+        assert!(matches("@(a|+(b))", "bbb"));
+        assert!(matches("\\@(a)", "@(a)"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("a\\?", "a?"));
+    }
+}
@@ -0,0 +1,680 @@
+use std::io::{stdout, Write};
+
+use crate::escapes;
+use crate::parser::is_name;
+use crate::variables::Variables;
+
+pub struct PrintfCommand;
+
+impl PrintfCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-printf
+    pub fn execute(args: &[String], variables: &mut Variables) -> i32 {
+        let mut operands = &args[1..];
+        let mut variable: Option<&String> = None;
+        while let Some(arg) = operands.first() {
+            match arg.as_str() {
+                "-v" => match operands.get(1) {
+                    Some(name) => {
+                        variable = Some(name);
+                        operands = &operands[2..];
+                    }
+                    None => {
+                        eprintln!("printf: -v: option requires an argument");
+                        return PrintfCommand::usage();
+                    }
+                },
+                "--" => {
+                    operands = &operands[1..];
+                    break;
+                }
+                arg if arg.starts_with('-') && arg.len() > 1 => {
+                    eprintln!("printf: {}: invalid option", arg);
+                    return PrintfCommand::usage();
+                }
+                _ => break,
+            }
+        }
+        if let Some(name) = variable {
+            if !is_name(name) {
+                eprintln!("printf: `{}': not a valid identifier", name);
+                return 2;
+            }
+        }
+        let Some(format) = operands.first() else {
+            return PrintfCommand::usage();
+        };
+
+        let mut printer = Printer {
+            format: format.chars().collect(),
+            args: &operands[1..],
+            next_arg: 0,
+            output: Vec::new(),
+            status: 0,
+        };
+        printer.run();
+
+        match variable {
+            Some(name) => {
+                let value = String::from_utf8_lossy(&printer.output).into_owned();
+                if let Err(message) = variables.set(name, value) {
+                    eprintln!("printf: {}", message);
+                    return 1;
+                }
+            }
+            None => {
+                let mut stdout = stdout().lock();
+                if stdout
+                    .write_all(&printer.output)
+                    .and_then(|_| stdout.flush())
+                    .is_err()
+                {
+                    return 1;
+                }
+            }
+        }
+        printer.status
+    }
+
+    fn usage() -> i32 {
+        eprintln!("printf: usage: printf [-v var] format [arguments]");
+        2
+    }
+}
+
+/// Widths and precisions are limited so that a conversion cannot exhaust the
+/// memory
+const MAX_FIELD: usize = 1_000_000;
+
+/// A conversion specification like `%-10.3s`
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+struct Printer<'a> {
+    format: Vec<char>,
+    args: &'a [String],
+    next_arg: usize,
+    /// escapes can write bytes that are not UTF-8
+    output: Vec<u8>,
+    status: i32,
+}
+
+impl Printer<'_> {
+    /// The format is reused as long as it consumes arguments
+    fn run(&mut self) {
+        loop {
+            let consumed = self.next_arg;
+            if !self.format_once() {
+                return;
+            }
+            if self.next_arg >= self.args.len() || self.next_arg == consumed {
+                return;
+            }
+        }
+    }
+
+    /// Returns false if the output stops early
+    fn format_once(&mut self) -> bool {
+        let mut current = 0;
+        while current < self.format.len() {
+            match self.format[current] {
+                '\\' => {
+                    let (decoded, next) = escapes::decode(&self.format, current + 1);
+                    self.output.extend_from_slice(&decoded);
+                    current = next;
+                }
+                '%' => match self.conversion(current + 1) {
+                    Some(next) => current = next,
+                    None => return false,
+                },
+                c => {
+                    escapes::push_char(&mut self.output, c);
+                    current += 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// Formats the conversion specification that starts at `start`, after the
+    /// `%`. Returns the index after it, or `None` if the output stops.
+    fn conversion(&mut self, start: usize) -> Option<usize> {
+        let mut spec = Spec::default();
+        let mut current = start;
+        while let Some(&c) = self.format.get(current) {
+            match c {
+                '-' => spec.left = true,
+                '0' => spec.zero = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alternate = true,
+                _ => break,
+            }
+            current += 1;
+        }
+        if self.format.get(current) == Some(&'*') {
+            let width = self.integer_argument();
+            spec.left |= width < 0;
+            spec.width = self.field(width.unsigned_abs(), "field width")?;
+            current += 1;
+        } else {
+            let (width, next) = self.digits(current);
+            spec.width = self.field(width, "field width")?;
+            current = next;
+        }
+        if self.format.get(current) == Some(&'.') {
+            current += 1;
+            if self.format.get(current) == Some(&'*') {
+                let precision = self.integer_argument();
+                if precision >= 0 {
+                    spec.precision = Some(self.field(precision as u64, "precision")?);
+                }
+                current += 1;
+            } else {
+                let (precision, next) = self.digits(current);
+                spec.precision = Some(self.field(precision, "precision")?);
+                current = next;
+            }
+        }
+
+        let Some(&conversion) = self.format.get(current) else {
+            eprintln!("printf: `%': missing format character");
+            self.status = 1;
+            return None;
+        };
+        let formatted = match conversion {
+            '%' => "%".to_string(),
+            's' => {
+                let arg = self.string_argument();
+                let arg = match spec.precision {
+                    Some(precision) => arg.chars().take(precision).collect(),
+                    None => arg,
+                };
+                pad(&spec, "", &arg, false)
+            }
+            'c' => {
+                let arg = self.string_argument();
+                pad(&spec, "", &arg.chars().take(1).collect::<String>(), false)
+            }
+            'b' => {
+                let arg = self.string_argument();
+                let (decoded, stopped) = decode_echo(&arg);
+                // pad around the bytes, which need not be UTF-8
+                let width = String::from_utf8_lossy(&decoded).chars().count();
+                let padding = " ".repeat(spec.width.saturating_sub(width));
+                if !spec.left {
+                    self.output.extend_from_slice(padding.as_bytes());
+                }
+                self.output.extend_from_slice(&decoded);
+                if spec.left {
+                    self.output.extend_from_slice(padding.as_bytes());
+                }
+                if stopped {
+                    return None;
+                }
+                return Some(current + 1);
+            }
+            'q' => pad(&spec, "", &quote(&self.string_argument()), false),
+            'd' | 'i' | 'u' | 'o' | 'x' | 'X' => {
+                let value = self.integer_argument();
+                format_integer(&spec, conversion, value)
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let value = self.float_argument();
+                format_float(&spec, conversion, value)
+            }
+            c => {
+                eprintln!("printf: `{}': invalid format character", c);
+                self.status = 1;
+                return None;
+            }
+        };
+        self.output.extend_from_slice(formatted.as_bytes());
+        Some(current + 1)
+    }
+
+    /// A width or precision written in the format. Only as many digits as
+    /// fit in a `u32` are read, so that extra ones make it too large.
+    fn digits(&self, start: usize) -> (u64, usize) {
+        let (value, next) = escapes::parse_digits(&self.format, start, 10, 10);
+        match self.format.get(next).is_some_and(char::is_ascii_digit) {
+            true => (u64::MAX, next),
+            false => (value as u64, next),
+        }
+    }
+
+    /// Checks a width or precision, `None` if it is too large and the output
+    /// stops
+    fn field(&mut self, value: u64, name: &str) -> Option<usize> {
+        if value > MAX_FIELD as u64 {
+            eprintln!("printf: invalid {}", name);
+            self.status = 1;
+            return None;
+        }
+        Some(value as usize)
+    }
+
+    fn next_argument(&mut self) -> Option<&str> {
+        let arg = self.args.get(self.next_arg)?;
+        self.next_arg += 1;
+        Some(arg)
+    }
+
+    /// Missing arguments are empty strings
+    fn string_argument(&mut self) -> String {
+        self.next_argument().unwrap_or_default().to_string()
+    }
+
+    /// Decimal, octal with a leading `0`, hexadecimal with a leading `0x`, or
+    /// the character code of the character after a leading quote. Missing
+    /// arguments are 0, and values out of range are clamped.
+    fn integer_argument(&mut self) -> i64 {
+        let Some(arg) = self.next_argument().map(str::to_string) else {
+            return 0;
+        };
+        let trimmed = arg.trim_start();
+        if let Some(rest) = trimmed.strip_prefix(['\'', '"']) {
+            return rest.chars().next().map_or(0, |c| c as i64);
+        }
+
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (radix, digits) = match unsigned.strip_prefix("0x").or(unsigned.strip_prefix("0X")) {
+            Some(hex) => (16, hex),
+            None if unsigned.len() > 1 && unsigned.starts_with('0') => (8, &unsigned[1..]),
+            None => (10, unsigned),
+        };
+        let end = digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len());
+        if end == 0 || end < digits.len() {
+            self.invalid_number(&arg);
+        }
+        let magnitude = match end {
+            0 => Some(0),
+            _ => u64::from_str_radix(&digits[..end], radix).ok(),
+        };
+        let value = match (negative, magnitude) {
+            (true, Some(magnitude)) => 0i64.checked_sub_unsigned(magnitude),
+            (false, Some(magnitude)) => i64::try_from(magnitude).ok(),
+            (_, None) => None,
+        };
+        value.unwrap_or_else(|| {
+            eprintln!("printf: warning: {}: Numerical result out of range", arg);
+            self.status = 1;
+            if negative {
+                i64::MIN
+            } else {
+                i64::MAX
+            }
+        })
+    }
+
+    fn float_argument(&mut self) -> f64 {
+        let Some(arg) = self.next_argument().map(str::to_string) else {
+            return 0.0;
+        };
+        let trimmed = arg.trim_start();
+        if let Some(rest) = trimmed.strip_prefix(['\'', '"']) {
+            return rest.chars().next().map_or(0.0, |c| c as u32 as f64);
+        }
+        if let Ok(value) = trimmed.parse::<f64>() {
+            return value;
+        }
+        // like strtod, use the longest prefix that is a number
+        self.invalid_number(&arg);
+        (0..trimmed.len())
+            .rev()
+            .filter(|&end| trimmed.is_char_boundary(end))
+            .find_map(|end| trimmed[..end].parse::<f64>().ok())
+            .unwrap_or(0.0)
+    }
+
+    fn invalid_number(&mut self, arg: &str) {
+        eprintln!("printf: {}: invalid number", arg);
+        self.status = 1;
+    }
+}
+
+/// Pad `sign` followed by `body` to the width of `spec`. Zero padding goes
+/// between the sign and the body of numbers.
+fn pad(spec: &Spec, sign: &str, body: &str, numeric: bool) -> String {
+    let len = sign.chars().count() + body.chars().count();
+    let padding = spec.width.saturating_sub(len);
+    if spec.left {
+        format!("{}{}{}", sign, body, " ".repeat(padding))
+    } else if spec.zero && numeric {
+        format!("{}{}{}", sign, "0".repeat(padding), body)
+    } else {
+        format!("{}{}{}", " ".repeat(padding), sign, body)
+    }
+}
+
+fn sign(spec: &Spec, negative: bool) -> &'static str {
+    if negative {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    }
+}
+
+fn format_integer(spec: &Spec, conversion: char, value: i64) -> String {
+    let (sign, mut digits, prefix) = match conversion {
+        'd' | 'i' => (sign(spec, value < 0), value.unsigned_abs().to_string(), ""),
+        'u' => ("", (value as u64).to_string(), ""),
+        'o' => ("", format!("{:o}", value as u64), ""),
+        'x' => ("", format!("{:x}", value as u64), "0x"),
+        _ => ("", format!("{:X}", value as u64), "0X"),
+    };
+    if let Some(precision) = spec.precision {
+        if precision == 0 && value == 0 {
+            digits.clear();
+        }
+        if digits.len() < precision {
+            digits = "0".repeat(precision - digits.len()) + &digits;
+        }
+    }
+    let prefix = match conversion {
+        'o' if spec.alternate && !digits.starts_with('0') => "0",
+        'x' | 'X' if spec.alternate && value != 0 => prefix,
+        _ => "",
+    };
+    // the precision takes over from zero padding
+    let numeric = spec.precision.is_none();
+    pad(spec, &format!("{}{}", sign, prefix), &digits, numeric)
+}
+
+fn format_float(spec: &Spec, conversion: char, value: f64) -> String {
+    let precision = spec.precision.unwrap_or(6);
+    let upper = conversion.is_ascii_uppercase();
+    let abs = value.abs();
+    let body = if abs.is_nan() {
+        "nan".to_string()
+    } else if abs.is_infinite() {
+        "inf".to_string()
+    } else {
+        match conversion.to_ascii_lowercase() {
+            'f' => {
+                let mut body = format!("{:.*}", precision, abs);
+                if spec.alternate && precision == 0 {
+                    body.push('.');
+                }
+                body
+            }
+            'e' => format_exponent(abs, precision, spec.alternate),
+            _ => format_general(abs, precision, spec.alternate),
+        }
+    };
+    let body = match upper {
+        true => body.to_uppercase(),
+        false => body,
+    };
+    let sign = sign(spec, value.is_sign_negative() && !value.is_nan());
+    pad(spec, sign, &body, abs.is_finite())
+}
+
+/// `%e`, like `1.500000e+00`
+fn format_exponent(abs: f64, precision: usize, alternate: bool) -> String {
+    let formatted = format!("{:.*e}", precision, abs);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let point = match alternate && precision == 0 {
+        true => ".",
+        false => "",
+    };
+    format!(
+        "{}{}e{}{:02}",
+        mantissa,
+        point,
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/// `%g` uses `%e` for very small or large numbers and `%f` otherwise, without
+/// trailing zeros
+fn format_general(abs: f64, precision: usize, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let exponent: i32 = match abs {
+        0.0 => 0,
+        _ => {
+            let formatted = format!("{:.*e}", precision - 1, abs);
+            formatted.split_once('e').unwrap().1.parse().unwrap()
+        }
+    };
+    let formatted = if exponent < -4 || exponent >= precision as i32 {
+        format_exponent(abs, precision - 1, alternate)
+    } else {
+        format!("{:.*}", (precision as i32 - 1 - exponent) as usize, abs)
+    };
+    if alternate {
+        return formatted;
+    }
+
+    let (mantissa, exponent) = match formatted.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa.to_string(), format!("e{}", exponent)),
+        None => (formatted, String::new()),
+    };
+    let mantissa = match mantissa.contains('.') {
+        true => mantissa
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string(),
+        false => mantissa,
+    };
+    mantissa + &exponent
+}
+
+/// `%b` expands backslash escapes like `echo -e`, except that octal values
+/// need no leading `0`. Returns the result and
+/// whether it stopped at `\c`.
+fn decode_echo(arg: &str) -> (Vec<u8>, bool) {
+    let chars: Vec<char> = arg.chars().collect();
+    let mut decoded = Vec::new();
+    let mut current = 0;
+    while current < chars.len() {
+        if chars[current] != '\\' {
+            escapes::push_char(&mut decoded, chars[current]);
+            current += 1;
+            continue;
+        }
+        match escapes::decode_echo(&chars, current + 1, true) {
+            Some((bytes, next)) => {
+                decoded.extend_from_slice(&bytes);
+                current = next;
+            }
+            None => return (decoded, true),
+        }
+    }
+    (decoded, false)
+}
+
+/// `%q` quotes the argument so that the shell reads it back unchanged
+fn quote(arg: &str) -> String {
+    if arg.is_empty() {
+        return "''".to_string();
+    }
+    if arg.chars().any(|c| c.is_control()) {
+        let mut quoted = "$'".to_string();
+        for c in arg.chars() {
+            match c {
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                '\r' => quoted.push_str("\\r"),
+                '\x1b' => quoted.push_str("\\E"),
+                '\'' | '\\' => {
+                    quoted.push('\\');
+                    quoted.push(c);
+                }
+                c if c.is_control() => quoted.push_str(&format!("\\{:03o}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        return quoted;
+    }
+
+    let mut quoted = String::new();
+    for (i, c) in arg.chars().enumerate() {
+        let special = !(c.is_alphanumeric() || "_-./:=@%+,".contains(c)) || (i == 0 && c == '~');
+        if special {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::Printer;
+
+    fn printf_bytes(format: &str, args: &[&str]) -> (Vec<u8>, i32) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut printer = Printer {
+            format: format.chars().collect(),
+            args: &args,
+            next_arg: 0,
+            output: Vec::new(),
+            status: 0,
+        };
+        printer.run();
+        (printer.output, printer.status)
+    }
+
+    fn printf(format: &str, args: &[&str]) -> (String, i32) {
+        let (output, status) = printf_bytes(format, args);
+        (String::from_utf8(output).unwrap(), status)
+    }
+
+    fn test(format: &str, args: &[&str], expected: &str) {
+        assert_eq!(printf(format, args), (expected.to_string(), 0));
+    }
+
+    #[test]
+    fn test_strings() {
+        test(
+            "%s-%5s|%-5s|%.2s\\n",
+            &["a", "b", "c", "def"],
+            "a-    b|c    |de\n",
+        );
+        test("%c%c%%\\t", &["xyz", ""], "x%\t");
+        test("[%s]", &["a", "b", "c"], "[a][b][c]");
+        test("%s %s;", &["a", "b", "c"], "a b;c ;");
+        test("%b|%s", &["a\\tb\\0101", "\\t"], "a\tbA|\\t");
+        test("%b%s", &["x\\cy", "z"], "x");
+        test("%q %q %q", &["a b", "", "it's\n"], "a\\ b '' $'it\\'s\\n'");
+        test("no args\\n", &[], "no args\n");
+        test("%-3b|%3b|", &["\\xe2\\x82\\xac", "é"], "€  |  é|");
+    }
+
+    #[test]
+    fn test_raw_bytes() {
+        test("\\xe2\\x82\\xac%b", &["\\0342\\0202\\0254"], "€€");
+        assert_eq!(printf_bytes("\\xff%b", &["\\0376"]), (vec![0xff, 0xfe], 0));
+    }
+
+    #[test]
+    fn test_octal_in_b() {
+        test(
+            "%b|%b|%b|%b",
+            &["\\101", "\\0101", "\\1010", "\\8"],
+            "A|A|A0|\\8",
+        );
+        assert_eq!(printf_bytes("%b", &["\\3777"]), (vec![0xff, b'7'], 0));
+    }
+
+    #[test]
+    fn test_integers() {
+        test(
+            "%d %i %+d % d %05d %-4d|",
+            &["42", "-7", "3", "3", "-42", "1"],
+            "42 -7 +3  3 -0042 1   |",
+        );
+        test(
+            "%x %X %#x %o %#o %u",
+            &["255", "0xff", "255", "8", "8", "-1"],
+            "ff FF 0xff 10 010 18446744073709551615",
+        );
+        test(
+            "%.3d %*d %-*d|",
+            &["7", "4", "5", "3", "6"],
+            "007    5 6  |",
+        );
+        test("%d %d %d", &["010", "'A", "\"a"], "8 65 97");
+        test("%d", &[], "0");
+    }
+
+    #[test]
+    fn test_floats() {
+        test(
+            "%f %.2f %8.3f %-8.1f|",
+            &["1.5", "2.345", "3.14159", "2"],
+            "1.500000 2.35    3.142 2.0     |",
+        );
+        test(
+            "%e %.2E",
+            &["12345.678", "0.000123"],
+            "1.234568e+04 1.23E-04",
+        );
+        test(
+            "%g %g %g %g %G",
+            &["100000", "1000000", "0.0001", "0.00001", "1e-10"],
+            "100000 1e+06 0.0001 1e-05 1E-10",
+        );
+        test(
+            "%g %.3g %#g",
+            &["3.14000", "2.71828", "1"],
+            "3.14 2.72 1.00000",
+        );
+        test("%+.1f %f", &["-0.04", "inf"], "-0.0 inf");
+    }
+
+    #[test]
+    fn test_invalid_numbers() {
+        assert_eq!(printf("%d|", &["12abc"]), ("12|".to_string(), 1));
+        assert_eq!(printf("%d|", &["abc"]), ("0|".to_string(), 1));
+        assert_eq!(printf("%.1f|", &["1.5x"]), ("1.5|".to_string(), 1));
+        assert_eq!(printf("a%z", &[]), ("a".to_string(), 1));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        assert_eq!(
+            printf(
+                "%d %d %d|",
+                &[
+                    "-9223372036854775808",
+                    "-9223372036854775809",
+                    "0x8000000000000000"
+                ]
+            ),
+            (
+                "-9223372036854775808 -9223372036854775808 9223372036854775807|".to_string(),
+                1
+            )
+        );
+        assert_eq!(printf("a%99999999999s|", &["x"]), ("a".to_string(), 1));
+        assert_eq!(printf("a%.1000001d|", &["1"]), ("a".to_string(), 1));
+        assert_eq!(
+            printf("a%*s|", &["-9223372036854775808", "x"]),
+            ("a".to_string(), 1)
+        );
+        assert_eq!(printf("%.*s|", &["-5", "xyz"]), ("xyz|".to_string(), 0));
+    }
+}
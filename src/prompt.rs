@@ -0,0 +1,206 @@
+use std::ffi::{CStr, CString};
+use std::mem::MaybeUninit;
+
+/// `\[` and `\]` become these markers around characters that take no room on
+/// the screen, like color sequences. They are not printed.
+pub const START_IGNORE: char = '\x01';
+pub const END_IGNORE: char = '\x02';
+
+/// What the escapes of a prompt show about the shell
+pub struct PromptContext {
+    pub home: Option<String>,
+    pub current_dir: String,
+    pub jobs: usize,
+    pub last_status: i32,
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Controlling-the-Prompt
+///
+/// Replaces the backslash escapes of `prompt`. The result is expanded like
+/// the body of a here-document afterwards, so the text the escapes insert is
+/// quoted with backslashes.
+pub fn decode(prompt: &str, context: &PromptContext) -> String {
+    let chars: Vec<char> = prompt.chars().collect();
+    let mut decoded = String::new();
+    let mut current = 0;
+    while current < chars.len() {
+        let c = chars[current];
+        current += 1;
+        if c != '\\' || current == chars.len() {
+            decoded.push(c);
+            continue;
+        }
+        let escape = chars[current];
+        current += 1;
+        let value = match escape {
+            'a' => "\x07".to_string(),
+            'd' => strftime("%a %b %d"),
+            'D' if chars.get(current) == Some(&'{') => {
+                let Some(length) = chars[current..].iter().position(|&c| c == '}') else {
+                    decoded.push_str("\\D");
+                    continue;
+                };
+                let format: String = chars[current + 1..current + length].iter().collect();
+                current += length + 1;
+                strftime(if format.is_empty() { "%X" } else { &format })
+            }
+            'e' => "\x1b".to_string(),
+            'h' => hostname().split('.').next().unwrap_or_default().to_string(),
+            'H' => hostname(),
+            'j' => context.jobs.to_string(),
+            'l' => terminal_name(),
+            'n' => "\n".to_string(),
+            'r' => "\r".to_string(),
+            's' => "rushell".to_string(),
+            't' => strftime("%H:%M:%S"),
+            'T' => strftime("%I:%M:%S"),
+            '@' => strftime("%I:%M %p"),
+            'A' => strftime("%H:%M"),
+            'u' => user_name(),
+            'v' => env!("CARGO_PKG_VERSION")
+                .rsplit_once('.')
+                .map_or(env!("CARGO_PKG_VERSION"), |(version, _)| version)
+                .to_string(),
+            'V' => env!("CARGO_PKG_VERSION").to_string(),
+            'w' => tilde(&context.current_dir, context.home.as_deref()),
+            'W' => match context.current_dir.rsplit_once('/') {
+                _ if context.home.as_deref() == Some(&context.current_dir) => "~".to_string(),
+                Some(("", "")) | None => context.current_dir.clone(),
+                Some((_, name)) => name.to_string(),
+            },
+            '?' => context.last_status.to_string(),
+            '$' => match unsafe { libc::geteuid() } {
+                0 => "#".to_string(),
+                _ => "$".to_string(),
+            },
+            '0'..='7' => {
+                let digits: String = chars[current - 1..]
+                    .iter()
+                    .take(3)
+                    .take_while(|c| c.is_digit(8))
+                    .collect();
+                current += digits.len() - 1;
+                let code = u32::from_str_radix(&digits, 8).unwrap_or(0);
+                char::from_u32(code).unwrap_or_default().to_string()
+            }
+            '\\' => "\\".to_string(),
+            '[' => START_IGNORE.to_string(),
+            ']' => END_IGNORE.to_string(),
+            _ => {
+                decoded.push('\\');
+                decoded.push(escape);
+                continue;
+            }
+        };
+        for c in value.chars() {
+            if matches!(c, '$' | '`' | '\\') {
+                decoded.push('\\');
+            }
+            decoded.push(c);
+        }
+    }
+    decoded
+}
+
+/// `path` with the home directory replaced by `~`
+fn tilde(path: &str, home: Option<&str>) -> String {
+    match home.and_then(|home| path.strip_prefix(home)) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("~{}", rest),
+        _ => path.to_string(),
+    }
+}
+
+/// The current local time formatted by strftime(3)
+fn strftime(format: &str) -> String {
+    let Ok(format) = CString::new(format) else {
+        return String::new();
+    };
+    let mut buffer = [0u8; 256];
+    let length = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut time = MaybeUninit::<libc::tm>::uninit();
+        if libc::localtime_r(&now, time.as_mut_ptr()).is_null() {
+            return String::new();
+        }
+        libc::strftime(
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            format.as_ptr(),
+            time.as_ptr(),
+        )
+    };
+    String::from_utf8_lossy(&buffer[..length]).to_string()
+}
+
+fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } < 0 {
+        return String::new();
+    }
+    CStr::from_bytes_until_nul(&buffer)
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn user_name() -> String {
+    unsafe {
+        let passwd = libc::getpwuid(libc::geteuid());
+        if passwd.is_null() {
+            return std::env::var("USER").unwrap_or_default();
+        }
+        CStr::from_ptr((*passwd).pw_name)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// The base name of the terminal of standard input, like `pts/0` without
+/// `pts/`
+fn terminal_name() -> String {
+    unsafe {
+        let name = libc::ttyname(libc::STDIN_FILENO);
+        if name.is_null() {
+            return "tty".to_string();
+        }
+        let name = CStr::from_ptr(name).to_string_lossy();
+        name.rsplit('/').next().unwrap_or_default().to_string()
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, PromptContext, END_IGNORE, START_IGNORE};
+
+    fn context(current_dir: &str) -> PromptContext {
+        PromptContext {
+            home: Some("/home/me".to_string()),
+            current_dir: current_dir.to_string(),
+            jobs: 2,
+            last_status: 1,
+        }
+    }
+
+    #[test]
+    fn test_directories() {
+        assert_eq!(decode("\\w \\W", &context("/home/me")), "~ ~");
+        assert_eq!(decode("\\w \\W", &context("/home/me/src")), "~/src src");
+        assert_eq!(
+            decode("\\w \\W", &context("/home/meadow")),
+            "/home/meadow meadow"
+        );
+        assert_eq!(decode("\\w \\W", &context("/")), "/ /");
+    }
+
+    #[test]
+    fn test_escapes() {
+        let context = context("/$HOME");
+        assert_eq!(decode("[\\j \\?]\\n\\141", &context), "[2 1]\na");
+        assert_eq!(decode("\\W \\\\ \\q $x", &context), "\\$HOME \\\\ \\q $x");
+        assert_eq!(
+            decode("\\[\\e[1m\\]>", &context),
+            format!("{}\x1b[1m{}>", START_IGNORE, END_IGNORE)
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use std::io::{self, ErrorKind, Write};
+use std::time::{Duration, Instant};
+
+use libc::c_int;
+
+use crate::line_reader;
+use crate::parser::is_name;
+use crate::signals;
+use crate::variables::Variables;
+
+pub struct ReadCommand;
+
+/// Options of `read`
+struct Options {
+    raw: bool,
+    silent: bool,
+    prompt: Option<String>,
+    array: Option<String>,
+    delimiter: char,
+    count: Option<usize>,
+    timeout: Option<Duration>,
+    fd: c_int,
+}
+
+/// Why reading stopped before the delimiter
+enum Stop {
+    EndOfInput,
+    Timeout,
+    Interrupted,
+    Error(io::Error),
+}
+
+impl ReadCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-read
+    pub fn execute(args: &[String], variables: &mut Variables) -> i32 {
+        let mut options = Options {
+            raw: false,
+            silent: false,
+            prompt: None,
+            array: None,
+            delimiter: '\n',
+            count: None,
+            timeout: None,
+            fd: 0,
+        };
+        let mut index = 1;
+        while let Some(arg) = args.get(index) {
+            index += 1;
+            if arg == "--" {
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                index -= 1;
+                break;
+            }
+            let mut chars = arg[1..].chars();
+            while let Some(option) = chars.next() {
+                match option {
+                    'r' => options.raw = true,
+                    's' => options.silent = true,
+                    'a' | 'd' | 'n' | 'p' | 't' | 'u' => {
+                        let rest: String = chars.by_ref().collect();
+                        let value = if !rest.is_empty() {
+                            rest
+                        } else if let Some(value) = args.get(index) {
+                            index += 1;
+                            value.clone()
+                        } else {
+                            eprintln!("read: -{}: option requires an argument", option);
+                            return ReadCommand::usage();
+                        };
+                        if let Err(status) = options.set(option, value) {
+                            return status;
+                        }
+                    }
+                    _ => {
+                        eprintln!("read: -{}: invalid option", option);
+                        return ReadCommand::usage();
+                    }
+                }
+            }
+        }
+
+        let names = &args[index..];
+        for name in names.iter().chain(&options.array) {
+            if !is_name(name) {
+                eprintln!("read: `{}': not a valid identifier", name);
+                return 1;
+            }
+        }
+        if options.timeout == Some(Duration::ZERO) {
+            return if poll(options.fd, Duration::ZERO).unwrap_or(false) {
+                0
+            } else {
+                1
+            };
+        }
+
+        let terminal = unsafe { libc::isatty(options.fd) } == 1;
+        if let (Some(prompt), true) = (&options.prompt, terminal) {
+            eprint!("{}", prompt);
+            io::stderr().flush().ok();
+        }
+        let saved_termios = if options.silent && terminal {
+            disable_echo(options.fd)
+        } else {
+            None
+        };
+        let (line, stop) = read_input(&options);
+        if let Some(termios) = saved_termios {
+            unsafe { libc::tcsetattr(options.fd, libc::TCSADRAIN, &termios) };
+        }
+
+        match stop {
+            Some(Stop::Interrupted) => return 128 + libc::SIGINT,
+            Some(Stop::Error(error)) => {
+                eprintln!("read: read error: {}: {}", options.fd, error);
+                return 1;
+            }
+            _ => {}
+        }
+
+        let ifs = variables.get("IFS").unwrap_or_else(|| " \t\n".to_string());
+        let result = if let Some(array) = &options.array {
+            variables.set_array(array, split(&line, &ifs, None))
+        } else if names.is_empty() {
+            let line: String = line.iter().map(|&(c, _)| c).collect();
+            variables.set("REPLY", line)
+        } else {
+            names
+                .iter()
+                .zip(split(&line, &ifs, Some(names.len())))
+                .try_for_each(|(name, value)| variables.set(name, value))
+        };
+        if let Err(message) = result {
+            eprintln!("read: {}", message);
+            return 1;
+        }
+
+        match stop {
+            None => 0,
+            Some(Stop::Timeout) => 128 + libc::SIGALRM,
+            Some(_) => 1,
+        }
+    }
+
+    fn usage() -> i32 {
+        eprintln!(
+            "read: usage: read [-rs] [-a array] [-d delim] [-n nchars] [-p prompt] [-t timeout] [-u fd] [name ...]"
+        );
+        2
+    }
+}
+
+impl Options {
+    fn set(&mut self, option: char, value: String) -> Result<(), i32> {
+        match option {
+            'a' => self.array = Some(value),
+            'd' => self.delimiter = value.chars().next().unwrap_or('\0'),
+            'p' => self.prompt = Some(value),
+            'n' => match value.parse() {
+                Ok(count) => self.count = Some(count),
+                Err(_) => {
+                    eprintln!("read: {}: invalid number", value);
+                    return Err(1);
+                }
+            },
+            't' => match value.parse::<f64>() {
+                Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+                    self.timeout = Some(Duration::from_secs_f64(seconds))
+                }
+                _ => {
+                    eprintln!("read: {}: invalid timeout specification", value);
+                    return Err(1);
+                }
+            },
+            'u' => match value.parse() {
+                Ok(fd) if unsafe { libc::fcntl(fd, libc::F_GETFD) } != -1 => self.fd = fd,
+                _ => {
+                    eprintln!("read: {}: invalid file descriptor", value);
+                    return Err(1);
+                }
+            },
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+/// Reads up to the delimiter, which is not included. Each character is paired
+/// with whether it was escaped with a backslash, which protects it from field
+/// splitting. Returns why reading stopped early, if it did.
+fn read_input(options: &Options) -> (Vec<(char, bool)>, Option<Stop>) {
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut line = Vec::new();
+    let mut escaped = false;
+    loop {
+        if options.count.is_some_and(|count| line.len() >= count) {
+            return (line, None);
+        }
+        let c = match read_char(options.fd, deadline) {
+            Ok(Some(c)) => c,
+            Ok(None) => return (line, Some(Stop::EndOfInput)),
+            Err(error) => {
+                let stop = match error.kind() {
+                    ErrorKind::TimedOut => Stop::Timeout,
+                    ErrorKind::Interrupted => Stop::Interrupted,
+                    _ => Stop::Error(error),
+                };
+                return (line, Some(stop));
+            }
+        };
+        if escaped {
+            escaped = false;
+            // backslash-newline continues the line
+            if c != '\n' {
+                line.push((c, true));
+            }
+        } else if c == '\\' && !options.raw {
+            escaped = true;
+        } else if c == options.delimiter {
+            return (line, None);
+        } else {
+            line.push((c, false));
+        }
+    }
+}
+
+/// Reads one character, a byte at a time so that nothing after it is consumed
+/// from input shared with the shell and other commands
+fn read_char(fd: c_int, deadline: Option<Instant>) -> io::Result<Option<char>> {
+    let Some(first) = read_byte(fd, deadline)? else {
+        return Ok(None);
+    };
+    let len = match first {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    while bytes.len() < len {
+        match read_byte(fd, deadline)? {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).chars().next())
+}
+
+fn read_byte(fd: c_int, deadline: Option<Instant>) -> io::Result<Option<u8>> {
+    if let Some(deadline) = deadline {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if !poll(fd, remaining)? {
+            return Err(ErrorKind::TimedOut.into());
+        }
+    }
+    line_reader::read_byte(fd)
+}
+
+/// Waits until `fd` is readable, false if `timeout` passes first
+fn poll(fd: c_int, timeout: Duration) -> io::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let millis = remaining.as_millis().min(c_int::MAX as u128) as c_int;
+        if unsafe { libc::poll(&mut pollfd, 1, millis) } != -1 {
+            return Ok(pollfd.revents != 0);
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != ErrorKind::Interrupted || signals::is_pending(libc::SIGINT) {
+            return Err(error);
+        }
+    }
+}
+
+/// Turns off echoing for `-s`, returning the settings to restore
+fn disable_echo(fd: c_int) -> Option<libc::termios> {
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+        return None;
+    }
+    let saved = termios;
+    termios.c_lflag &= !libc::ECHO;
+    unsafe { libc::tcsetattr(fd, libc::TCSADRAIN, &termios) };
+    Some(saved)
+}
+
+/// Splits `line` into `count` fields with IFS, the last field getting the rest
+/// of the line without trailing IFS whitespace. Without a count, every field
+/// is split off, as for `-a`.
+fn split(line: &[(char, bool)], ifs: &str, count: Option<usize>) -> Vec<String> {
+    let is_whitespace = |&(c, escaped): &(char, bool)| {
+        !escaped && matches!(c, ' ' | '\t' | '\n') && ifs.contains(c)
+    };
+    let is_delimiter = |&(c, escaped): &(char, bool)| !escaped && ifs.contains(c);
+    let collect = |chars: &[(char, bool)]| chars.iter().map(|&(c, _)| c).collect::<String>();
+
+    let mut fields = Vec::new();
+    let mut i = 0;
+    while i < line.len() && is_whitespace(&line[i]) {
+        i += 1;
+    }
+    while count.map_or(true, |count| fields.len() + 1 < count) && i < line.len() {
+        let start = i;
+        while i < line.len() && !is_delimiter(&line[i]) {
+            i += 1;
+        }
+        fields.push(collect(&line[start..i]));
+        while i < line.len() && is_whitespace(&line[i]) {
+            i += 1;
+        }
+        if i < line.len() && is_delimiter(&line[i]) {
+            i += 1;
+            while i < line.len() && is_whitespace(&line[i]) {
+                i += 1;
+            }
+        }
+    }
+    let Some(count) = count else {
+        return fields;
+    };
+    let mut end = line.len();
+    while end > i && is_whitespace(&line[end - 1]) {
+        end -= 1;
+    }
+    fields.push(collect(&line[i..end]));
+    fields.resize(count, String::new());
+    fields
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_str(line: &str, ifs: &str, count: usize) -> Vec<String> {
+        let line: Vec<(char, bool)> = line.chars().map(|c| (c, false)).collect();
+        split(&line, ifs, Some(count))
+    }
+
+    #[test]
+    fn test_splitting() {
+        assert_eq!(split_str("  a  b  c  ", " \t\n", 2), vec!["a", "b  c"]);
+        assert_eq!(split_str("a b", " \t\n", 3), vec!["a", "b", ""]);
+        assert_eq!(split_str("a:b::c", ":", 4), vec!["a", "b", "", "c"]);
+        assert_eq!(split_str("a : b : c", " :", 2), vec!["a", "b : c"]);
+        assert_eq!(split_str("  a b  ", "", 1), vec!["  a b  "]);
+    }
+
+    #[test]
+    fn test_escaped_characters_do_not_split() {
+        let line = vec![
+            ('a', false),
+            (' ', true),
+            ('b', false),
+            (' ', false),
+            ('c', false),
+        ];
+        assert_eq!(split(&line, " ", Some(2)), vec!["a b", "c"]);
+    }
+
+    #[test]
+    fn test_splitting_all_fields() {
+        let line: Vec<(char, bool)> = " a b:c ".chars().map(|c| (c, false)).collect();
+        assert_eq!(split(&line, " :", None), vec!["a", "b", "c"]);
+        assert_eq!(split(&[], " ", None), Vec::<String>::new());
+    }
+}
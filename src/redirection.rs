@@ -0,0 +1,133 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::IntoRawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use libc::c_int;
+
+use crate::ast::RedirectionOperator;
+
+/// File descriptors replaced by redirections of a builtin, a function or a
+/// compound command running in the shell process, so that they can be put
+/// back once it is done
+pub struct SavedFds {
+    /// the redirected descriptor and a copy of what it was, `None` if it was
+    /// closed
+    fds: Vec<(c_int, Option<c_int>)>,
+}
+
+impl SavedFds {
+    pub fn new() -> Self {
+        SavedFds { fds: Vec::new() }
+    }
+
+    fn save(&mut self, fd: c_int) {
+        if self.fds.iter().any(|(saved, _)| *saved == fd) {
+            return;
+        }
+        // keep the copy out of the way of the descriptors scripts use
+        let copy = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 10) };
+        self.fds.push((fd, (copy >= 0).then_some(copy)));
+    }
+
+    pub fn restore(self) {
+        for (fd, copy) in self.fds.into_iter().rev() {
+            unsafe {
+                match copy {
+                    Some(copy) => {
+                        libc::dup2(copy, fd);
+                        libc::close(copy);
+                    }
+                    None => {
+                        libc::close(fd);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Open the file that `operator` redirects to. With `noclobber`, `>` and `&>`
+/// refuse to truncate an existing regular file.
+pub fn open(path: &str, operator: RedirectionOperator, noclobber: bool) -> io::Result<c_int> {
+    if noclobber
+        && matches!(
+            operator,
+            RedirectionOperator::Output | RedirectionOperator::OutputAndError
+        )
+        && fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "cannot overwrite existing file",
+        ));
+    }
+    let mut options = OpenOptions::new();
+    match operator {
+        RedirectionOperator::Input => options.read(true),
+        RedirectionOperator::ReadWrite => options.read(true).write(true).create(true),
+        RedirectionOperator::Append | RedirectionOperator::AppendOutputAndError => {
+            options.append(true).create(true)
+        }
+        _ => options.write(true).create(true).truncate(true),
+    };
+    options.mode(0o666);
+    Ok(options.open(path)?.into_raw_fd())
+}
+
+/// A file descriptor to read `contents` from, for here-documents and
+/// here-strings. Uses an unlinked temporary file, which unlike a pipe does
+/// not block on large contents.
+pub fn here_document(contents: &str) -> io::Result<c_int> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "rushell-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    file.write_all(contents.as_bytes())?;
+    file.rewind()?;
+    Ok(file.into_raw_fd())
+}
+
+/// Make `fd` refer to what `source` refers to, or close it if `source` is
+/// `None`. `source` stays open.
+pub fn duplicate(source: Option<c_int>, fd: c_int, saved: Option<&mut SavedFds>) -> io::Result<()> {
+    if let Some(saved) = saved {
+        saved.save(fd);
+    }
+    let result = match source {
+        Some(source) if source == fd => {
+            // the descriptor must not be closed when it is executed
+            unsafe { libc::fcntl(fd, libc::F_SETFD, 0) }
+        }
+        Some(source) => unsafe { libc::dup2(source, fd) },
+        None => unsafe { libc::close(fd) },
+    };
+    match result {
+        -1 if source.is_some() => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Like `duplicate`, but closes `source` which was opened for the redirection
+pub fn replace(source: c_int, fd: c_int, saved: Option<&mut SavedFds>) -> io::Result<()> {
+    if source == fd {
+        // files are opened close-on-exec, but the redirection is for commands
+        unsafe { libc::fcntl(fd, libc::F_SETFD, 0) };
+        return Ok(());
+    }
+    let result = duplicate(Some(source), fd, saved);
+    unsafe {
+        libc::close(source);
+    }
+    result
+}
@@ -0,0 +1,156 @@
+use std::ffi::CString;
+
+/// A POSIX extended regular expression, compiled by regcomp(3), for
+/// `[[ string =~ regex ]]`
+///
+/// https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/V1_chap09.html#tag_09_04
+pub struct Regex {
+    // regexec(3) is given the address of the compiled expression, so it stays
+    // in place
+    regex: Box<libc::regex_t>,
+    groups: usize,
+}
+
+impl Regex {
+    /// `None` if `pattern` is not a valid extended regular expression
+    pub fn new(pattern: &str) -> Option<Regex> {
+        let c_pattern = CString::new(pattern).ok()?;
+        let mut regex = Box::new(unsafe { std::mem::zeroed::<libc::regex_t>() });
+        if unsafe { libc::regcomp(&mut *regex, c_pattern.as_ptr(), libc::REG_EXTENDED) } != 0 {
+            return None;
+        }
+        Some(Regex {
+            regex,
+            groups: group_count(pattern),
+        })
+    }
+
+    /// The text of the leftmost longest match followed by the text of each
+    /// group, empty for groups that did not take part in it. `None` if
+    /// `string` does not match.
+    pub fn captures(&self, string: &str) -> Option<Vec<String>> {
+        let c_string = CString::new(string).ok()?;
+        let unmatched = libc::regmatch_t {
+            rm_so: -1,
+            rm_eo: -1,
+        };
+        let mut matches = vec![unmatched; self.groups + 1];
+        let status = unsafe {
+            libc::regexec(
+                &*self.regex,
+                c_string.as_ptr(),
+                matches.len(),
+                matches.as_mut_ptr(),
+                0,
+            )
+        };
+        if status != 0 {
+            return None;
+        }
+        let bytes = string.as_bytes();
+        let captures = matches
+            .iter()
+            .map(|group| match group.rm_so {
+                -1 => String::new(),
+                start => {
+                    let range = start as usize..group.rm_eo as usize;
+                    String::from_utf8_lossy(&bytes[range]).into_owned()
+                }
+            })
+            .collect();
+        Some(captures)
+    }
+}
+
+impl Drop for Regex {
+    fn drop(&mut self) {
+        unsafe { libc::regfree(&mut *self.regex) };
+    }
+}
+
+/// The number of parenthesized groups in a valid `pattern`, which `regex_t`
+/// does not expose on every system
+fn group_count(pattern: &str) -> usize {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut count = 0;
+    let mut current = 0;
+    while current < chars.len() {
+        match chars[current] {
+            '\\' => current += 1,
+            '(' => count += 1,
+            '[' => current = bracket_expression_end(&chars, current),
+            _ => {}
+        }
+        current += 1;
+    }
+    count
+}
+
+/// The index of the `]` that closes the bracket expression at `start`. A `]`
+/// right after the opening bracket or `[^` is an ordinary character, and so
+/// are the brackets of `[:class:]`, `[.symbol.]` and `[=equivalent=]`.
+fn bracket_expression_end(chars: &[char], start: usize) -> usize {
+    let mut current = start + 1;
+    if chars.get(current) == Some(&'^') {
+        current += 1;
+    }
+    if chars.get(current) == Some(&']') {
+        current += 1;
+    }
+    while current < chars.len() {
+        match (chars[current], chars.get(current + 1)) {
+            (']', _) => return current,
+            ('[', Some(&delimiter @ (':' | '.' | '='))) => {
+                current += 2;
+                while current + 1 < chars.len()
+                    && !(chars[current] == delimiter && chars[current + 1] == ']')
+                {
+                    current += 1;
+                }
+                current += 1;
+            }
+            _ => {}
+        }
+        current += 1;
+    }
+    chars.len()
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{group_count, Regex};
+
+    fn captures(pattern: &str, string: &str) -> Option<Vec<String>> {
+        Regex::new(pattern).unwrap().captures(string)
+    }
+
+    #[test]
+    fn test_leftmost_longest() {
+        assert_eq!(
+            captures("(a|ab)(c)?", "xab"),
+            Some(vec!["ab".to_string(), "ab".to_string(), String::new()])
+        );
+        assert_eq!(captures("b+", "abbbc"), Some(vec!["bbb".to_string()]));
+        assert_eq!(captures("^b", "ab"), None);
+    }
+
+    #[test]
+    fn test_extended_syntax() {
+        assert!(Regex::new("([").is_none());
+        assert_eq!(captures("\\d", "5"), None);
+        assert_eq!(
+            captures("[[:digit:]]{2}", "a123"),
+            Some(vec!["12".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_group_count() {
+        assert_eq!(group_count("(a)(b(c))"), 3);
+        assert_eq!(group_count("\\((a)[(]"), 1);
+        assert_eq!(group_count("[]()][[:alpha:](]"), 0);
+        assert_eq!(group_count("[^](](x)"), 1);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::shell::{Jump, Shell};
+
+pub struct ReturnCommand;
+
+impl ReturnCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-return
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        if !shell.can_return() {
+            eprintln!("return: can only `return' from a function or sourced script");
+            return 1;
+        }
+        if args.len() > 2 {
+            eprintln!("return: too many arguments");
+            return 1;
+        }
+
+        let status = match args.get(1) {
+            None => shell.last_status(),
+            Some(arg) => match arg.trim().parse::<i64>() {
+                Ok(n) => n.rem_euclid(256) as i32,
+                Err(_) => {
+                    eprintln!("return: {}: numeric argument required", arg);
+                    2
+                }
+            },
+        };
+        shell.jump(Jump::Return);
+        status
+    }
+}
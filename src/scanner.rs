@@ -1,14 +1,21 @@
 use std::fmt::Display;
 
+use crate::escapes;
+use crate::parser::is_name;
 use crate::token::{Token, TokenType};
 
 pub struct Scanner {
-    source: String,
+    source: Vec<char>,
+    /// `shopt -s extglob`: `?(...)`, `*(...)`, `+(...)`, `@(...)` and
+    /// `!(...)` are part of words. They always are inside `[[ ]]`.
+    pub extglob: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct ScannerError {
     pub message: String,
+    /// the source ended in the middle of a token, more input may complete it
+    pub incomplete: bool,
 }
 
 impl Display for ScannerError {
@@ -17,66 +24,161 @@ impl Display for ScannerError {
     }
 }
 
+/// A here-document operator whose body has not been read yet
+struct PendingHereDocument {
+    /// index of the operator token
+    operator: usize,
+    delimiter: String,
+    /// `<<-` removes leading tabs from the lines and the delimiter
+    strip_tabs: bool,
+}
+
+impl PendingHereDocument {
+    fn unterminated(&self) -> ScannerError {
+        ScannerError {
+            message: format!(
+                "here-document delimited by end-of-file (wanted `{}')",
+                self.delimiter
+            ),
+            incomplete: true,
+        }
+    }
+}
+
 impl Scanner {
     pub fn new(source: String) -> Self {
-        Scanner { source }
+        Scanner {
+            source: source.chars().collect(),
+            extglob: false,
+        }
     }
 
     pub fn scan_tokens(&self) -> Result<Vec<Token>, ScannerError> {
-        enum Arm {
-            SingleQuoted,
-            DoubleQuoted,
-        }
-
-        let mut previous_arm: Option<Arm> = None;
         let mut tokens: Vec<Token> = Vec::new();
         let mut current = 0;
-        let mut start: usize;
+        // `<<` or `<<-` operator whose delimiter is the next word
+        let mut here_document_operator: Option<usize> = None;
+        // here-documents whose body starts after the next newline
+        let mut pending_here_documents: Vec<PendingHereDocument> = Vec::new();
+        // inside `[[ ]]`, where the word after `=~` is a regular expression
+        let mut in_conditional = false;
 
         while current < self.source.len() {
-            start = current;
-
-            let c = self.source.chars().nth(current).unwrap();
-            current += 1;
+            let c = self.source[current];
+            // a regular expression may start with characters like `(`
+            let regex = in_conditional
+                && !matches!(c, ')' | ';')
+                && tokens
+                    .last()
+                    .is_some_and(|token| token.type_ == TokenType::String && token.raw == "=~");
             if c == ' ' || c == '\t' {
-                previous_arm = None;
-            } else if c == '\'' {
-                let value = self.scan_single_quoted_string(start)?;
-                tokens.push(Token::new(TokenType::String, value.1));
-                current = value.0;
-                previous_arm = Some(Arm::SingleQuoted);
-            } else if c == '"' {
-                let value = self.scan_double_quoted_string(start)?;
-                tokens.push(Token::new(TokenType::String, value.1));
-                current = value.0;
-                previous_arm = Some(Arm::DoubleQuoted);
-            } else {
-                let value = self.scan_unquoted_word(start)?;
-                match previous_arm {
-                    Some(Arm::SingleQuoted) | Some(Arm::DoubleQuoted) => {
-                        if let Some(mut token) = tokens.pop() {
-                            token.lexeme.push_str(value.1.as_str());
-                            tokens.push(token);
-                        }
-                    }
-                    None => tokens.push(Token::new(TokenType::String, value.1)),
+                current += 1;
+            } else if c == '\\' && self.source.get(current + 1) == Some(&'\n') {
+                // line continuation
+                current += 2;
+                if current == self.source.len() {
+                    return Err(self.unexpected_eof_after_backslash());
+                }
+            } else if c == '#' {
+                // https://www.gnu.org/software/bash/manual/bash.html#Comments
+                // a word starting with `#` and the rest of the line are
+                // ignored, which also covers the `#!` line of scripts
+                while current < self.source.len() && self.source[current] != '\n' {
+                    current += 1;
+                }
+            } else if c == '\n' {
+                current += 1;
+                tokens.push(Token::new(TokenType::Newline, "\n".to_string()));
+                for here_document in pending_here_documents.drain(..) {
+                    let (end, body) = self.scan_here_document(current, &here_document)?;
+                    tokens[here_document.operator].here_document = Some(body);
+                    current = end;
                 }
-                previous_arm = None;
+            } else if let Some((type_, lexeme)) = self.scan_operator(current).filter(|_| !regex) {
+                current += lexeme.len();
+                if lexeme == "<<" || lexeme == "<<-" {
+                    here_document_operator = Some(tokens.len());
+                }
+                tokens.push(Token::new(type_, lexeme.to_string()));
+            } else if self.is_metacharacter(c) && !regex {
+                return Err(ScannerError {
+                    message: format!("syntax error near unexpected token `{}'", c),
+                    incomplete: false,
+                });
+            } else {
+                // quoted and unquoted parts that are not separated by blanks
+                // make up a single word
+                let value = self.scan_word(current, regex, self.extglob || in_conditional)?;
+                let raw: String = self.source[current..value.0].iter().collect();
                 current = value.0;
+                let next = self.source.get(current).copied();
+                if raw.chars().all(|c| c.is_ascii_digit()) && matches!(next, Some('<' | '>')) {
+                    tokens.push(Token::new(TokenType::IoNumber, raw));
+                    continue;
+                }
+                if let Some(operator) = here_document_operator.take() {
+                    pending_here_documents.push(PendingHereDocument {
+                        operator,
+                        delimiter: value.1.clone(),
+                        strip_tabs: tokens[operator].lexeme == "<<-",
+                    });
+                }
+                match raw.as_str() {
+                    "[[" => in_conditional = true,
+                    "]]" => in_conditional = false,
+                    _ => {}
+                }
+                tokens.push(Token::word(value.1, raw));
             }
         }
 
+        if let Some(here_document) = pending_here_documents.first() {
+            return Err(here_document.unterminated());
+        }
+
         tokens.push(Token::new(TokenType::Eof, "".to_string()));
         Ok(tokens)
     }
 
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-control-operator
+    fn scan_operator(&self, start: usize) -> Option<(TokenType, &'static str)> {
+        let next = self.source.get(start + 1).copied();
+        let after_next = self.source.get(start + 2).copied();
+        match (self.source[start], next) {
+            ('<', Some('<')) => match after_next {
+                Some('<') => Some((TokenType::Redirection, "<<<")),
+                Some('-') => Some((TokenType::Redirection, "<<-")),
+                _ => Some((TokenType::Redirection, "<<")),
+            },
+            ('<', Some('&')) => Some((TokenType::Redirection, "<&")),
+            ('<', Some('>')) => Some((TokenType::Redirection, "<>")),
+            ('<', _) => Some((TokenType::Redirection, "<")),
+            ('>', Some('>')) => Some((TokenType::Redirection, ">>")),
+            ('>', Some('&')) => Some((TokenType::Redirection, ">&")),
+            ('>', Some('|')) => Some((TokenType::Redirection, ">|")),
+            ('>', _) => Some((TokenType::Redirection, ">")),
+            ('&', Some('>')) => match after_next {
+                Some('>') => Some((TokenType::Redirection, "&>>")),
+                _ => Some((TokenType::Redirection, "&>")),
+            },
+            ('|', Some('|')) => Some((TokenType::OrIf, "||")),
+            ('|', _) => Some((TokenType::Pipe, "|")),
+            ('&', Some('&')) => Some((TokenType::AndIf, "&&")),
+            ('&', _) => Some((TokenType::Ampersand, "&")),
+            (';', _) => Some((TokenType::Semicolon, ";")),
+            ('(', _) => Some((TokenType::LeftParen, "(")),
+            (')', _) => Some((TokenType::RightParen, ")")),
+            _ => None,
+        }
+    }
+
     /// https://www.gnu.org/software/bash/manual/bash.html#Double-Quotes
     fn scan_double_quoted_string(&self, start: usize) -> Result<(usize, String), ScannerError> {
         let mut end_at: Option<usize> = None; // points to index of closing "
         let mut value = String::new();
         // `start` is "
         // start iterating from `start+1`
-        let mut iter = self.source.chars().skip(start + 1).enumerate();
+        let mut iter = self.source.iter().copied().skip(start + 1).enumerate();
         while let Some((i, c)) = iter.next() {
             // stop once we find closing "
             if c == '"' {
@@ -99,8 +201,8 @@ impl Scanner {
                         // only print matching character, and not backslash
                         value.push(c);
                     }
-                    'n' => {
-                        value.push_str("\\n");
+                    '\n' => {
+                        // line continuation
                     }
                     _ => {
                         // Backslashes preceding characters without a special meaning are left unmodified.
@@ -110,6 +212,14 @@ impl Scanner {
                         value.push(c);
                     }
                 }
+            } else if c == '`' || (c == '$' && self.source.get(start + i + 2) == Some(&'(')) {
+                // the commands of a command substitution may contain quotes
+                let end = match c {
+                    '`' => self.scan_backquoted(start + i + 1)?,
+                    _ => self.scan_command_substitution(start + i + 1)?,
+                };
+                value.extend(&self.source[start + i + 1..end]);
+                iter.nth(end - (start + i + 1) - 2);
             } else {
                 value.push(c);
             }
@@ -117,6 +227,7 @@ impl Scanner {
         if end_at.is_none() {
             return Err(ScannerError {
                 message: "unexpected EOF while looking for matching `\"'".to_string(),
+                incomplete: true,
             });
         }
         let end_at = end_at.unwrap();
@@ -129,9 +240,9 @@ impl Scanner {
     /// https://www.gnu.org/software/bash/manual/bash.html#Single-Quotes
     fn scan_single_quoted_string(&self, start: usize) -> Result<(usize, String), ScannerError> {
         let mut end_at: Option<usize> = None; // points to index of closing '
-        for (i, c) in self.source.chars().skip(start + 1).enumerate() {
+        for (i, c) in self.source.iter().skip(start + 1).enumerate() {
             // stop once we find closing '
-            if c == '\'' {
+            if *c == '\'' {
                 end_at = Some(start + i + 1);
                 break;
             }
@@ -139,38 +250,83 @@ impl Scanner {
         if end_at.is_none() {
             return Err(ScannerError {
                 message: "unexpected EOF while looking for matching `''".to_string(),
+                incomplete: true,
             });
         }
         let end_at = end_at.unwrap();
 
         // exclude opening ' in substr
-        let value = self.source[(start + 1)..end_at].to_string();
+        let value = self.source[(start + 1)..end_at].iter().collect();
         Ok((end_at + 1, value))
     }
 
+    /// https://www.gnu.org/software/bash/manual/bash.html#ANSI_002dC-Quoting
+    fn scan_ansi_c_quoted_string(&self, start: usize) -> Result<(usize, String), ScannerError> {
+        // `start` is $, `start+1` is '
+        escapes::scan_ansi_c_quoted(&self.source, start + 2).ok_or_else(|| ScannerError {
+            message: "unexpected EOF while looking for matching `''".to_string(),
+            incomplete: true,
+        })
+    }
+
     /// https://www.gnu.org/software/bash/manual/bash.html#index-metacharacter
     fn is_metacharacter(&self, c: char) -> bool {
         " \t\n|&;()<>".contains(c)
     }
 
-    fn scan_unquoted_word(&self, start: usize) -> Result<(usize, String), ScannerError> {
+    /// A regular expression after `=~` may contain `|`, `&`, `<`, `>` and
+    /// parentheses, and blanks inside parentheses. With `extglob`, so may the
+    /// pattern lists of extended patterns.
+    fn scan_word(
+        &self,
+        start: usize,
+        regex: bool,
+        extglob: bool,
+    ) -> Result<(usize, String), ScannerError> {
         let mut value = String::new();
         let mut current = start;
+        let mut depth = 0;
 
-        while current < self.source.len()
-            && !self.is_metacharacter(self.source.chars().nth(current).unwrap())
-        {
-            let c = self.source.chars().nth(current).unwrap();
+        while current < self.source.len() {
+            let c = self.source[current];
+            if regex {
+                match c {
+                    '\n' => break,
+                    ' ' | '\t' | ';' | ')' if depth == 0 => break,
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+            } else if c == '(' && is_array_assignment(&self.source[start..current]) {
+                let end = self.scan_compound_value(current)?;
+                value.extend(&self.source[current..end]);
+                current = end;
+                continue;
+            } else if c == '('
+                && extglob
+                && current > start
+                && matches!(self.source[current - 1], '?' | '*' | '+' | '@' | '!')
+            {
+                let end = self.scan_pattern_list(current)?;
+                value.extend(&self.source[current..end]);
+                current = end;
+                continue;
+            } else if self.is_metacharacter(c) {
+                break;
+            }
             if c == '\\' {
                 // Handle escape sequence
                 current += 1; // Skip '\'
                 if current < self.source.len() {
-                    value.push(self.source.chars().nth(current).unwrap());
+                    // a backslash-newline pair continues the word on the next line
+                    if self.source[current] != '\n' {
+                        value.push(self.source[current]);
+                    } else if current + 1 == self.source.len() {
+                        return Err(self.unexpected_eof_after_backslash());
+                    }
                     current += 1;
                 } else {
-                    return Err(ScannerError {
-                        message: "unexpected EOF after '\\'".to_string(),
-                    });
+                    return Err(self.unexpected_eof_after_backslash());
                 }
             } else if c == '\'' {
                 let ret = self.scan_single_quoted_string(current)?;
@@ -180,6 +336,22 @@ impl Scanner {
                 let ret = self.scan_double_quoted_string(current)?;
                 value.push_str(&ret.1);
                 current = ret.0;
+            } else if c == '$' && self.source.get(current + 1) == Some(&'\'') {
+                let ret = self.scan_ansi_c_quoted_string(current)?;
+                value.push_str(&ret.1);
+                current = ret.0;
+            } else if c == '$' && self.source.get(current + 1) == Some(&'{') {
+                let end = self.scan_parameter_expansion(current)?;
+                value.extend(&self.source[current..end]);
+                current = end;
+            } else if c == '$' && self.source.get(current + 1) == Some(&'(') {
+                let end = self.scan_command_substitution(current)?;
+                value.extend(&self.source[current..end]);
+                current = end;
+            } else if c == '`' {
+                let end = self.scan_backquoted(current)?;
+                value.extend(&self.source[current..end]);
+                current = end;
             } else {
                 value.push(c);
                 current += 1;
@@ -188,6 +360,235 @@ impl Scanner {
 
         Ok((current, value))
     }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Here-Documents
+    ///
+    /// Reads the lines from `start` up to the delimiter line. Returns the index
+    /// after the delimiter line and the body.
+    fn scan_here_document(
+        &self,
+        start: usize,
+        here_document: &PendingHereDocument,
+    ) -> Result<(usize, String), ScannerError> {
+        let mut body = String::new();
+        let mut current = start;
+        while current < self.source.len() {
+            let end = self.source[current..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map(|i| current + i);
+            let line: String = self.source[current..end.unwrap_or(self.source.len())]
+                .iter()
+                .collect();
+            let line = match here_document.strip_tabs {
+                true => line.trim_start_matches('\t'),
+                false => line.as_str(),
+            };
+            current = end.map_or(self.source.len(), |end| end + 1);
+            if line == here_document.delimiter {
+                return Ok((current, body));
+            }
+            if end.is_none() {
+                break;
+            }
+            body.push_str(line);
+            body.push('\n');
+        }
+        Err(here_document.unterminated())
+    }
+
+    fn unexpected_eof_after_backslash(&self) -> ScannerError {
+        ScannerError {
+            message: "unexpected EOF after '\\'".to_string(),
+            incomplete: true,
+        }
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Arrays
+    ///
+    /// The `(...)` of `name=(...)`, left for the parser to split into words.
+    /// Returns the index after the closing parenthesis.
+    fn scan_compound_value(&self, start: usize) -> Result<usize, ScannerError> {
+        let mut current = start + 1;
+        while current < self.source.len() {
+            match self.source[current] {
+                ')' => return Ok(current + 1),
+                '\\' => current += 2,
+                '\'' => current = self.scan_single_quoted_string(current)?.0,
+                '"' => current = self.scan_double_quoted_string(current)?.0,
+                '$' if self.source.get(current + 1) == Some(&'\'') => {
+                    current = self.scan_ansi_c_quoted_string(current)?.0;
+                }
+                '$' if self.source.get(current + 1) == Some(&'{') => {
+                    current = self.scan_parameter_expansion(current)?;
+                }
+                '$' if self.source.get(current + 1) == Some(&'(') => {
+                    current = self.scan_command_substitution(current)?;
+                }
+                '`' => current = self.scan_backquoted(current)?,
+                '#' if matches!(self.source[current - 1], ' ' | '\t' | '\n' | '(') => {
+                    while current < self.source.len() && self.source[current] != '\n' {
+                        current += 1;
+                    }
+                }
+                _ => current += 1,
+            }
+        }
+        Err(ScannerError {
+            message: "unexpected EOF while looking for matching `)'".to_string(),
+            incomplete: true,
+        })
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Pattern-Matching
+    ///
+    /// The `(...)` of an extended pattern like `@(a|b)`, which may be nested.
+    /// Returns the index after the closing parenthesis.
+    fn scan_pattern_list(&self, start: usize) -> Result<usize, ScannerError> {
+        let mut current = start + 1;
+        let mut depth = 1;
+        while current < self.source.len() {
+            match self.source[current] {
+                '(' => {
+                    depth += 1;
+                    current += 1;
+                }
+                ')' => {
+                    depth -= 1;
+                    current += 1;
+                    if depth == 0 {
+                        return Ok(current);
+                    }
+                }
+                '\\' => current += 2,
+                '\'' => current = self.scan_single_quoted_string(current)?.0,
+                '"' => current = self.scan_double_quoted_string(current)?.0,
+                '$' if self.source.get(current + 1) == Some(&'{') => {
+                    current = self.scan_parameter_expansion(current)?;
+                }
+                _ => current += 1,
+            }
+        }
+        Err(ScannerError {
+            message: "unexpected EOF while looking for matching `)'".to_string(),
+            incomplete: true,
+        })
+    }
+
+    /// `${...}` may contain blanks and metacharacters, it is left for the
+    /// shell to expand. Returns the index after the closing brace.
+    fn scan_parameter_expansion(&self, start: usize) -> Result<usize, ScannerError> {
+        // `start` is $, `start+1` is {
+        let mut current = start + 2;
+        while current < self.source.len() {
+            match self.source[current] {
+                '}' => return Ok(current + 1),
+                '\\' => current += 2,
+                '\'' => current = self.scan_single_quoted_string(current)?.0,
+                '"' => current = self.scan_double_quoted_string(current)?.0,
+                '$' if self.source.get(current + 1) == Some(&'{') => {
+                    current = self.scan_parameter_expansion(current)?;
+                }
+                '$' if self.source.get(current + 1) == Some(&'(') => {
+                    current = self.scan_command_substitution(current)?;
+                }
+                '`' => current = self.scan_backquoted(current)?,
+                _ => current += 1,
+            }
+        }
+        Err(ScannerError {
+            message: "unexpected EOF while looking for matching `}'".to_string(),
+            incomplete: true,
+        })
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Command-Substitution
+    ///
+    /// The commands of `$(...)` may contain blanks, operators and nested
+    /// parentheses. They are left for the shell to parse when it expands the
+    /// word. Returns the index after the closing parenthesis.
+    fn scan_command_substitution(&self, start: usize) -> Result<usize, ScannerError> {
+        // `start` is $, `start+1` is (
+        let mut current = start + 2;
+        let mut depth = 0;
+        while current < self.source.len() {
+            match self.source[current] {
+                '(' => {
+                    depth += 1;
+                    current += 1;
+                }
+                ')' if depth == 0 => return Ok(current + 1),
+                ')' => {
+                    depth -= 1;
+                    current += 1;
+                }
+                '\\' => current += 2,
+                '\'' => current = self.scan_single_quoted_string(current)?.0,
+                '"' => current = self.scan_double_quoted_string(current)?.0,
+                '`' => current = self.scan_backquoted(current)?,
+                '$' if self.source.get(current + 1) == Some(&'\'') => {
+                    current = self.scan_ansi_c_quoted_string(current)?.0;
+                }
+                '$' if self.source.get(current + 1) == Some(&'{') => {
+                    current = self.scan_parameter_expansion(current)?;
+                }
+                '#' if matches!(self.source[current - 1], ' ' | '\t' | '\n' | '(' | ';') => {
+                    while current < self.source.len() && self.source[current] != '\n' {
+                        current += 1;
+                    }
+                }
+                _ => current += 1,
+            }
+        }
+        Err(ScannerError {
+            message: "unexpected EOF while looking for matching `)'".to_string(),
+            incomplete: true,
+        })
+    }
+
+    /// The old form of command substitution, `` `...` ``, ends at the next
+    /// backquote that is not escaped. Returns the index after it.
+    fn scan_backquoted(&self, start: usize) -> Result<usize, ScannerError> {
+        let mut current = start + 1;
+        while current < self.source.len() {
+            match self.source[current] {
+                '`' => return Ok(current + 1),
+                '\\' => current += 2,
+                _ => current += 1,
+            }
+        }
+        Err(ScannerError {
+            message: "unexpected EOF while looking for matching ``'".to_string(),
+            incomplete: true,
+        })
+    }
+}
+
+/// The index after the end of the command substitution starting at `start` in
+/// a word that was already scanned, `$(...)` or `` `...` ``
+pub fn command_substitution_end(word: &[char], start: usize) -> usize {
+    let scanner = Scanner::new(word.iter().collect());
+    let end = match word[start] {
+        '`' => scanner.scan_backquoted(start),
+        _ => scanner.scan_command_substitution(start),
+    };
+    end.unwrap_or(word.len())
+}
+
+/// Whether `word` is `name=` or `name+=`, so that a `(` after it starts a
+/// compound assignment. `name[subscript]=` is included for the shell to
+/// report that a list cannot be assigned to an element.
+fn is_array_assignment(word: &[char]) -> bool {
+    let word: String = word.iter().collect();
+    let Some(name) = word.strip_suffix('=') else {
+        return false;
+    };
+    let name = name.strip_suffix('+').unwrap_or(name);
+    let name = match name.strip_suffix(']').and_then(|name| name.split_once('[')) {
+        Some((name, _)) => name,
+        None => name,
+    };
+    is_name(name)
 }
 
 // MARK: Tests
@@ -526,4 +927,138 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_line_continuation() {
+        test(
+            "echo a\\\nb \\\n c".to_string(),
+            vec![
+                Token::new(TokenType::String, "echo".to_string()),
+                Token::new(TokenType::String, "ab".to_string()),
+                Token::new(TokenType::String, "c".to_string()),
+                eof_token(),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        for input in ["echo 'a", "echo \"a", "echo a\\\n", "echo ${a"] {
+            let error = Scanner::new(input.to_string()).scan_tokens().unwrap_err();
+            assert!(error.incomplete, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_here_document() {
+        let input = "cat <<EOF 2>&1; cat <<-'X'\nline $a\nEOF\n\tindented\n\tX\necho after";
+        let tokens = Scanner::new(input.to_string()).scan_tokens().unwrap();
+        assert_eq!(tokens[1].here_document.as_deref(), Some("line $a\n"));
+        assert_eq!(tokens[3].type_, TokenType::IoNumber);
+        assert_eq!(tokens[8].here_document.as_deref(), Some("indented\n"));
+        assert_eq!(tokens[9].raw, "'X'");
+        assert_eq!(tokens[11].lexeme, "echo");
+
+        let error = Scanner::new("cat <<EOF\nline\n".to_string())
+            .scan_tokens()
+            .unwrap_err();
+        assert!(error.incomplete);
+    }
+
+    #[test]
+    fn test_comments() {
+        test(
+            "#!/usr/bin/env rushell\necho a#b $# # note 'unterminated\n#".to_string(),
+            vec![
+                Token::new(TokenType::Newline, "\n".to_string()),
+                Token::new(TokenType::String, "echo".to_string()),
+                Token::new(TokenType::String, "a#b".to_string()),
+                Token::new(TokenType::String, "$#".to_string()),
+                Token::new(TokenType::Newline, "\n".to_string()),
+                eof_token(),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_ansi_c_quoted_string() {
+        test(
+            "echo $'a\\tb\\'c' x$'\\n'y".to_string(),
+            vec![
+                Token::new(TokenType::String, "echo".to_string()),
+                Token::new(TokenType::String, "a\tb'c".to_string()),
+                Token::new(TokenType::String, "x\ny".to_string()),
+                eof_token(),
+            ],
+        );
+        assert!(
+            Scanner::new("$'abc".to_string())
+                .scan_tokens()
+                .unwrap_err()
+                .incomplete
+        );
+    }
+
+    #[test]
+    fn test_regex_after_match_operator() {
+        test(
+            "[[ x =~ ^(a|b c)$ && x =~ (x) ]]".to_string(),
+            vec![
+                Token::new(TokenType::String, "[[".to_string()),
+                Token::new(TokenType::String, "x".to_string()),
+                Token::new(TokenType::String, "=~".to_string()),
+                Token::new(TokenType::String, "^(a|b c)$".to_string()),
+                Token::new(TokenType::AndIf, "&&".to_string()),
+                Token::new(TokenType::String, "x".to_string()),
+                Token::new(TokenType::String, "=~".to_string()),
+                Token::new(TokenType::String, "(x)".to_string()),
+                Token::new(TokenType::String, "]]".to_string()),
+                eof_token(),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_extended_patterns() {
+        let mut scanner = Scanner::new("echo @(a|b c)x !(*.rs)".to_string());
+        assert_eq!(scanner.scan_tokens().unwrap()[1].lexeme, "@");
+        scanner.extglob = true;
+        let tokens = scanner.scan_tokens().unwrap();
+        let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(lexemes, ["echo", "@(a|b c)x", "!(*.rs)", ""]);
+        test(
+            "[[ x == +(x) ]]".to_string(),
+            vec![
+                Token::new(TokenType::String, "[[".to_string()),
+                Token::new(TokenType::String, "x".to_string()),
+                Token::new(TokenType::String, "==".to_string()),
+                Token::new(TokenType::String, "+(x)".to_string()),
+                Token::new(TokenType::String, "]]".to_string()),
+                eof_token(),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_command_substitution() {
+        let scanner = Scanner::new("echo $(a \"b)\" (c); d)x \"$(e \"f\")\" `g | h`".to_string());
+        let tokens = scanner.scan_tokens().unwrap();
+        let raws: Vec<&str> = tokens.iter().map(|t| t.raw.as_str()).collect();
+        assert_eq!(
+            raws,
+            [
+                "echo",
+                "$(a \"b)\" (c); d)x",
+                "\"$(e \"f\")\"",
+                "`g | h`",
+                ""
+            ]
+        );
+        assert!(
+            Scanner::new("echo $(a".to_string())
+                .scan_tokens()
+                .unwrap_err()
+                .incomplete
+        );
+    }
 }
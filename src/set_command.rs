@@ -0,0 +1,88 @@
+use crate::declare_command;
+use crate::options::Options;
+use crate::shell::Shell;
+
+pub struct SetCommand;
+
+impl SetCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#The-Set-Builtin
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        if args.len() == 1 {
+            for (name, variable) in shell.variables().all() {
+                if variable.value.is_some() {
+                    println!("{}", declare_command::definition(name, variable));
+                }
+            }
+            return 0;
+        }
+
+        let mut index = 1;
+        let mut parameters: Option<Vec<String>> = None;
+        while let Some(arg) = args.get(index) {
+            index += 1;
+            let on = match arg.chars().next() {
+                Some('-') => true,
+                Some('+') => false,
+                _ => {
+                    parameters = Some(args[index - 1..].to_vec());
+                    break;
+                }
+            };
+            if arg == "--" {
+                parameters = Some(args[index..].to_vec());
+                break;
+            }
+            // `set -` ends the options, but only changes the positional
+            // parameters if there are more arguments
+            if arg == "-" || arg == "+" {
+                if index < args.len() {
+                    parameters = Some(args[index..].to_vec());
+                }
+                break;
+            }
+            for letter in arg[1..].chars() {
+                let name = match letter {
+                    'o' => match args.get(index) {
+                        Some(name) => {
+                            index += 1;
+                            name.as_str()
+                        }
+                        None => {
+                            SetCommand::print_options(shell.options(), on);
+                            continue;
+                        }
+                    },
+                    _ => match Options::name(letter) {
+                        Some(name) => name,
+                        None => {
+                            eprintln!("set: {}{}: invalid option", &arg[..1], letter);
+                            eprintln!("set: usage: set [-Cefnux] [-o option-name] [--] [arg ...]");
+                            return 2;
+                        }
+                    },
+                };
+                if let Err(message) = shell.options().set(name, on) {
+                    eprintln!("set: {}", message);
+                    return 2;
+                }
+            }
+        }
+        if let Some(parameters) = parameters {
+            shell.set_positional_parameters(parameters);
+        }
+        0
+    }
+
+    /// `set -o` prints whether each option is on, `set +o` prints the
+    /// commands that restore them
+    fn print_options(options: &Options, on: bool) {
+        for (name, value) in options.list() {
+            match (on, value) {
+                (true, true) => println!("{:<15}\ton", name),
+                (true, false) => println!("{:<15}\toff", name),
+                (false, true) => println!("set -o {}", name),
+                (false, false) => println!("set +o {}", name),
+            }
+        }
+    }
+}
@@ -1,70 +1,1537 @@
+use crate::alias_command::AliasCommand;
+use crate::aliases::Aliases;
+use crate::ast::{
+    AndOr, Assignment, AssignmentValue, Command, CommandList, CompoundCommand,
+    ConditionalExpression, Connector, ForCommand, FunctionDefinition, IfCommand, LoopCommand,
+    Pipeline, Redirection, RedirectionOperator, SimpleCommand,
+};
+use crate::bg_command::BgCommand;
+use crate::break_command::BreakCommand;
+use crate::builtin_command::BuiltinCommand;
 use crate::cd_command::CdCommand;
+use crate::command_command::CommandCommand;
+use crate::continue_command::ContinueCommand;
+use crate::declare_command::DeclareCommand;
 use crate::echo_command::EchoCommand;
+use crate::eval_command::EvalCommand;
+use crate::exec_command::ExecCommand;
 use crate::exit_command::ExitCommand;
+use crate::expansion::{self, Environment, ExpansionError};
+use crate::fg_command::FgCommand;
+use crate::jobs::{Job, Jobs};
+use crate::jobs_command::JobsCommand;
+use crate::line_reader::LineReader;
+use crate::options::Options;
+use crate::parser::{Parser, ParserError};
+use crate::pattern;
+use crate::printf_command::PrintfCommand;
+use crate::prompt::{self, PromptContext};
 use crate::pwd_command::PwdCommand;
+use crate::read_command::ReadCommand;
+use crate::redirection::{self, SavedFds};
+use crate::regex::Regex;
+use crate::return_command::ReturnCommand;
 use crate::scanner::Scanner;
-use crate::token::TokenType;
+use crate::set_command::SetCommand;
+use crate::shopt_command::ShoptCommand;
+use crate::signals;
+use crate::source_command::SourceCommand;
+use crate::test_command::{self, TestCommand};
+use crate::trap_command::TrapCommand;
+use crate::traps::{Condition, Traps};
 use crate::type_command::TypeCommand;
-use std::collections::HashSet;
-use std::io::{stderr, stdin, stdout, BufRead, BufReader, Write};
-use std::process::Command;
+use crate::unalias_command::UnaliasCommand;
+use crate::unset_command::UnsetCommand;
+use crate::utils;
+use crate::variables::Variables;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::File;
+use std::io::{self, stdout, ErrorKind, Read, Write};
+use std::os::fd::FromRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process;
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
 
 pub struct Shell {
     built_in_commands: HashSet<String>,
+    functions: HashMap<String, Rc<FunctionDefinition>>,
+    aliases: Aliases,
+    jobs: Jobs,
+    traps: Traps,
+    variables: Variables,
+    options: Options,
+    /// `$0`
+    name: String,
+    /// `$1`, `$2`, ...
+    positional_parameters: Vec<String>,
+    /// `$$`, which stays the same in subshells
+    pid: libc::pid_t,
+    /// `$!`
+    last_background_pid: Option<libc::pid_t>,
+    /// reading commands from a terminal: show prompts and enable job control
+    interactive: bool,
+    /// exit status of the most recently executed pipeline
+    last_status: i32,
+    /// DEBUG, ERR and RETURN traps are not run while a trap action runs
+    running_trap: bool,
+    /// set by `exit`, stops execution so that the shell can unwind to `main`
+    pending_exit: Option<i32>,
+    /// set by `break`, `continue` and `return`
+    pending_jump: Option<Jump>,
+    /// number of enclosing loops, for `break` and `continue`
+    loop_depth: usize,
+    /// number of enclosing function calls, for `return`
+    function_depth: usize,
+    /// number of files being run with `source`, for `return`
+    source_depth: usize,
+    /// exit status of the last command substitution in the command being
+    /// expanded, which is the status of a command without a command name
+    substitution_status: Option<i32>,
+    /// running the condition of `if`, `while` or `until`, whose failure does
+    /// not trigger the ERR trap
+    in_condition: bool,
+    /// `exit` refuses to leave stopped jobs behind unless it is run twice in a row
+    stopped_jobs_warned: bool,
 }
 
 impl Shell {
     pub fn new() -> Self {
+        let mut variables = Variables::new();
+        if !variables.contains("PS4") {
+            variables.set("PS4", "+ ".to_string()).ok();
+        }
         Shell {
             built_in_commands: Shell::get_built_in_commands(),
+            functions: HashMap::new(),
+            aliases: Aliases::new(),
+            jobs: Jobs::new(),
+            traps: Traps::new(),
+            variables,
+            options: Options::default(),
+            name: "rushell".to_string(),
+            positional_parameters: Vec::new(),
+            pid: unsafe { libc::getpid() },
+            last_background_pid: None,
+            interactive: false,
+            last_status: 0,
+            running_trap: false,
+            pending_exit: None,
+            pending_jump: None,
+            loop_depth: 0,
+            function_depth: 0,
+            source_depth: 0,
+            substitution_status: None,
+            in_condition: false,
+            stopped_jobs_warned: false,
         }
     }
 
-    pub fn main(&self) {
+    /// Set `$0` and the positional parameters
+    pub fn set_arguments(&mut self, name: String, parameters: Vec<String>) {
+        self.name = name;
+        self.positional_parameters = parameters;
+    }
+
+    /// Read commands from standard input. The shell is interactive when it is
+    /// connected to a terminal, unless `force_interactive` is set.
+    pub fn main(&mut self, force_interactive: bool, startup: &StartupFiles) {
+        self.interactive = force_interactive
+            || unsafe {
+                libc::isatty(libc::STDIN_FILENO) == 1 && libc::isatty(libc::STDERR_FILENO) == 1
+            };
+        if self.interactive {
+            signals::init_interactive();
+            self.jobs.init();
+            self.options.expand_aliases = true;
+            for (name, value) in [("PS1", "$ "), ("PS2", "> ")] {
+                if !self.variables.contains(name) {
+                    self.variables.set(name, value.to_string()).ok();
+                }
+            }
+        }
+        self.run_startup_files(startup);
         self.run_prompt();
+        let status = self.pending_exit.unwrap_or(self.last_status);
+        self.exit(status);
+    }
+
+    /// `rushell script.sh args...`
+    pub fn run_script(&mut self, path: &str, startup: &StartupFiles) -> ! {
+        let file = match File::open(path) {
+            Ok(file) if file.metadata().is_ok_and(|m| m.is_dir()) => {
+                eprintln!("rushell: {}: Is a directory", path);
+                std::process::exit(126);
+            }
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("rushell: {}: {}", path, utils::error_message(&e));
+                std::process::exit(if e.kind() == ErrorKind::NotFound {
+                    127
+                } else {
+                    126
+                });
+            }
+        };
+        self.run_startup_files(startup);
+        self.run_reader(&mut LineReader::from_file(file));
+        let status = self.pending_exit.unwrap_or(self.last_status);
+        self.exit(status);
+    }
+
+    /// `rushell -c 'string'`
+    pub fn run_command_string(&mut self, source: String, startup: &StartupFiles) -> ! {
+        self.run_startup_files(startup);
+        self.run_reader(&mut LineReader::from_string(source));
+        let status = self.pending_exit.unwrap_or(self.last_status);
+        self.exit(status);
+    }
+
+    pub fn last_status(&self) -> i32 {
+        self.last_status
+    }
+
+    /// Stop executing commands and leave the shell with `status` once control
+    /// gets back to the main loop
+    pub fn request_exit(&mut self, status: i32) {
+        self.pending_exit = Some(status);
+    }
+
+    pub fn loop_depth(&self) -> usize {
+        self.loop_depth
+    }
+
+    pub fn in_function(&self) -> bool {
+        self.function_depth > 0
+    }
+
+    /// Parse `source` and run it in this shell, for `eval`
+    pub fn eval(&mut self, source: &str) -> i32 {
+        match self.parse(source) {
+            Ok(list) => self.execute_list(&list),
+            Err(e) => {
+                eprintln!("eval: {}", e);
+                2
+            }
+        }
+    }
+
+    /// Run the builtin `args[0]`, `None` if there is no such builtin
+    pub fn run_builtin(&mut self, args: &[String]) -> Option<i32> {
+        match self.built_in_commands.contains(&args[0]) {
+            true => Some(self.execute_built_in(&args[0], args)),
+            false => None,
+        }
+    }
+
+    /// Run `args` as an external command even if there is a function of the
+    /// same name, for `command`
+    pub fn run_external(&mut self, args: &[String]) -> i32 {
+        let text = args.join(" ");
+        self.launch_job(vec![Stage::External(args.to_vec())], &text, true)
+    }
+
+    pub fn aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    pub fn functions(&self) -> &HashMap<String, Rc<FunctionDefinition>> {
+        &self.functions
+    }
+
+    pub fn built_in_commands(&self) -> &HashSet<String> {
+        &self.built_in_commands
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    /// Whether `return` has a function or sourced file to return from
+    pub fn can_return(&self) -> bool {
+        self.function_depth > 0 || self.source_depth > 0
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-source
+    ///
+    /// Run the commands of `file` in this shell. With `parameters`, they are
+    /// the positional parameters until the end of the file.
+    pub fn source(&mut self, file: File, parameters: Option<Vec<String>>) -> i32 {
+        let saved = parameters.map(|p| std::mem::replace(&mut self.positional_parameters, p));
+        self.source_depth += 1;
+        self.last_status = 0;
+        self.run_reader(&mut LineReader::from_file(file));
+        self.source_depth -= 1;
+        if let Some(parameters) = saved {
+            self.positional_parameters = parameters;
+        }
+        if self.pending_jump == Some(Jump::Return) {
+            self.pending_jump = None;
+        }
+        self.run_trap(Condition::Return);
+        self.last_status
+    }
+
+    /// Stop executing commands until control gets back to the enclosing loop
+    /// or function
+    pub fn jump(&mut self, jump: Jump) {
+        self.pending_jump = Some(jump);
+    }
+
+    pub fn variables(&mut self) -> &mut Variables {
+        &mut self.variables
+    }
+
+    /// The value of a variable, for tests
+    #[cfg(test)]
+    pub(crate) fn variable(&self, name: &str) -> Option<String> {
+        self.variables.get(name)
+    }
+
+    pub fn options(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    pub fn traps(&self) -> &Traps {
+        &self.traps
+    }
+
+    /// `set -- args`
+    pub fn set_positional_parameters(&mut self, parameters: Vec<String>) {
+        self.positional_parameters = parameters;
+    }
+
+    /// Returns true the first time it is called while there are stopped jobs
+    pub fn warn_stopped_jobs(&mut self) -> bool {
+        if self.stopped_jobs_warned || !self.jobs.has_stopped() {
+            return false;
+        }
+        self.stopped_jobs_warned = true;
+        true
+    }
+
+    /// Run the EXIT trap and terminate the shell
+    fn exit(&mut self, status: i32) -> ! {
+        let status = self.run_exit_trap(status);
+        stdout().flush().unwrap();
+        process::exit(status)
+    }
+
+    /// Returns the status to exit with, which an `exit` in the action changes
+    fn run_exit_trap(&mut self, status: i32) -> i32 {
+        let Some(action) = self.traps.get(Condition::Exit).cloned() else {
+            return status;
+        };
+        // an `exit` in the action must not run it again
+        self.traps.reset(Condition::Exit);
+        self.last_status = status;
+        self.pending_exit = None;
+        self.run(action);
+        self.pending_exit.unwrap_or(status)
     }
 
-    fn run_prompt(&self) {
-        let input = stdin().lock();
-        let mut reader = BufReader::new(input);
+    /// In POSIX mode, interactive shells only run the file named by the
+    /// expansion of `$ENV`
+    fn run_startup_files(&mut self, startup: &StartupFiles) {
+        let mut files = Vec::new();
+        if self.options.posix {
+            if let Some(env) = self.variables.get("ENV").filter(|_| self.interactive) {
+                match expansion::expand_word_to_string(&env, self) {
+                    Ok(path) => files.push(path),
+                    Err(e) => eprintln!("rushell: {}", e),
+                }
+            }
+        } else {
+            let home = self.variables.get("HOME");
+            files = startup.files(self.interactive, home.as_deref());
+        }
 
+        for path in files {
+            if self.pending_exit.is_some() {
+                break;
+            }
+            match File::open(&path) {
+                Ok(file) => {
+                    self.source(file, None);
+                }
+                // a missing startup file is not an error
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => eprintln!("rushell: {}: {}", path, utils::error_message(&e)),
+            }
+        }
+    }
+
+    /// Run commands read from a script or a string until the end of input
+    fn run_reader(&mut self, reader: &mut LineReader) {
         loop {
-            print!("$ ");
-            stdout().flush().unwrap();
-            let mut line: String = String::new();
-            if reader.read_line(&mut line).is_err() {
+            self.run_pending_traps();
+            // `return` leaves a sourced file
+            if self.is_unwinding() {
                 break;
             }
-            line = line.trim().to_string();
-            self.run(line);
+            match self.read_commands(reader, false) {
+                Ok(Some(list)) => {
+                    self.execute_list(&list);
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("rushell: {}", utils::error_message(&e));
+                    break;
+                }
+            }
         }
     }
 
-    fn run(&self, source: String) {
-        let scanner = Scanner::new(source);
-        let scanned_tokens = scanner.scan_tokens();
+    fn run_prompt(&mut self) {
+        let mut reader = match self.interactive {
+            true => LineReader::terminal(libc::STDIN_FILENO),
+            false => LineReader::new(libc::STDIN_FILENO),
+        };
+        // consecutive end of file conditions ignored so far
+        let mut eof_count: usize = 0;
 
-        match scanned_tokens {
-            Ok(scanned_tokens) => {
-                let args: Vec<String> = scanned_tokens
-                    .iter()
-                    .filter(|token| token.type_ != TokenType::Eof)
-                    .map(|token| token.lexeme.clone())
-                    .collect();
-                if args.is_empty() {
-                    return;
+        loop {
+            self.run_pending_traps();
+            if self.pending_exit.is_some() {
+                break;
+            }
+            // Ctrl-C and Ctrl-\ that were not trapped have been dealt with
+            signals::take(libc::SIGINT);
+            signals::take(libc::SIGQUIT);
+            self.pending_jump = None;
+            self.jobs.notify();
+            if self.interactive {
+                self.run_prompt_command();
+                if self.pending_exit.is_some() {
+                    break;
+                }
+            }
+            match self.read_commands(&mut reader, self.interactive) {
+                Ok(Some(list)) => {
+                    eof_count = 0;
+                    self.execute_list(&list);
+                }
+                Ok(None) => {
+                    if self.interactive {
+                        if eof_count < self.ignored_eof_limit() {
+                            eof_count += 1;
+                            println!();
+                            eprintln!("Use \"exit\" to leave the shell.");
+                            continue;
+                        }
+                        eprintln!("exit");
+                    }
+                    break;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {
+                    // Ctrl-C discards the lines being typed
+                    println!();
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("rushell: {}", utils::error_message(&e));
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Read lines until they make up complete commands, prompting with `PS1`
+    /// and then `PS2` for the continuation lines if `prompt` is set. Returns
+    /// `None` at the end of input. Syntax errors are reported and result in an
+    /// empty list.
+    fn read_commands(
+        &mut self,
+        reader: &mut LineReader,
+        prompt: bool,
+    ) -> io::Result<Option<CommandList>> {
+        let mut source = String::new();
+        loop {
+            let prompt = match (prompt, source.is_empty()) {
+                (false, _) => String::new(),
+                (true, true) => self.prompt("PS1"),
+                (true, false) => self.prompt("PS2"),
+            };
+            let mut line = String::new();
+            let at_end = reader.read_line(&prompt, &mut line)? == 0;
+            if at_end && source.is_empty() {
+                return Ok(None);
+            }
+            source.push_str(&line);
+
+            match self.parse(&source) {
+                Ok(list) => return Ok(Some(list)),
+                Err(e) if e.incomplete && !at_end => continue,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    self.last_status = 2;
+                    if !self.interactive {
+                        // a script cannot go on after a syntax error
+                        self.request_exit(2);
+                    }
+                    return Ok(Some(CommandList { items: Vec::new() }));
                 }
-                self.execute(&args);
+            }
+        }
+    }
+
+    /// Run the commands in `PROMPT_COMMAND`, or in each of its elements if it
+    /// is an array, before printing `PS1`. `$?` is left alone.
+    fn run_prompt_command(&mut self) {
+        let status = self.last_status;
+        for (_, command) in self.variables.elements("PROMPT_COMMAND") {
+            self.run(command);
+            if self.pending_exit.is_some() {
+                return;
+            }
+        }
+        self.last_status = status;
+    }
+
+    /// The value of `PS1`, `PS2` or `PS4` with its backslash escapes decoded,
+    /// then expanded like the body of a here-document. Command substitutions
+    /// in it do not change `$?` and are not traced.
+    fn prompt(&mut self, name: &str) -> String {
+        let Some(prompt) = self.variables.get(name) else {
+            return String::new();
+        };
+        let context = PromptContext {
+            home: self.variables.get("HOME"),
+            current_dir: env::current_dir()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            jobs: self.jobs.ids().len(),
+            last_status: self.last_status,
+        };
+        let decoded = prompt::decode(&prompt, &context);
+        let (status, xtrace) = (self.last_status, self.options.xtrace);
+        self.options.xtrace = false;
+        let expanded = expansion::expand_here_document(&decoded, self);
+        self.last_status = status;
+        self.options.xtrace = xtrace;
+        match expanded {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                eprintln!("rushell: {}", e);
+                decoded
+            }
+        }
+    }
+
+    /// Number of consecutive Ctrl-D that are ignored before the interactive
+    /// shell exits, set with `IGNOREEOF` or `set -o ignoreeof`. Like in bash,
+    /// a value that is not a plain number means 10.
+    fn ignored_eof_limit(&self) -> usize {
+        match self.variables.get("IGNOREEOF") {
+            Some(value) if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                value.parse().unwrap_or(usize::MAX)
+            }
+            Some(_) => 10,
+            None if self.options.ignoreeof => 10,
+            None => 0,
+        }
+    }
+
+    fn parse(&self, source: &str) -> Result<CommandList, ParserError> {
+        let mut scanner = Scanner::new(source.to_string());
+        scanner.extglob = self.options.extglob;
+        let mut tokens = scanner.scan_tokens()?;
+        if self.options.expand_aliases {
+            tokens = self.aliases.expand(tokens)?;
+        }
+        Parser::new(tokens).parse()
+    }
+
+    fn run(&mut self, source: String) {
+        match self.parse(&source) {
+            Ok(list) => {
+                self.execute_list(&list);
             }
             Err(e) => eprintln!("{}", e),
         }
     }
 
-    fn execute(&self, args: &[String]) -> i32 {
+    /// `exit`, `break`, `continue` or `return` is stopping the execution
+    fn is_unwinding(&self) -> bool {
+        self.pending_exit.is_some() || self.pending_jump.is_some()
+    }
+
+    /// `set -n` stops running commands as soon as it is set, except in
+    /// interactive shells which ignore it
+    fn is_noexec(&self) -> bool {
+        self.options.noexec && !self.interactive
+    }
+
+    fn execute_list(&mut self, list: &CommandList) -> i32 {
+        let mut status: i32 = 0;
+        for item in &list.items {
+            if self.is_unwinding() || self.is_noexec() {
+                break;
+            }
+            if item.background {
+                self.execute_background(&item.and_or);
+                status = 0;
+            } else {
+                status = self.execute_and_or(&item.and_or);
+            }
+        }
+        status
+    }
+
+    fn execute_and_or(&mut self, and_or: &AndOr) -> i32 {
+        // pipelines followed by `&&` or `||` are conditions, their failure
+        // does not trigger the ERR trap or `set -e`, and neither does that of
+        // `!` pipelines or of commands inside them
+        let run = |shell: &mut Shell, pipeline: &Pipeline, condition: bool| {
+            let in_condition = shell.in_condition;
+            shell.in_condition |= condition;
+            let status = shell.execute_pipeline(pipeline, true);
+            shell.in_condition = in_condition;
+            status
+        };
+        let mut ran_last = and_or.rest.is_empty() && !and_or.first.negated;
+        let mut status = run(self, &and_or.first, !ran_last);
+        for (i, (connector, pipeline)) in and_or.rest.iter().enumerate() {
+            if self.is_unwinding() || self.is_noexec() {
+                return status;
+            }
+            let should_run = match connector {
+                Connector::And => status == 0,
+                Connector::Or => status != 0,
+            };
+            if should_run {
+                ran_last = i == and_or.rest.len() - 1 && !pipeline.negated;
+                status = run(self, pipeline, !ran_last);
+            }
+        }
+        if status != 0 && ran_last && !self.in_condition && !self.is_unwinding() {
+            self.run_trap(Condition::Err);
+            if self.options.errexit {
+                self.request_exit(status);
+            }
+        }
+        status
+    }
+
+    fn execute_background(&mut self, and_or: &AndOr) {
+        if and_or.rest.is_empty() {
+            self.execute_pipeline(&and_or.first, false);
+            return;
+        }
+
+        // a list of pipelines runs as a single job in a subshell
+        match self.fork() {
+            -1 => {}
+            0 => {
+                self.setup_child(0, false);
+                let status = self.execute_and_or(and_or);
+                self.exit_child(status);
+            }
+            pid => {
+                self.jobs.setup_parent(pid, pid);
+                let mut job = Job::new(pid, vec![pid], format!("{} &", and_or.text()));
+                job.pipefail = self.options.pipefail;
+                self.last_background_pid = Some(self.jobs.background(job));
+            }
+        }
+    }
+
+    fn execute_pipeline(&mut self, pipeline: &Pipeline, foreground: bool) -> i32 {
+        self.run_trap(Condition::Debug);
+        let mut status = self.launch_pipeline(pipeline, foreground);
+        if pipeline.negated {
+            status = (status == 0) as i32;
+        }
+        self.last_status = status;
+        self.run_pending_traps();
+        status
+    }
+
+    fn launch_pipeline(&mut self, pipeline: &Pipeline, foreground: bool) -> i32 {
+        if foreground && pipeline.commands.len() == 1 {
+            match &pipeline.commands[0] {
+                Command::Compound(CompoundCommand::Subshell(_), _) => {}
+                command => return self.execute_command(command, &pipeline.text),
+            }
+        }
+        if foreground && self.options.lastpipe && !self.jobs.is_enabled() {
+            return self.launch_lastpipe(pipeline);
+        }
+
+        let stages = pipeline.commands.iter().map(Shell::stage).collect();
+        self.launch_job(stages, &pipeline.text, foreground)
+    }
+
+    /// Run a command in the shell process
+    fn execute_command(&mut self, command: &Command, text: &str) -> i32 {
+        match command {
+            Command::Simple(command) => self.execute_simple_command(command, text),
+            Command::Compound(command, redirections) => self
+                .with_redirections(redirections, |shell| {
+                    shell.execute_compound_command(command, text)
+                }),
+            Command::FunctionDefinition(definition) => self.define_function(definition),
+        }
+    }
+
+    fn stage(command: &Command) -> Stage<'_> {
+        match command {
+            Command::Compound(CompoundCommand::Subshell(list), redirections) => {
+                Stage::Subshell(list, redirections)
+            }
+            _ => Stage::Command(command),
+        }
+    }
+
+    /// With `shopt -s lastpipe`, the last command of a pipeline runs in the
+    /// shell when job control is off, so that `echo hi | read x` sets `x`
+    fn launch_lastpipe(&mut self, pipeline: &Pipeline) -> i32 {
+        let (last, rest) = pipeline.commands.split_last().unwrap();
+        let stages: Vec<Stage> = rest.iter().map(Shell::stage).collect();
+        let (pgid, pids, read_end) = self.start_job(&stages, true, true);
+        if pids.is_empty() {
+            return 1;
+        }
+        let status = unsafe {
+            let saved = libc::fcntl(libc::STDIN_FILENO, libc::F_DUPFD_CLOEXEC, 10);
+            libc::dup2(read_end, libc::STDIN_FILENO);
+            libc::close(read_end);
+            let status = self.execute_command(last, &pipeline.text);
+            libc::dup2(saved, libc::STDIN_FILENO);
+            libc::close(saved);
+            status
+        };
+        let mut job = Job::new(pgid, pids, pipeline.text.clone());
+        job.pipefail = self.options.pipefail;
+        let job_status = self.jobs.foreground(job, false);
+        match self.options.pipefail && status == 0 {
+            true => job_status,
+            false => status,
+        }
+    }
+
+    fn execute_simple_command(&mut self, command: &SimpleCommand, text: &str) -> i32 {
+        self.substitution_status = None;
+        let expanded = match self.expand_command(command) {
+            Ok(command) => command,
+            Err(e) => {
+                eprintln!("rushell: {}", e);
+                return 1;
+            }
+        };
+        if expanded.args.is_empty() {
+            for assignment in &command.assignments {
+                match self.assign(assignment) {
+                    Ok(word) => self.trace(&[word]),
+                    Err(message) => {
+                        eprintln!("rushell: {}", message);
+                        return 1;
+                    }
+                }
+            }
+            let status = self.substitution_status.unwrap_or(0);
+            return self.with_redirections(&command.redirections, |_| status);
+        }
+        let command = expanded;
+        self.trace_command(&command);
+        let name = command.args[0].as_str();
+        if command.args.len() == 1 && name == "exec" && !self.functions.contains_key(name) {
+            // `exec` without a command makes its redirections permanent
+            return match self.redirect(&command.redirections, None) {
+                true => 0,
+                false => 1,
+            };
+        }
+        let builtin = self.built_in_commands.contains(name) || self.functions.contains_key(name);
+        let autocd = self.options.autocd && self.interactive && command.args.len() == 1;
+        if !builtin && autocd && Path::new(name).is_dir() {
+            eprintln!("cd -- {}", name);
+            let args = ["cd".to_string(), name.to_string()];
+            return CdCommand::execute(&args, &self.options);
+        }
+        if builtin {
+            // builtins and functions have to run in the shell process to
+            // affect its state
+            return self.with_redirections(&command.redirections, |shell| {
+                shell.execute_with_assignments(&command)
+            });
+        }
+        self.launch_job(vec![Stage::Expanded(command)], text, true)
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Compound-Commands
+    fn execute_compound_command(&mut self, command: &CompoundCommand, text: &str) -> i32 {
+        match command {
+            CompoundCommand::BraceGroup(list) => self.execute_list(list),
+            CompoundCommand::Subshell(list) => {
+                self.launch_job(vec![Stage::Subshell(list, &[])], text, true)
+            }
+            CompoundCommand::If(command) => self.execute_if(command),
+            CompoundCommand::Loop(command) => self.execute_loop(command),
+            CompoundCommand::For(command) => self.execute_for(command),
+            CompoundCommand::Select(command) => self.execute_select(command),
+            CompoundCommand::Conditional(expression) => {
+                match self.evaluate_conditional(expression) {
+                    Ok(true) => 0,
+                    Ok(false) => 1,
+                    Err(message) => {
+                        eprintln!("rushell: {}", message);
+                        2
+                    }
+                }
+            }
+        }
+    }
+
+    fn execute_if(&mut self, command: &IfCommand) -> i32 {
+        for (condition, body) in &command.branches {
+            let status = self.execute_condition(condition);
+            if self.is_unwinding() {
+                return status;
+            }
+            if status == 0 {
+                return self.execute_list(body);
+            }
+        }
+        match &command.else_branch {
+            Some(list) => self.execute_list(list),
+            None => 0,
+        }
+    }
+
+    fn execute_loop(&mut self, command: &LoopCommand) -> i32 {
+        let mut status = 0;
+        self.loop_depth += 1;
+        loop {
+            let condition = self.execute_condition(&command.condition);
+            if self.end_of_iteration() || (condition == 0) == command.until {
+                break;
+            }
+            status = self.execute_list(&command.body);
+            if self.end_of_iteration() {
+                break;
+            }
+        }
+        self.loop_depth -= 1;
+        status
+    }
+
+    fn execute_for(&mut self, command: &ForCommand) -> i32 {
+        let Some(values) = self.loop_values(command) else {
+            return 1;
+        };
+
+        let mut status = 0;
+        self.loop_depth += 1;
+        for value in values {
+            if let Err(message) = self.variables.set(&command.name, value) {
+                eprintln!("rushell: {}", message);
+                status = 1;
+                break;
+            }
+            status = self.execute_list(&command.body);
+            if self.end_of_iteration() {
+                break;
+            }
+        }
+        self.loop_depth -= 1;
+        status
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-select
+    ///
+    /// Prints a numbered menu of the words and reads a line after `PS3` until
+    /// the input ends or `break`. The line goes to `REPLY` and the word it is
+    /// the number of to the name, which is empty for other lines. An empty
+    /// line shows the menu again.
+    fn execute_select(&mut self, command: &ForCommand) -> i32 {
+        let Some(values) = self.loop_values(command) else {
+            return 1;
+        };
+        if values.is_empty() {
+            return 0;
+        }
+
+        let mut show_menu = true;
+        self.loop_depth += 1;
+        let status = loop {
+            if show_menu {
+                let columns = self.variables.get("COLUMNS").and_then(|c| c.parse().ok());
+                eprint!("{}", select_menu(&values, columns.unwrap_or(80)));
+            }
+            let prompt = self.variables.get("PS3");
+            eprint!("{}", prompt.as_deref().unwrap_or("#? "));
+            let mut line = String::new();
+            if !matches!(
+                LineReader::read_line_from_fd(libc::STDIN_FILENO, &mut line),
+                Ok(1..)
+            ) {
+                println!();
+                break 1;
+            }
+            let reply = line.trim();
+            show_menu = reply.is_empty();
+            if show_menu {
+                continue;
+            }
+            let choice = reply
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| values.get(number.checked_sub(1)?))
+                .cloned()
+                .unwrap_or_default();
+            let assigned = self
+                .variables
+                .set("REPLY", reply.to_string())
+                .and_then(|_| self.variables.set(&command.name, choice));
+            if let Err(message) = assigned {
+                eprintln!("rushell: {}", message);
+                break 1;
+            }
+            let status = self.execute_list(&command.body);
+            if self.end_of_iteration() {
+                break status;
+            }
+        };
+        self.loop_depth -= 1;
+        status
+    }
+
+    /// The expanded words of `for` and `select`, or the positional parameters
+    /// without `in`. `None` if an expansion fails.
+    fn loop_values(&mut self, command: &ForCommand) -> Option<Vec<String>> {
+        let Some(words) = &command.words else {
+            return Some(self.positional_parameters.clone());
+        };
+        let mut values: Vec<String> = Vec::new();
+        for word in words {
+            match expansion::expand_word(word, self) {
+                Ok(fields) => values.extend(fields),
+                Err(e) => {
+                    eprintln!("rushell: {}", e);
+                    return None;
+                }
+            }
+        }
+        Some(values)
+    }
+
+    /// `[[ expression ]]`, where `&&` and `||` short-circuit, the right side of
+    /// `==` and `!=` is a pattern and the right side of `=~` a regular
+    /// expression
+    fn evaluate_conditional(&mut self, expression: &ConditionalExpression) -> Result<bool, String> {
+        let mut expand = |word: &str| {
+            expansion::expand_word_to_string(word, &mut *self).map_err(|e| e.to_string())
+        };
+        match expression {
+            ConditionalExpression::Word(word) => Ok(!expand(word)?.is_empty()),
+            ConditionalExpression::Unary(operator, word) => {
+                Ok(test_command::unary_test(operator, &expand(word)?))
+            }
+            ConditionalExpression::Binary(left, operator, right) => {
+                let left = expand(left)?;
+                match operator.as_str() {
+                    "==" | "=" | "!=" => {
+                        let pattern =
+                            expansion::expand_pattern(right, self).map_err(|e| e.to_string())?;
+                        Ok(pattern::matches(&pattern, &left) == (operator != "!="))
+                    }
+                    "=~" => {
+                        let regex =
+                            expansion::expand_regex(right, self).map_err(|e| e.to_string())?;
+                        self.match_regex(&left, &regex)
+                    }
+                    _ => test_command::binary_test(&left, operator, &expand(right)?),
+                }
+            }
+            ConditionalExpression::Not(expression) => Ok(!self.evaluate_conditional(expression)?),
+            ConditionalExpression::And(left, right) => {
+                Ok(self.evaluate_conditional(left)? && self.evaluate_conditional(right)?)
+            }
+            ConditionalExpression::Or(left, right) => {
+                Ok(self.evaluate_conditional(left)? || self.evaluate_conditional(right)?)
+            }
+        }
+    }
+
+    /// `[[ string =~ regex ]]` sets `BASH_REMATCH` to the matched text followed
+    /// by the text of each capture group
+    fn match_regex(&mut self, string: &str, regex: &str) -> Result<bool, String> {
+        let regex =
+            Regex::new(regex).ok_or_else(|| format!("{}: invalid regular expression", regex))?;
+        let matched = regex.captures(string).unwrap_or_default();
+        let is_match = !matched.is_empty();
+        self.variables.set_array("BASH_REMATCH", matched)?;
+        Ok(is_match)
+    }
+
+    /// Handle `break` and `continue` at the end of an iteration of the
+    /// innermost loop, returns true if the loop must stop
+    fn end_of_iteration(&mut self) -> bool {
+        match self.pending_jump {
+            Some(Jump::Break(n)) => {
+                self.pending_jump = (n > 1).then_some(Jump::Break(n - 1));
+                true
+            }
+            Some(Jump::Continue(n)) if n > 1 => {
+                self.pending_jump = Some(Jump::Continue(n - 1));
+                true
+            }
+            Some(Jump::Continue(_)) => {
+                self.pending_jump = None;
+                false
+            }
+            Some(Jump::Return) | Some(Jump::Interrupt) => true,
+            None => self.pending_exit.is_some(),
+        }
+    }
+
+    /// The condition of `if`, `while` and `until`
+    fn execute_condition(&mut self, list: &CommandList) -> i32 {
+        let in_condition = self.in_condition;
+        self.in_condition = true;
+        let status = self.execute_list(list);
+        self.in_condition = in_condition;
+        status
+    }
+
+    fn define_function(&mut self, definition: &FunctionDefinition) -> i32 {
+        self.functions
+            .insert(definition.name.clone(), Rc::new(definition.clone()));
+        0
+    }
+
+    /// Functions run in the shell process with their own positional parameters
+    /// and local variables
+    fn call_function(&mut self, function: Rc<FunctionDefinition>, args: &[String]) -> i32 {
+        let parameters = std::mem::replace(&mut self.positional_parameters, args[1..].to_vec());
+        self.function_depth += 1;
+        self.variables.push_scope();
+        let text = args.join(" ");
+        let mut status = self.with_redirections(&function.redirections, |shell| {
+            shell.execute_compound_command(&function.body, &text)
+        });
+        self.variables.pop_scope();
+        self.function_depth -= 1;
+        self.positional_parameters = parameters;
+
+        if self.pending_jump == Some(Jump::Return) {
+            self.pending_jump = None;
+            status = self.last_status;
+        }
+        self.last_status = status;
+        self.run_trap(Condition::Return);
+        status
+    }
+
+    /// Every job runs in its own process group, so that the terminal can be
+    /// handed to it and it can be stopped and resumed as a single job.
+    fn launch_job(&mut self, stages: Vec<Stage>, text: &str, foreground: bool) -> i32 {
+        let (pgid, pids, _) = self.start_job(&stages, foreground, false);
+        if pids.is_empty() {
+            return 1;
+        }
+        if foreground {
+            let mut job = Job::new(pgid, pids, text.to_string());
+            job.pipefail = self.options.pipefail;
+            let status = self.jobs.foreground(job, false);
+            if self.interactive && status == 128 + libc::SIGINT {
+                // Ctrl-C abandons the rest of the command line, even in loops
+                self.pending_jump = Some(Jump::Interrupt);
+            }
+            status
+        } else {
+            let mut job = Job::new(pgid, pids, format!("{} &", text));
+            job.pipefail = self.options.pipefail;
+            self.last_background_pid = Some(self.jobs.background(job));
+            0
+        }
+    }
+
+    /// Fork a process for each stage, connected by pipes. Returns the process
+    /// group, the processes and, with `pipe_output`, the read end of a pipe
+    /// from the last stage.
+    fn start_job(
+        &mut self,
+        stages: &[Stage],
+        foreground: bool,
+        pipe_output: bool,
+    ) -> (libc::pid_t, Vec<libc::pid_t>, libc::c_int) {
+        let mut pgid: libc::pid_t = 0;
+        let mut pids: Vec<libc::pid_t> = Vec::new();
+        let mut input_fd = libc::STDIN_FILENO;
+
+        for (i, stage) in stages.iter().enumerate() {
+            let is_last = i == stages.len() - 1 && !pipe_output;
+            let (read_end, output_fd) = if is_last {
+                (-1, libc::STDOUT_FILENO)
+            } else {
+                let mut fds: [libc::c_int; 2] = [0; 2];
+                if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+                    eprintln!("rushell: pipe error: {}", utils::last_os_error());
+                    break;
+                }
+                (fds[0], fds[1])
+            };
+
+            match self.fork() {
+                -1 => break,
+                0 => {
+                    self.setup_child(pgid, foreground);
+                    unsafe {
+                        if input_fd != libc::STDIN_FILENO {
+                            libc::dup2(input_fd, libc::STDIN_FILENO);
+                            libc::close(input_fd);
+                        }
+                        if output_fd != libc::STDOUT_FILENO {
+                            libc::dup2(output_fd, libc::STDOUT_FILENO);
+                            libc::close(output_fd);
+                            libc::close(read_end);
+                        }
+                    }
+                    self.execute_stage(stage);
+                }
+                pid => {
+                    if pgid == 0 {
+                        pgid = pid;
+                    }
+                    self.jobs.setup_parent(pid, pgid);
+                    pids.push(pid);
+                }
+            }
+
+            unsafe {
+                if input_fd != libc::STDIN_FILENO {
+                    libc::close(input_fd);
+                }
+                if output_fd != libc::STDOUT_FILENO {
+                    libc::close(output_fd);
+                }
+            }
+            input_fd = read_end;
+        }
+        (pgid, pids, input_fd)
+    }
+
+    /// Run a command of a job in the forked child
+    fn execute_stage(&mut self, stage: &Stage) -> ! {
+        let status = match stage {
+            Stage::Expanded(command) => self.execute_expanded(command),
+            Stage::External(args) => self.execute_external(&args[0], args),
+            Stage::Subshell(list, redirections) => match self.redirect(redirections, None) {
+                true => self.execute_list(list),
+                false => 1,
+            },
+            Stage::Command(Command::Simple(command)) => match self.expand_command(command) {
+                Ok(command) => {
+                    self.trace_command(&command);
+                    self.execute_expanded(&command)
+                }
+                Err(e) => {
+                    eprintln!("rushell: {}", e);
+                    1
+                }
+            },
+            Stage::Command(Command::Compound(command, redirections)) => {
+                match self.redirect(redirections, None) {
+                    true => self.execute_compound_command(command, ""),
+                    false => 1,
+                }
+            }
+            Stage::Command(Command::FunctionDefinition(definition)) => {
+                self.define_function(definition)
+            }
+        };
+        self.exit_child(self.pending_exit.unwrap_or(status));
+    }
+
+    /// Only called in a forked child
+    fn execute_expanded(&mut self, command: &ExpandedCommand) -> i32 {
+        if !self.redirect(&command.redirections, None) {
+            return 1;
+        }
+        // assignments before a command only apply to its environment
+        for (name, value) in &command.assignments {
+            if let Err(message) = self.variables.set(name, value.clone()) {
+                eprintln!("rushell: {}", message);
+                return 1;
+            }
+            env::set_var(name, self.variables.get(name).unwrap_or_default());
+        }
+        match command.args.is_empty() {
+            true => 0,
+            false => self.execute(&command.args),
+        }
+    }
+
+    fn expand_command(
+        &mut self,
+        command: &SimpleCommand,
+    ) -> Result<ExpandedCommand, ExpansionError> {
+        let mut args: Vec<String> = Vec::new();
+        for word in &command.words {
+            let declaration = args
+                .first()
+                .is_some_and(|name| DECLARATION_COMMANDS.contains(&name.as_str()));
+            match Parser::assignment(word) {
+                Some(assignment) if declaration => args.push(self.expand_declaration(&assignment)?),
+                _ => args.extend(expansion::expand_word(word, self)?),
+            }
+        }
+        // without a command, the assignments are made by `assign`
+        let mut assignments: Vec<(String, String)> = Vec::new();
+        if !args.is_empty() {
+            for Assignment { name, value, .. } in &command.assignments {
+                let value = match value {
+                    AssignmentValue::Scalar(value) => {
+                        expansion::expand_word_to_string(value, self)?
+                    }
+                    AssignmentValue::Array(words) => {
+                        let mut fields: Vec<String> = Vec::new();
+                        for word in words {
+                            fields.extend(expansion::expand_word(word, self)?);
+                        }
+                        fields.join(" ")
+                    }
+                };
+                assignments.push((name.clone(), value));
+            }
+        }
+        Ok(ExpandedCommand {
+            args,
+            assignments,
+            redirections: command.redirections.clone(),
+        })
+    }
+
+    /// Assignments in the arguments of `declare` expand like assignments,
+    /// without word splitting. The values are quoted again for the builtin to
+    /// parse the argument as an assignment.
+    fn expand_declaration(&mut self, assignment: &Assignment) -> Result<String, ExpansionError> {
+        let mut word = assignment.name.clone();
+        if let Some(subscript) = &assignment.subscript {
+            let subscript = expansion::expand_word_to_string(subscript, self)?;
+            word.push_str(&format!("[{}]", utils::quote(&subscript)));
+        }
+        word.push_str(if assignment.append { "+=" } else { "=" });
+        match &assignment.value {
+            AssignmentValue::Scalar(value) => {
+                let value = expansion::expand_word_to_string(value, self)?;
+                word.push_str(&utils::quote(&value));
+            }
+            AssignmentValue::Array(words) => {
+                let mut elements: Vec<String> = Vec::new();
+                for element in words {
+                    match element.strip_prefix('[').and_then(|e| e.split_once("]=")) {
+                        Some((subscript, value)) => {
+                            let subscript = expansion::expand_word_to_string(subscript, self)?;
+                            let value = expansion::expand_word_to_string(value, self)?;
+                            elements.push(format!(
+                                "[{}]={}",
+                                utils::quote(&subscript),
+                                utils::quote(&value)
+                            ));
+                        }
+                        None => {
+                            let fields = expansion::expand_word(element, self)?;
+                            elements.extend(fields.iter().map(|field| utils::quote(field)));
+                        }
+                    }
+                }
+                word.push_str(&format!("({})", elements.join(" ")));
+            }
+        }
+        Ok(word)
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Shell-Parameters
+    ///
+    /// Returns the assignment as it was made after expansion, for `set -x`.
+    pub fn assign(&mut self, assignment: &Assignment) -> Result<String, String> {
+        let Assignment {
+            name,
+            subscript,
+            append,
+            value,
+        } = assignment;
+        let expand = |shell: &mut Shell, word: &str| {
+            expansion::expand_word_to_string(word, shell).map_err(|e| e.to_string())
+        };
+        let operator = if *append { "+=" } else { "=" };
+        match (value, subscript) {
+            (AssignmentValue::Scalar(value), None) => {
+                let value = expand(self, value)?;
+                let word = format!("{}{}{}", name, operator, utils::quote(&value));
+                match append {
+                    true => self.variables.append(name, &value)?,
+                    false => self.variables.set(name, value)?,
+                }
+                Ok(word)
+            }
+            (AssignmentValue::Scalar(value), Some(subscript)) => {
+                let subscript = expand(self, subscript)?;
+                let value = expand(self, value)?;
+                let word = format!(
+                    "{}[{}]{}{}",
+                    name,
+                    subscript,
+                    operator,
+                    utils::quote(&value)
+                );
+                self.variables
+                    .set_element(name, &subscript, value, *append)?;
+                Ok(word)
+            }
+            (AssignmentValue::Array(_), Some(subscript)) => Err(format!(
+                "{}[{}]: cannot assign list to array member",
+                name, subscript
+            )),
+            (AssignmentValue::Array(words), None) => {
+                // words expand like the arguments of a command, except for
+                // `[subscript]=value`
+                let mut elements: Vec<(Option<String>, String)> = Vec::new();
+                for word in words {
+                    let element = word
+                        .strip_prefix('[')
+                        .and_then(|word| word.split_once("]="));
+                    match element {
+                        Some((subscript, value)) => {
+                            elements.push((Some(expand(self, subscript)?), expand(self, value)?));
+                        }
+                        None => {
+                            let fields =
+                                expansion::expand_word(word, self).map_err(|e| e.to_string())?;
+                            elements.extend(fields.into_iter().map(|field| (None, field)));
+                        }
+                    }
+                }
+                let word = elements
+                    .iter()
+                    .map(|(subscript, value)| match subscript {
+                        Some(subscript) => format!("[{}]={}", subscript, utils::quote(value)),
+                        None => utils::quote(value),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let word = format!("{}{}({})", name, operator, word);
+                self.variables.assign_array(name, elements, *append)?;
+                Ok(word)
+            }
+        }
+    }
+
+    /// With `set -x`, print a command before it runs, after `PS4`
+    fn trace(&mut self, words: &[String]) {
+        if self.options.xtrace {
+            let prompt = self.prompt("PS4");
+            eprintln!("{}{}", prompt, words.join(" "));
+        }
+    }
+
+    fn trace_command(&mut self, command: &ExpandedCommand) {
+        for (name, value) in &command.assignments {
+            self.trace(&[format!("{}={}", name, utils::quote(value))]);
+        }
+        let args: Vec<String> = command.args.iter().map(|arg| utils::quote(arg)).collect();
+        self.trace(&args);
+    }
+
+    /// Run `f` in the shell process with `redirections` applied, and put the
+    /// file descriptors back afterwards
+    fn with_redirections(
+        &mut self,
+        redirections: &[Redirection],
+        f: impl FnOnce(&mut Shell) -> i32,
+    ) -> i32 {
+        if redirections.is_empty() {
+            return f(self);
+        }
+        let mut saved = SavedFds::new();
+        let status = match self.redirect(redirections, Some(&mut saved)) {
+            true => f(self),
+            false => 1,
+        };
+        stdout().flush().unwrap();
+        saved.restore();
+        status
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Redirections
+    ///
+    /// With `saved`, the replaced file descriptors are kept so that they can be
+    /// restored. Reports the first redirection that fails and returns false.
+    fn redirect(&mut self, redirections: &[Redirection], mut saved: Option<&mut SavedFds>) -> bool {
+        // output of builtins must not end up in the new file
+        stdout().flush().unwrap();
+        for redirection in redirections {
+            if let Err(message) = self.apply_redirection(redirection, saved.as_deref_mut()) {
+                eprintln!("rushell: {}", message);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn apply_redirection(
+        &mut self,
+        redirection: &Redirection,
+        mut saved: Option<&mut SavedFds>,
+    ) -> Result<(), String> {
+        let fd = redirection.fd.unwrap_or(redirection.operator.default_fd());
+        let mut operator = redirection.operator;
+        let contents = match operator {
+            RedirectionOperator::HereDocument { quoted: true } => Some(redirection.target.clone()),
+            RedirectionOperator::HereDocument { quoted: false } => Some(
+                expansion::expand_here_document(&redirection.target, self)
+                    .map_err(|e| e.to_string())?,
+            ),
+            RedirectionOperator::HereString => Some(
+                expansion::expand_word_to_string(&redirection.target, self)
+                    .map_err(|e| e.to_string())?
+                    + "\n",
+            ),
+            _ => None,
+        };
+        if let Some(contents) = contents {
+            let source = redirection::here_document(&contents)
+                .map_err(|e| format!("cannot create temp file for here-document: {}", e))?;
+            return redirection::replace(source, fd, saved).map_err(|e| utils::error_message(&e));
+        }
+
+        let fields =
+            expansion::expand_word(&redirection.target, self).map_err(|e| e.to_string())?;
+        let [target] = fields.as_slice() else {
+            return Err(format!("{}: ambiguous redirect", redirection.target));
+        };
+        if matches!(
+            operator,
+            RedirectionOperator::DuplicateInput | RedirectionOperator::DuplicateOutput
+        ) {
+            if target == "-" {
+                return redirection::duplicate(None, fd, saved)
+                    .map_err(|e| utils::error_message(&e));
+            }
+            if let Ok(source) = target.parse::<libc::c_int>() {
+                return redirection::duplicate(Some(source), fd, saved)
+                    .map_err(|e| format!("{}: {}", target, utils::error_message(&e)));
+            }
+            // `>&file` is the same as `&>file`
+            if operator == RedirectionOperator::DuplicateInput || redirection.fd.is_some() {
+                return Err(format!("{}: ambiguous redirect", redirection.target));
+            }
+            operator = RedirectionOperator::OutputAndError;
+        }
+
+        let source = redirection::open(target, operator, self.options.noclobber)
+            .map_err(|e| format!("{}: {}", target, utils::error_message(&e)))?;
+        if matches!(
+            operator,
+            RedirectionOperator::OutputAndError | RedirectionOperator::AppendOutputAndError
+        ) {
+            redirection::duplicate(Some(source), libc::STDERR_FILENO, saved.as_deref_mut())
+                .map_err(|e| utils::error_message(&e))?;
+        }
+        redirection::replace(source, fd, saved).map_err(|e| utils::error_message(&e))
+    }
+
+    /// Assignments before a builtin only last until it returns
+    fn execute_with_assignments(&mut self, command: &ExpandedCommand) -> i32 {
+        let mut saved: Vec<(&String, Option<String>)> = Vec::new();
+        let mut status = 0;
+        for (name, value) in &command.assignments {
+            let previous = self.variables.get(name);
+            if let Err(message) = self.variables.set(name, value.clone()) {
+                eprintln!("rushell: {}", message);
+                status = 1;
+                break;
+            }
+            saved.push((name, previous));
+        }
+        if status == 0 {
+            status = self.execute(&command.args);
+        }
+        for (name, value) in saved.into_iter().rev() {
+            match value {
+                Some(value) => self.variables.set(name, value).ok(),
+                None => self.variables.unset(name).ok(),
+            };
+        }
+        status
+    }
+
+    fn fork(&self) -> libc::pid_t {
+        // anything still buffered would otherwise be written by both processes
+        stdout().flush().unwrap();
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            eprintln!("rushell: fork: {}", utils::last_os_error());
+        }
+        pid
+    }
+
+    fn setup_child(&mut self, pgid: libc::pid_t, foreground: bool) {
+        self.jobs.setup_child(pgid, foreground);
+        self.reset_for_subshell();
+        if !foreground && !self.jobs.is_enabled() {
+            // without job control, Ctrl-C must only interrupt foreground commands
+            signals::ignore(libc::SIGINT);
+            signals::ignore(libc::SIGQUIT);
+        }
+    }
+
+    /// Subshells have no job control of their own and do not inherit the
+    /// signal dispositions of the shell
+    fn reset_for_subshell(&mut self) {
+        self.jobs = Jobs::new();
+        signals::reset();
+        self.traps.reset_for_subshell();
+    }
+
+    /// Run the actions of trapped signals received since the last safe point
+    fn run_pending_traps(&mut self) {
+        for (signal, _) in signals::SIGNALS {
+            let condition = Condition::Signal(*signal);
+            if self.traps.get(condition).is_some() && signals::take(*signal) {
+                self.run_trap(condition);
+            }
+        }
+    }
+
+    fn run_trap(&mut self, condition: Condition) {
+        let action = match self.traps.get(condition) {
+            Some(action) if !action.is_empty() => action.clone(),
+            _ => return,
+        };
+        if self.running_trap && !matches!(condition, Condition::Signal(_)) {
+            return;
+        }
+
+        // the action must not change `$?`
+        let status = self.last_status;
+        let running_trap = self.running_trap;
+        self.running_trap = true;
+        self.run(action);
+        self.running_trap = running_trap;
+        self.last_status = status;
+    }
+
+    fn exit_child(&self, status: i32) -> ! {
+        stdout().flush().unwrap();
+        unsafe { libc::_exit(status) }
+    }
+
+    fn execute(&mut self, args: &[String]) -> i32 {
         assert!(!args.is_empty());
         let command: &str = args.first().unwrap().as_str();
+        if command != "exit" {
+            self.stopped_jobs_warned = false;
+        }
 
-        if self.built_in_commands.contains(command) {
+        if let Some(function) = self.functions.get(command) {
+            self.call_function(Rc::clone(function), args)
+        } else if self.built_in_commands.contains(command) {
             self.execute_built_in(command, args)
         } else {
             self.execute_external(command, args)
@@ -77,34 +1544,475 @@ impl Shell {
     }
 
     fn get_built_in_commands() -> HashSet<String> {
-        HashSet::from(["exit", "echo", "type", "pwd", "cd"].map(str::to_string))
+        HashSet::from(
+            [
+                "exit", "echo", "type", "pwd", "cd", "jobs", "fg", "bg", "trap", "break",
+                "continue", "return", "printf", "read", "test", "[", "unset", "declare", "typeset",
+                "local", "readonly", "export", "set", "shopt", "alias", "unalias", "source", ".",
+                "eval", "exec", "command", "builtin",
+            ]
+            .map(str::to_string),
+        )
     }
 
-    fn execute_built_in(&self, command: &str, args: &[String]) -> i32 {
+    fn execute_built_in(&mut self, command: &str, args: &[String]) -> i32 {
         match command {
-            "exit" => ExitCommand::execute(args),
-            "echo" => EchoCommand::execute(args),
-            "type" => TypeCommand::execute(args, &self.built_in_commands),
+            "exit" => ExitCommand::execute(args, self),
+            "echo" => EchoCommand::execute(args, self.options.xpg_echo),
+            "type" => TypeCommand::execute(
+                args,
+                &self.aliases,
+                &self.built_in_commands,
+                &self.functions,
+            ),
             "pwd" => PwdCommand::execute(args),
-            "cd" => CdCommand::execute(args),
+            "cd" => CdCommand::execute(args, &self.options),
+            "jobs" => JobsCommand::execute(args, &mut self.jobs),
+            "fg" => FgCommand::execute(args, &mut self.jobs),
+            "bg" => BgCommand::execute(args, &mut self.jobs),
+            "trap" => TrapCommand::execute(args, &mut self.traps),
+            "break" => BreakCommand::execute(args, self),
+            "continue" => ContinueCommand::execute(args, self),
+            "return" => ReturnCommand::execute(args, self),
+            "printf" => PrintfCommand::execute(args, &mut self.variables),
+            "read" => ReadCommand::execute(args, &mut self.variables),
+            "test" | "[" => TestCommand::execute(args),
+            "unset" => UnsetCommand::execute(args, &mut self.variables, &mut self.functions),
+            "declare" | "typeset" | "local" | "readonly" | "export" => {
+                DeclareCommand::execute(args, self)
+            }
+            "set" => SetCommand::execute(args, self),
+            "shopt" => ShoptCommand::execute(args, self),
+            "alias" => AliasCommand::execute(args, &mut self.aliases),
+            "unalias" => UnaliasCommand::execute(args, &mut self.aliases),
+            "source" | "." => SourceCommand::execute(args, self),
+            "eval" => EvalCommand::execute(args, self),
+            "exec" => ExecCommand::execute(args, self),
+            "command" => CommandCommand::execute(args, self),
+            "builtin" => BuiltinCommand::execute(args, self),
             _ => self.command_not_found(command),
         }
     }
 
+    /// Only called in a forked child, replaces the process with `command`
     fn execute_external(&self, command: &str, args: &[String]) -> i32 {
-        let cmd = Command::new(command).args(&args[1..]).output();
-        if let Ok(output) = cmd {
-            stdout().write_all(&output.stdout).unwrap();
-            stdout().flush().unwrap();
-            stderr().write_all(&output.stderr).unwrap();
-            stderr().flush().unwrap();
-
-            output.status.code().unwrap_or(
-                // TODO: what do we return when status code is None
-                1,
-            )
-        } else {
-            self.command_not_found(command)
+        let error = process::Command::new(command).args(&args[1..]).exec();
+        match error.kind() {
+            ErrorKind::NotFound => self.command_not_found(command),
+            _ => {
+                eprintln!("{}: {}", command, utils::error_message(&error));
+                126
+            }
+        }
+    }
+}
+
+/// The startup files to run, chosen on the command line
+///
+/// https://www.gnu.org/software/bash/manual/bash.html#Bash-Startup-Files
+#[derive(Debug, Default)]
+pub struct StartupFiles {
+    /// `-l`, `--login` or a name starting with `-`: run `/etc/profile` and
+    /// `~/.rushell_profile`
+    pub login: bool,
+    /// `--noprofile`
+    pub no_profile: bool,
+    /// `--norc`: interactive shells skip `~/.rushellrc`
+    pub no_rc: bool,
+    /// `--rcfile file`, run instead of `~/.rushellrc`
+    pub rc_file: Option<String>,
+}
+
+impl StartupFiles {
+    /// Login shells run the profiles, other interactive shells run
+    /// `~/.rushellrc`
+    fn files(&self, interactive: bool, home: Option<&str>) -> Vec<String> {
+        let mut files = Vec::new();
+        if self.login {
+            if !self.no_profile {
+                files.push("/etc/profile".to_string());
+                files.extend(home.map(|home| format!("{}/.rushell_profile", home)));
+            }
+        } else if interactive && !self.no_rc {
+            match &self.rc_file {
+                Some(rc_file) => files.push(rc_file.clone()),
+                None => files.extend(home.map(|home| format!("{}/.rushellrc", home))),
+            }
+        }
+        files
+    }
+}
+
+/// The menu of `select`, numbered from 1 and laid out in as many columns of
+/// `columns` as fit, with tabs like bash
+fn select_menu(values: &[String], columns: usize) -> String {
+    let digits = |n: usize| n.to_string().len();
+    let number_width = digits(values.len());
+    let widest = values.iter().map(|value| value.width()).max().unwrap_or(0);
+    // `n) ` and two spaces before the next column
+    let column_width = widest + number_width + 4;
+    let fitting = (columns / column_width).max(1);
+    let mut rows = values.len().div_ceil(fitting);
+    if rows == 1 {
+        rows = values.len();
+    }
+
+    let mut menu = String::new();
+    for row in 0..rows {
+        let mut index = row;
+        let mut position = 0;
+        loop {
+            // the first column only needs room for the numbers of the rows
+            let width = if position == 0 {
+                digits(rows)
+            } else {
+                number_width
+            };
+            menu.push_str(&format!("{:>width$}) {}", index + 1, values[index]));
+            let mut end = position + width + 2 + values[index].width();
+            index += rows;
+            if index >= values.len() {
+                break;
+            }
+            position += column_width;
+            while end < position {
+                if position / 8 > end / 8 {
+                    menu.push('\t');
+                    end += 8 - end % 8;
+                } else {
+                    menu.push(' ');
+                    end += 1;
+                }
+            }
         }
+        menu.push('\n');
+    }
+    menu
+}
+
+/// Non-local control flow, which unwinds to the enclosing loop or function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Jump {
+    /// `break n`
+    Break(usize),
+    /// `continue n`
+    Continue(usize),
+    Return,
+    /// a foreground job was killed by Ctrl-C
+    Interrupt,
+}
+
+/// Builtins whose arguments may be assignments
+const DECLARATION_COMMANDS: [&str; 5] = ["declare", "typeset", "local", "readonly", "export"];
+
+/// A simple command after expansion
+struct ExpandedCommand {
+    args: Vec<String>,
+    assignments: Vec<(String, String)>,
+    /// expanded when they are applied
+    redirections: Vec<Redirection>,
+}
+
+/// A command of a job, which runs in a forked child
+enum Stage<'a> {
+    Expanded(ExpandedCommand),
+    /// run by `command`, which skips functions
+    External(Vec<String>),
+    Command(&'a Command),
+    Subshell(&'a CommandList, &'a [Redirection]),
+}
+
+impl Environment for Shell {
+    fn parameter(&mut self, name: &str) -> Option<String> {
+        match name {
+            "#" => Some(self.positional_parameters.len().to_string()),
+            "?" => Some(self.last_status.to_string()),
+            "$" => Some(self.pid.to_string()),
+            "!" => self.last_background_pid.map(|pid| pid.to_string()),
+            "0" => Some(self.name.clone()),
+            "-" => {
+                let mut flags = self.options.flags();
+                if self.interactive {
+                    flags.push('i');
+                }
+                Some(flags)
+            }
+            "@" | "*" => Some(self.positional_parameters.join(" ")),
+            _ => match name.parse::<usize>() {
+                Ok(n) => n
+                    .checked_sub(1)
+                    .and_then(|i| self.positional_parameters.get(i).cloned()),
+                Err(_) => self.variables.get(name),
+            },
+        }
+    }
+
+    fn positional_parameters(&self) -> Vec<String> {
+        self.positional_parameters.clone()
+    }
+
+    fn element(&mut self, name: &str, subscript: &str) -> Result<Option<String>, ExpansionError> {
+        self.variables
+            .element(name, subscript)
+            .map_err(|message| ExpansionError { message })
+    }
+
+    fn elements(&mut self, name: &str) -> Vec<(String, String)> {
+        self.variables.elements(name)
+    }
+
+    /// A script stops at the first unset parameter with `set -u`
+    fn unbound(&mut self, name: &str) -> Result<(), ExpansionError> {
+        if !self.options.nounset {
+            return Ok(());
+        }
+        if !self.interactive {
+            self.request_exit(1);
+        }
+        Err(ExpansionError {
+            message: format!("{}: unbound variable", name),
+        })
+    }
+
+    /// A script stops when `${name=word}` cannot assign, like with `set -u`
+    fn assign(&mut self, name: &str, value: String) -> Result<(), ExpansionError> {
+        self.variables.set(name, value).map_err(|message| {
+            if !self.interactive {
+                self.request_exit(1);
+            }
+            ExpansionError { message }
+        })
+    }
+
+    fn unset_error(&mut self, message: String) -> ExpansionError {
+        if !self.interactive {
+            self.request_exit(1);
+        }
+        ExpansionError { message }
+    }
+
+    /// https://www.gnu.org/software/bash/manual/bash.html#Command-Substitution
+    fn command_substitution(&mut self, command: &str) -> Result<String, ExpansionError> {
+        let list = self.parse(command).map_err(|e| ExpansionError {
+            message: e.to_string(),
+        })?;
+        let mut fds: [libc::c_int; 2] = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+            return Err(ExpansionError {
+                message: format!("pipe error: {}", utils::last_os_error()),
+            });
+        }
+        let [read_end, write_end] = fds;
+        let pid = self.fork();
+        if pid < 0 {
+            unsafe {
+                libc::close(read_end);
+                libc::close(write_end);
+            }
+            return Err(ExpansionError {
+                message: "cannot make child for command substitution".to_string(),
+            });
+        }
+        if pid == 0 {
+            // the child stays in the process group of the shell, where Ctrl-Z
+            // must not stop it
+            let job_control = self.jobs.is_enabled();
+            self.reset_for_subshell();
+            if job_control {
+                for signal in [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU] {
+                    signals::ignore(signal);
+                }
+            }
+            unsafe {
+                libc::close(read_end);
+                libc::dup2(write_end, libc::STDOUT_FILENO);
+                libc::close(write_end);
+            }
+            let status = self.execute_list(&list);
+            self.exit_child(self.pending_exit.unwrap_or(status));
+        }
+
+        unsafe { libc::close(write_end) };
+        let mut output = Vec::new();
+        let mut reader = unsafe { File::from_raw_fd(read_end) };
+        let result = reader.read_to_end(&mut output);
+        drop(reader);
+        let status = self.jobs.wait_for_process(pid);
+        self.last_status = status;
+        self.substitution_status = Some(status);
+        if let Err(e) = result {
+            return Err(ExpansionError {
+                message: format!("command substitution: {}", utils::error_message(&e)),
+            });
+        }
+        let output = String::from_utf8_lossy(&output);
+        Ok(output.trim_end_matches('\n').to_string())
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{select_menu, LineReader, Shell, StartupFiles};
+
+    fn new_shell() -> Shell {
+        let mut shell = Shell::new();
+        for name in ["a", "b", "status"] {
+            shell.variables().unset(name).ok();
+        }
+        shell
+    }
+
+    #[test]
+    fn test_select_menu() {
+        let values: Vec<String> = ["apple", "b c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(select_menu(&values, 80), "1) apple\n2) b c\n");
+
+        let values: Vec<String> = (1..=12).map(|n| format!("item{}", n)).collect();
+        assert_eq!(
+            select_menu(&values, 40),
+            "1) item1     5) item5\t 9) item9\n\
+             2) item2     6) item6\t10) item10\n\
+             3) item3     7) item7\t11) item11\n\
+             4) item4     8) item8\t12) item12\n"
+        );
+    }
+
+    #[test]
+    fn test_startup_files() {
+        let home = Some("/home/me");
+        let login = StartupFiles {
+            login: true,
+            ..Default::default()
+        };
+        let profiles = ["/etc/profile", "/home/me/.rushell_profile"];
+        assert_eq!(login.files(false, home), profiles);
+        // interactive login shells do not run the rc file
+        assert_eq!(login.files(true, home), profiles);
+        let no_profile = StartupFiles {
+            no_profile: true,
+            ..login
+        };
+        assert!(no_profile.files(true, home).is_empty());
+
+        let startup = StartupFiles::default();
+        assert_eq!(startup.files(true, home), ["/home/me/.rushellrc"]);
+        assert!(startup.files(false, home).is_empty());
+        assert!(startup.files(true, None).is_empty());
+        let rc_file = StartupFiles {
+            rc_file: Some("rc".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(rc_file.files(true, home), ["rc"]);
+        let no_rc = StartupFiles {
+            no_rc: true,
+            ..rc_file
+        };
+        assert!(no_rc.files(true, home).is_empty());
+    }
+
+    #[test]
+    fn test_posix_startup_file() {
+        let name = format!("rushell-{}-env", std::process::id());
+        let path = std::env::temp_dir().join(&name);
+        fs::write(&path, "a=1\n").unwrap();
+        let mut shell = new_shell();
+        shell.options.posix = true;
+        shell.eval(&format!(
+            "directory={}; ENV='$directory/{}'",
+            path.parent().unwrap().display(),
+            name
+        ));
+        shell.run_startup_files(&StartupFiles::default());
+        assert_eq!(shell.variable("a"), None);
+        shell.interactive = true;
+        shell.run_startup_files(&StartupFiles::default());
+        assert_eq!(shell.variable("a").as_deref(), Some("1"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_noexec_stops_commands_at_once() {
+        let mut shell = new_shell();
+        shell.eval("a=1; set -n; b=1");
+        assert_eq!(shell.variable("a").as_deref(), Some("1"));
+        assert_eq!(shell.variable("b"), None);
+
+        let mut shell = new_shell();
+        shell.eval("if true; then set -n && b=1; fi; b=2");
+        assert_eq!(shell.variable("b"), None);
+    }
+
+    #[test]
+    fn test_ignored_eof_limit() {
+        let mut shell = new_shell();
+        shell.variables().unset("IGNOREEOF").ok();
+        assert_eq!(shell.ignored_eof_limit(), 0);
+        shell.eval("set -o ignoreeof");
+        assert_eq!(shell.ignored_eof_limit(), 10);
+        for (value, limit) in [
+            ("3", 3),
+            ("0", 0),
+            ("", 10),
+            ("abc", 10),
+            ("-1", 10),
+            ("+2", 10),
+        ] {
+            shell.eval(&format!("IGNOREEOF='{}'", value));
+            assert_eq!(shell.ignored_eof_limit(), limit, "{:?}", value);
+        }
+        shell.eval("set +o ignoreeof; IGNOREEOF=99999999999999999999999");
+        assert_eq!(shell.ignored_eof_limit(), usize::MAX);
+        shell.eval("unset IGNOREEOF");
+        assert_eq!(shell.ignored_eof_limit(), 0);
+    }
+
+    #[test]
+    fn test_end_of_input_keeps_last_status() {
+        let path = std::env::temp_dir().join(format!("rushell-{}-eof", std::process::id()));
+        fs::write(&path, "a=1\nfalse").unwrap();
+        let mut shell = new_shell();
+        shell.run_reader(&mut LineReader::from_file(fs::File::open(&path).unwrap()));
+        assert_eq!(shell.last_status(), 1);
+        assert_eq!(shell.variable("a").as_deref(), Some("1"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_exit_status_is_taken_mod_256() {
+        for (source, status) in [("exit 300", 44), ("exit -1", 255), ("exit 256", 0)] {
+            let mut shell = new_shell();
+            shell.eval(source);
+            assert_eq!(shell.pending_exit, Some(status), "{}", source);
+        }
+
+        let mut shell = new_shell();
+        shell.eval("false; exit");
+        assert_eq!(shell.pending_exit, Some(1));
+
+        let mut shell = new_shell();
+        shell.eval("exit 3; a=1");
+        assert_eq!(shell.pending_exit, Some(3));
+        assert_eq!(shell.variable("a"), None);
+    }
+
+    #[test]
+    fn test_exit_trap_runs_on_exit() {
+        let mut shell = new_shell();
+        shell.eval("trap 'status=$?; a=1' EXIT; exit 7");
+        assert_eq!(shell.run_exit_trap(7), 7);
+        assert_eq!(shell.variable("status").as_deref(), Some("7"));
+        assert_eq!(shell.variable("a").as_deref(), Some("1"));
+
+        // the trap runs only once, and `exit` in it sets the status
+        let mut shell = new_shell();
+        shell.eval("trap 'a=1; exit 9; b=1' EXIT");
+        assert_eq!(shell.run_exit_trap(0), 9);
+        assert_eq!(shell.variable("a").as_deref(), Some("1"));
+        assert_eq!(shell.variable("b"), None);
+        assert_eq!(shell.run_exit_trap(0), 0);
     }
 }
@@ -0,0 +1,105 @@
+use crate::shell::Shell;
+
+pub struct ShoptCommand;
+
+/// Options of `shopt`
+#[derive(Default)]
+struct Flags {
+    /// print the options as commands that restore them
+    print: bool,
+    /// only return whether the options are on
+    quiet: bool,
+    set: bool,
+    unset: bool,
+    /// work on the options of `set -o` instead
+    set_options: bool,
+}
+
+impl ShoptCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#The-Shopt-Builtin
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let mut flags = Flags::default();
+        let mut index = 1;
+        while let Some(arg) = args.get(index) {
+            if arg == "--" {
+                index += 1;
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            index += 1;
+            for letter in arg[1..].chars() {
+                match letter {
+                    'p' => flags.print = true,
+                    'q' => flags.quiet = true,
+                    's' => flags.set = true,
+                    'u' => flags.unset = true,
+                    'o' => flags.set_options = true,
+                    _ => {
+                        eprintln!("shopt: -{}: invalid option", letter);
+                        eprintln!("shopt: usage: shopt [-pqsu] [-o] [optname ...]");
+                        return 2;
+                    }
+                }
+            }
+        }
+        if flags.set && flags.unset {
+            eprintln!("shopt: cannot set and unset shell options simultaneously");
+            return 1;
+        }
+
+        let names = &args[index..];
+        let options = match flags.set_options {
+            true => shell.options().list(),
+            false => shell.options().shopt_list(),
+        };
+        if names.is_empty() {
+            // `-s` and `-u` alone list the options that are on or off
+            for (name, on) in options {
+                if (!flags.set || on) && (!flags.unset || !on) && !flags.quiet {
+                    ShoptCommand::print(name, on, &flags);
+                }
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for name in names {
+            let Some(&(_, on)) = options.iter().find(|(option, _)| option == name) else {
+                match flags.set_options {
+                    true => eprintln!("shopt: {}: invalid option name", name),
+                    false => eprintln!("shopt: {}: invalid shell option name", name),
+                }
+                status = 1;
+                continue;
+            };
+            if flags.set || flags.unset {
+                let result = match flags.set_options {
+                    true => shell.options().set(name, flags.set),
+                    false => shell.options().shopt(name, flags.set),
+                };
+                if let Err(message) = result {
+                    eprintln!("shopt: {}", message);
+                    status = 1;
+                }
+                continue;
+            }
+            if !on {
+                status = 1;
+            }
+            if !flags.quiet {
+                ShoptCommand::print(name, on, &flags);
+            }
+        }
+        status
+    }
+
+    fn print(name: &str, on: bool, flags: &Flags) {
+        match (flags.print, flags.set_options) {
+            (false, _) => println!("{:<15}\t{}", name, if on { "on" } else { "off" }),
+            (true, false) => println!("shopt {} {}", if on { "-s" } else { "-u" }, name),
+            (true, true) => println!("set {}o {}", if on { "-" } else { "+" }, name),
+        }
+    }
+}
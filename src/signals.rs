@@ -0,0 +1,202 @@
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use libc::c_int;
+
+/// Bitmask of signals received by the shell that have not been handled yet
+static PENDING: AtomicU64 = AtomicU64::new(0);
+/// Signals the shell catches or ignores for its own sake, restored when a trap
+/// on them is reset
+static SHELL_CAUGHT: AtomicU64 = AtomicU64::new(0);
+static SHELL_IGNORED: AtomicU64 = AtomicU64::new(0);
+
+/// Signals that can be named in `trap` and `kill`, without the `SIG` prefix
+pub const SIGNALS: &[(c_int, &str)] = &[
+    (libc::SIGHUP, "HUP"),
+    (libc::SIGINT, "INT"),
+    (libc::SIGQUIT, "QUIT"),
+    (libc::SIGILL, "ILL"),
+    (libc::SIGTRAP, "TRAP"),
+    (libc::SIGABRT, "ABRT"),
+    (libc::SIGBUS, "BUS"),
+    (libc::SIGFPE, "FPE"),
+    (libc::SIGKILL, "KILL"),
+    (libc::SIGUSR1, "USR1"),
+    (libc::SIGSEGV, "SEGV"),
+    (libc::SIGUSR2, "USR2"),
+    (libc::SIGPIPE, "PIPE"),
+    (libc::SIGALRM, "ALRM"),
+    (libc::SIGTERM, "TERM"),
+    (libc::SIGCHLD, "CHLD"),
+    (libc::SIGCONT, "CONT"),
+    (libc::SIGSTOP, "STOP"),
+    (libc::SIGTSTP, "TSTP"),
+    (libc::SIGTTIN, "TTIN"),
+    (libc::SIGTTOU, "TTOU"),
+    (libc::SIGURG, "URG"),
+    (libc::SIGXCPU, "XCPU"),
+    (libc::SIGXFSZ, "XFSZ"),
+    (libc::SIGVTALRM, "VTALRM"),
+    (libc::SIGPROF, "PROF"),
+    (libc::SIGWINCH, "WINCH"),
+    (libc::SIGIO, "IO"),
+    (libc::SIGSYS, "SYS"),
+];
+
+extern "C" fn handler(signal: c_int) {
+    PENDING.fetch_or(1 << signal, Ordering::SeqCst);
+}
+
+/// Catch `signal` and record it as pending. Slow system calls like `read` and
+/// `waitpid` are not restarted, so the shell gets a chance to react.
+pub fn catch(signal: c_int) {
+    set_action(
+        signal,
+        handler as extern "C" fn(c_int) as libc::sighandler_t,
+    );
+}
+
+pub fn ignore(signal: c_int) {
+    set_action(signal, libc::SIG_IGN);
+}
+
+pub fn default(signal: c_int) {
+    set_action(signal, libc::SIG_DFL);
+}
+
+/// Go back to the disposition the shell uses for `signal` when it is not trapped
+pub fn restore(signal: c_int) {
+    let mask = 1 << signal;
+    if SHELL_CAUGHT.load(Ordering::SeqCst) & mask != 0 {
+        catch(signal);
+    } else if SHELL_IGNORED.load(Ordering::SeqCst) & mask != 0 {
+        ignore(signal);
+    } else {
+        default(signal);
+    }
+}
+
+pub fn is_pending(signal: c_int) -> bool {
+    PENDING.load(Ordering::SeqCst) & (1 << signal) != 0
+}
+
+/// Returns whether `signal` was pending and marks it as handled
+pub fn take(signal: c_int) -> bool {
+    let mask = 1 << signal;
+    PENDING.fetch_and(!mask, Ordering::SeqCst) & mask != 0
+}
+
+/// The interactive shell must survive Ctrl-C and Ctrl-\, which are meant for
+/// the foreground job or for discarding the line being typed.
+pub fn init_interactive() {
+    for signal in [libc::SIGINT, libc::SIGQUIT] {
+        SHELL_CAUGHT.fetch_or(1 << signal, Ordering::SeqCst);
+        catch(signal);
+    }
+}
+
+/// Ctrl-Z should stop the foreground job, not the shell
+pub fn init_job_control() {
+    for signal in [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU] {
+        SHELL_IGNORED.fetch_or(1 << signal, Ordering::SeqCst);
+        ignore(signal);
+    }
+}
+
+/// Called in a forked child, so that commands start with the default signal
+/// dispositions rather than the ones the shell uses for itself
+pub fn reset() {
+    for signal in [
+        libc::SIGINT,
+        libc::SIGQUIT,
+        libc::SIGTSTP,
+        libc::SIGTTIN,
+        libc::SIGTTOU,
+        libc::SIGPIPE,
+    ] {
+        default(signal);
+    }
+}
+
+/// Parse a signal name like `INT`, `SIGINT` or `sigint`, or a signal number
+pub fn from_name(name: &str) -> Option<c_int> {
+    if let Ok(number) = name.parse::<c_int>() {
+        return SIGNALS
+            .iter()
+            .find(|(signal, _)| *signal == number)
+            .map(|(signal, _)| *signal);
+    }
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNALS
+        .iter()
+        .find(|(_, other)| *other == name)
+        .map(|(signal, _)| *signal)
+}
+
+/// Name of `signal` with the `SIG` prefix
+pub fn name(signal: c_int) -> String {
+    match SIGNALS.iter().find(|(other, _)| *other == signal) {
+        Some((_, name)) => format!("SIG{}", name),
+        None => signal.to_string(),
+    }
+}
+
+fn set_action(signal: c_int, handler: libc::sighandler_t) {
+    unsafe {
+        let mut action: libc::sigaction = MaybeUninit::zeroed().assume_init();
+        action.sa_sigaction = handler;
+        action.sa_flags = 0;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(signal, &action, std::ptr::null_mut());
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use super::{catch, default, from_name, ignore, is_pending, name, reset, take};
+
+    fn disposition(signal: libc::c_int) -> libc::sighandler_t {
+        unsafe {
+            let mut action: libc::sigaction = MaybeUninit::zeroed().assume_init();
+            libc::sigaction(signal, std::ptr::null(), &mut action);
+            action.sa_sigaction
+        }
+    }
+
+    #[test]
+    fn test_caught_signals_are_pending() {
+        // SIGWINCH is harmless if it arrives while not caught
+        catch(libc::SIGWINCH);
+        assert!(!take(libc::SIGWINCH));
+        unsafe { libc::raise(libc::SIGWINCH) };
+        assert!(is_pending(libc::SIGWINCH));
+        assert!(take(libc::SIGWINCH));
+        assert!(!is_pending(libc::SIGWINCH));
+        default(libc::SIGWINCH);
+    }
+
+    #[test]
+    fn test_children_get_default_dispositions() {
+        ignore(libc::SIGTTIN);
+        catch(libc::SIGTTOU);
+        assert_eq!(disposition(libc::SIGTTIN), libc::SIG_IGN);
+        reset();
+        assert_eq!(disposition(libc::SIGTTIN), libc::SIG_DFL);
+        assert_eq!(disposition(libc::SIGTTOU), libc::SIG_DFL);
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(from_name("INT"), Some(libc::SIGINT));
+        assert_eq!(from_name("sigterm"), Some(libc::SIGTERM));
+        assert_eq!(from_name("9"), Some(libc::SIGKILL));
+        assert_eq!(from_name("0"), None);
+        assert_eq!(from_name("SIGNOPE"), None);
+        assert_eq!(name(libc::SIGQUIT), "SIGQUIT");
+    }
+}
@@ -0,0 +1,110 @@
+use std::env;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::shell::Shell;
+use crate::utils;
+
+pub struct SourceCommand;
+
+impl SourceCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-source
+    ///
+    /// Also runs as `.`. A name without a slash is searched for in `PATH`,
+    /// then in the current directory.
+    pub fn execute(args: &[String], shell: &mut Shell) -> i32 {
+        let mut operands = &args[1..];
+        if operands.first().is_some_and(|arg| arg == "--") {
+            operands = &operands[1..];
+        }
+        let Some(name) = operands.first() else {
+            eprintln!("{}: filename argument required", args[0]);
+            eprintln!("{}: usage: {} filename [arguments]", args[0], args[0]);
+            return 2;
+        };
+
+        let path = SourceCommand::find(name);
+        if path.is_dir() {
+            eprintln!("{}: {}: is a directory", args[0], name);
+            return 1;
+        }
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("{}: {}", name, utils::error_message(&e));
+                return 1;
+            }
+        };
+        let parameters = (operands.len() > 1).then(|| operands[1..].to_vec());
+        shell.source(file, parameters)
+    }
+
+    fn find(name: &str) -> PathBuf {
+        if !name.contains('/') {
+            let path = env::var("PATH").unwrap_or_default();
+            let found = path
+                .split(':')
+                .map(|directory| Path::new(directory).join(name))
+                .find(|path| path.is_file());
+            if let Some(found) = found {
+                return found;
+            }
+        }
+        PathBuf::from(name)
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::shell::Shell;
+
+    fn script(name: &str, source: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rushell-{}-{}", std::process::id(), name));
+        fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_positional_parameters() {
+        let path = script("positional", "inside=\"$# $*\"\nset -- changed\n");
+        let mut shell = Shell::new();
+        shell.set_positional_parameters(vec!["x".to_string()]);
+        let status = shell.eval(&format!("source {} a 'b c'; after=\"$*\"", path.display()));
+        assert_eq!(status, 0);
+        assert_eq!(shell.variable("inside").as_deref(), Some("2 a b c"));
+        assert_eq!(shell.variable("after").as_deref(), Some("x"));
+
+        // without arguments the file sees and changes those of the caller
+        shell.eval(&format!(". {}; after=\"$*\"", path.display()));
+        assert_eq!(shell.variable("inside").as_deref(), Some("1 x"));
+        assert_eq!(shell.variable("after").as_deref(), Some("changed"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_return() {
+        let path = script(
+            "return",
+            "f() { return 5; }\nf\nfunction_status=$?\nreturn 3\nnot_run=1\n",
+        );
+        let mut shell = Shell::new();
+        let status = shell.eval(&format!(". {}; status=$?", path.display()));
+        assert_eq!(status, 0);
+        assert_eq!(shell.variable("function_status").as_deref(), Some("5"));
+        assert_eq!(shell.variable("status").as_deref(), Some("3"));
+        assert_eq!(shell.variable("not_run"), None);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let mut shell = Shell::new();
+        assert_eq!(shell.eval("source /nonexistent/rushell"), 1);
+        assert_eq!(shell.eval("source"), 2);
+    }
+}
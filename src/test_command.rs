@@ -0,0 +1,286 @@
+use std::ffi::CString;
+use std::fs::{self, Metadata};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+use libc::c_int;
+
+pub struct TestCommand;
+
+impl TestCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-test
+    ///
+    /// Also runs as `[`, which requires a closing `]`.
+    pub fn execute(args: &[String]) -> i32 {
+        let mut operands = &args[1..];
+        if args[0] == "[" {
+            match operands.split_last() {
+                Some((last, rest)) if last == "]" => operands = rest,
+                _ => {
+                    eprintln!("[: missing `]'");
+                    return 2;
+                }
+            }
+        }
+        match evaluate(operands) {
+            Ok(true) => 0,
+            Ok(false) => 1,
+            Err(message) => {
+                eprintln!("{}: {}", args[0], message);
+                2
+            }
+        }
+    }
+}
+
+/// Up to four arguments are evaluated by how many there are, as POSIX
+/// specifies, so that `[ -n ]` or `[ ! = x ]` mean what they look like
+fn evaluate(args: &[String]) -> Result<bool, String> {
+    match args {
+        [] => Ok(false),
+        [arg] => Ok(!arg.is_empty()),
+        [not, arg] if not == "!" => Ok(arg.is_empty()),
+        [operator, operand] if is_unary_operator(operator) => Ok(unary_test(operator, operand)),
+        [arg, _] => Err(format!("{}: unary operator expected", arg)),
+        [left, operator, right] if is_binary_operator(operator) => {
+            binary_test(left, operator, right)
+        }
+        [left, operator, _] if operator != "-a" && operator != "-o" => {
+            if left == "!" {
+                evaluate(&args[1..]).map(|result| !result)
+            } else if left == "(" && args[2] == ")" {
+                Ok(!operator.is_empty())
+            } else {
+                Err(format!("{}: binary operator expected", operator))
+            }
+        }
+        [not, ..] if not == "!" && args.len() == 4 => evaluate(&args[1..]).map(|result| !result),
+        [open, .., close] if open == "(" && close == ")" && args.len() == 4 => {
+            evaluate(&args[1..3])
+        }
+        _ => {
+            let mut expression = Expression { args, current: 0 };
+            let result = expression.or()?;
+            match args.get(expression.current) {
+                Some(_) => Err("too many arguments".to_string()),
+                None => Ok(result),
+            }
+        }
+    }
+}
+
+/// The full grammar of `test`, with `-o` binding looser than `-a`
+struct Expression<'a> {
+    args: &'a [String],
+    current: usize,
+}
+
+impl Expression<'_> {
+    /// or ::= and ( '-o' and )*
+    fn or(&mut self) -> Result<bool, String> {
+        let mut result = self.and()?;
+        while self.match_arg("-o") {
+            let right = self.and()?;
+            result = result || right;
+        }
+        Ok(result)
+    }
+
+    /// and ::= not ( '-a' not )*
+    fn and(&mut self) -> Result<bool, String> {
+        let mut result = self.not()?;
+        while self.match_arg("-a") {
+            let right = self.not()?;
+            result = result && right;
+        }
+        Ok(result)
+    }
+
+    /// not ::= '!' not | primary
+    fn not(&mut self) -> Result<bool, String> {
+        if self.match_arg("!") {
+            return self.not().map(|result| !result);
+        }
+        self.primary()
+    }
+
+    /// primary ::= '(' or ')' | unary_operator arg | arg binary_operator arg | arg
+    fn primary(&mut self) -> Result<bool, String> {
+        let Some(arg) = self.args.get(self.current) else {
+            return Err("argument expected".to_string());
+        };
+        self.current += 1;
+        if arg == "(" {
+            let result = self.or()?;
+            if !self.match_arg(")") {
+                return Err("`)' expected".to_string());
+            }
+            return Ok(result);
+        }
+        if let [operator, right, ..] = &self.args[self.current..] {
+            if is_binary_operator(operator) {
+                self.current += 2;
+                return binary_test(arg, operator, right);
+            }
+        }
+        if is_unary_operator(arg) {
+            let Some(operand) = self.args.get(self.current) else {
+                return Err(format!("{}: unary operator expected", arg));
+            };
+            self.current += 1;
+            return Ok(unary_test(arg, operand));
+        }
+        Ok(!arg.is_empty())
+    }
+
+    fn match_arg(&mut self, arg: &str) -> bool {
+        if self.args.get(self.current).is_some_and(|a| a == arg) {
+            self.current += 1;
+            return true;
+        }
+        false
+    }
+}
+
+/// https://www.gnu.org/software/bash/manual/bash.html#Bash-Conditional-Expressions
+const UNARY_OPERATORS: [&str; 23] = [
+    "-a", "-b", "-c", "-d", "-e", "-f", "-g", "-h", "-k", "-p", "-r", "-s", "-t", "-u", "-w", "-x",
+    "-G", "-L", "-N", "-O", "-S", "-z", "-n",
+];
+
+const BINARY_OPERATORS: [&str; 14] = [
+    "=", "==", "!=", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge", "-nt", "-ot", "-ef",
+];
+
+pub fn is_unary_operator(operator: &str) -> bool {
+    UNARY_OPERATORS.contains(&operator)
+}
+
+pub fn is_binary_operator(operator: &str) -> bool {
+    BINARY_OPERATORS.contains(&operator)
+}
+
+/// File and string tests like `-f file` and `-z string`
+pub fn unary_test(operator: &str, operand: &str) -> bool {
+    let metadata = || fs::metadata(operand).ok();
+    let mode = |bits: u32| metadata().is_some_and(|m| m.mode() & bits != 0);
+    match operator {
+        "-z" => operand.is_empty(),
+        "-n" => !operand.is_empty(),
+        "-a" | "-e" => metadata().is_some(),
+        "-f" => metadata().is_some_and(|m| m.is_file()),
+        "-d" => metadata().is_some_and(|m| m.is_dir()),
+        "-s" => metadata().is_some_and(|m| m.len() > 0),
+        "-b" => metadata().is_some_and(|m| m.file_type().is_block_device()),
+        "-c" => metadata().is_some_and(|m| m.file_type().is_char_device()),
+        "-p" => metadata().is_some_and(|m| m.file_type().is_fifo()),
+        "-S" => metadata().is_some_and(|m| m.file_type().is_socket()),
+        "-h" | "-L" => fs::symlink_metadata(operand).is_ok_and(|m| m.file_type().is_symlink()),
+        "-g" => mode(libc::S_ISGID),
+        "-u" => mode(libc::S_ISUID),
+        "-k" => mode(libc::S_ISVTX),
+        "-r" => access(operand, libc::R_OK),
+        "-w" => access(operand, libc::W_OK),
+        "-x" => access(operand, libc::X_OK),
+        "-O" => metadata().is_some_and(|m| m.uid() == unsafe { libc::geteuid() }),
+        "-G" => metadata().is_some_and(|m| m.gid() == unsafe { libc::getegid() }),
+        "-N" => metadata().is_some_and(|m| m.mtime() > m.atime()),
+        "-t" => operand
+            .parse::<c_int>()
+            .is_ok_and(|fd| unsafe { libc::isatty(fd) } == 1),
+        _ => false,
+    }
+}
+
+/// String, integer and file comparisons like `a = b` and `1 -lt 2`
+pub fn binary_test(left: &str, operator: &str, right: &str) -> Result<bool, String> {
+    let result = match operator {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "-eq" => integer(left)? == integer(right)?,
+        "-ne" => integer(left)? != integer(right)?,
+        "-lt" => integer(left)? < integer(right)?,
+        "-le" => integer(left)? <= integer(right)?,
+        "-gt" => integer(left)? > integer(right)?,
+        "-ge" => integer(left)? >= integer(right)?,
+        "-nt" => match (modified(left), modified(right)) {
+            (Some(left), Some(right)) => left > right,
+            (left, _) => left.is_some(),
+        },
+        "-ot" => match (modified(left), modified(right)) {
+            (Some(left), Some(right)) => left < right,
+            (_, right) => right.is_some(),
+        },
+        "-ef" => match (fs::metadata(left), fs::metadata(right)) {
+            (Ok(left), Ok(right)) => left.dev() == right.dev() && left.ino() == right.ino(),
+            _ => false,
+        },
+        _ => return Err(format!("{}: binary operator expected", operator)),
+    };
+    Ok(result)
+}
+
+fn integer(s: &str) -> Result<i64, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("{}: integer expression expected", s))
+}
+
+fn modified(path: &str) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m: Metadata| m.modified()).ok()
+}
+
+fn access(path: &str, mode: c_int) -> bool {
+    CString::new(path).is_ok_and(|path| unsafe { libc::access(path.as_ptr(), mode) } == 0)
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+
+    fn test(args: &[&str]) -> Result<bool, String> {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        evaluate(&args)
+    }
+
+    #[test]
+    fn test_by_argument_count() {
+        assert_eq!(test(&[]), Ok(false));
+        assert_eq!(test(&["-n"]), Ok(true));
+        assert_eq!(test(&[""]), Ok(false));
+        assert_eq!(test(&["!", ""]), Ok(true));
+        assert_eq!(test(&["-z", ""]), Ok(true));
+        assert_eq!(test(&["=", "=", "="]), Ok(true));
+        assert_eq!(test(&["!", "=", "x"]), Ok(false));
+        assert_eq!(test(&["(", "x", ")"]), Ok(true));
+        assert_eq!(test(&["!", "a", "=", "b"]), Ok(true));
+        assert!(test(&["a", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(test(&["10", "-gt", "9"]), Ok(true));
+        assert_eq!(test(&["a", "<", "b"]), Ok(true));
+        assert_eq!(test(&["a", "!=", "a"]), Ok(false));
+        assert!(test(&["a", "-eq", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_connectives() {
+        assert_eq!(test(&["a", "-a", "", "-o", "b"]), Ok(true));
+        assert_eq!(test(&["a", "-a", "(", "", "-o", "b", ")"]), Ok(true));
+        assert_eq!(test(&["!", "(", "a", "=", "a", ")", "-o", ""]), Ok(false));
+        assert!(test(&["(", "a", "-a", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_file_tests() {
+        assert_eq!(test(&["-d", "/"]), Ok(true));
+        assert_eq!(test(&["-f", "/"]), Ok(false));
+        assert_eq!(test(&["-e", "/nonexistent"]), Ok(false));
+        assert_eq!(test(&["/", "-ef", "/."]), Ok(true));
+    }
+}
@@ -1,17 +1,55 @@
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TokenType {
     Eof,
     String,
+    /// `|`
+    Pipe,
+    /// `||`
+    OrIf,
+    /// `&`
+    Ampersand,
+    /// `&&`
+    AndIf,
+    /// `;`
+    Semicolon,
+    /// `(`
+    LeftParen,
+    /// `)`
+    RightParen,
+    /// `<`, `>`, `>>`, `<<` and the other redirection operators
+    Redirection,
+    /// digits right before a redirection operator, like `2` in `2>file`
+    IoNumber,
+    Newline,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Token {
     pub type_: TokenType,
     pub lexeme: String,
+    /// source text of the token, with quotes and expansions left in place
+    pub raw: String,
+    /// body of a here-document, for `<<` and `<<-` operators
+    pub here_document: Option<String>,
 }
 
 impl Token {
     pub fn new(type_: TokenType, lexeme: String) -> Self {
-        Token { type_, lexeme }
+        let raw = lexeme.clone();
+        Token {
+            type_,
+            lexeme,
+            raw,
+            here_document: None,
+        }
+    }
+
+    pub fn word(lexeme: String, raw: String) -> Self {
+        Token {
+            type_: TokenType::String,
+            lexeme,
+            raw,
+            here_document: None,
+        }
     }
 }
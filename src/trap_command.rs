@@ -0,0 +1,203 @@
+use crate::signals;
+use crate::traps::{Condition, Traps};
+use crate::utils;
+
+pub struct TrapCommand;
+
+impl TrapCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-trap
+    pub fn execute(args: &[String], traps: &mut Traps) -> i32 {
+        let mut args: &[String] = &args[1..];
+        let mut print = false;
+
+        while let Some(arg) = args.first() {
+            match arg.as_str() {
+                "-l" => {
+                    TrapCommand::list_signals();
+                    return 0;
+                }
+                "-p" => print = true,
+                "--" => {
+                    args = &args[1..];
+                    break;
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    eprintln!("trap: {}: invalid option", arg);
+                    eprintln!("trap: usage: trap [-lp] [[action] condition ...]");
+                    return 2;
+                }
+                _ => break,
+            }
+            args = &args[1..];
+        }
+
+        if print || args.is_empty() {
+            return TrapCommand::print(args, traps);
+        }
+
+        // a lone condition, `-` or an unsigned number as the first operand
+        // resets the conditions instead of setting an action
+        let first = &args[0];
+        let (action, specs) = if args.len() == 1 || first.parse::<u32>().is_ok() {
+            (None, args)
+        } else if first == "-" {
+            (None, &args[1..])
+        } else {
+            (Some(first), &args[1..])
+        };
+
+        let mut return_code: i32 = 0;
+        for spec in specs {
+            match Condition::parse(spec) {
+                Some(Condition::Signal(signal))
+                    if signal == libc::SIGKILL || signal == libc::SIGSTOP =>
+                {
+                    eprintln!("trap: {}: cannot be trapped", spec);
+                    return_code = 1;
+                }
+                Some(condition) => match action {
+                    Some(action) => traps.set(condition, action.clone()),
+                    None => traps.reset(condition),
+                },
+                None => {
+                    eprintln!("trap: {}: invalid signal specification", spec);
+                    return_code = 1;
+                }
+            }
+        }
+        return_code
+    }
+
+    /// Print the traps in a form that can be reused as shell input
+    fn print(specs: &[String], traps: &Traps) -> i32 {
+        let mut return_code: i32 = 0;
+        let conditions: Vec<Condition> = if specs.is_empty() {
+            traps.conditions()
+        } else {
+            let mut conditions = Vec::new();
+            for spec in specs {
+                match Condition::parse(spec) {
+                    Some(condition) => conditions.push(condition),
+                    None => {
+                        eprintln!("trap: {}: invalid signal specification", spec);
+                        return_code = 1;
+                    }
+                }
+            }
+            conditions
+        };
+
+        for condition in conditions {
+            if let Some(action) = traps.get(condition) {
+                println!("{}", definition(condition, action));
+            }
+        }
+        return_code
+    }
+
+    fn list_signals() {
+        for (i, (signal, name)) in signals::SIGNALS.iter().enumerate() {
+            let entry = format!("{:>2}) SIG{}", signal, name);
+            if i % 5 == 4 || i == signals::SIGNALS.len() - 1 {
+                println!("{}", entry);
+            } else {
+                print!("{}\t", entry);
+            }
+        }
+    }
+}
+
+/// The `trap` command that sets `action` again
+fn definition(condition: Condition, action: &str) -> String {
+    format!(
+        "trap -- {} {}",
+        utils::single_quote(action),
+        condition.name()
+    )
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{definition, TrapCommand};
+    use crate::traps::{Condition, Traps};
+
+    fn trap(args: &[&str], traps: &mut Traps) -> i32 {
+        let args: Vec<String> = std::iter::once("trap")
+            .chain(args.iter().copied())
+            .map(str::to_string)
+            .collect();
+        TrapCommand::execute(&args, traps)
+    }
+
+    #[test]
+    fn test_pseudo_signals() {
+        let mut traps = Traps::new();
+        assert_eq!(
+            trap(&["echo bye", "EXIT", "err", "DEBUG", "RETURN"], &mut traps),
+            0
+        );
+        assert_eq!(
+            traps.conditions(),
+            [
+                Condition::Exit,
+                Condition::Debug,
+                Condition::Err,
+                Condition::Return
+            ]
+        );
+        assert_eq!(
+            traps.get(Condition::Err).map(String::as_str),
+            Some("echo bye")
+        );
+        assert_eq!(trap(&["echo 0", "0"], &mut traps), 0);
+        assert_eq!(
+            traps.get(Condition::Exit).map(String::as_str),
+            Some("echo 0")
+        );
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut traps = Traps::new();
+        trap(&["echo usr", "USR1", "SIGUSR2", "EXIT", "ERR"], &mut traps);
+        assert_eq!(trap(&["-", "USR1", "EXIT"], &mut traps), 0);
+        assert_eq!(traps.get(Condition::Signal(libc::SIGUSR1)), None);
+        assert_eq!(traps.get(Condition::Exit), None);
+        // a lone condition resets it too
+        assert_eq!(trap(&["ERR"], &mut traps), 0);
+        assert_eq!(traps.get(Condition::Err), None);
+        // and so does an unsigned number as the first operand
+        assert_eq!(trap(&["12", "SIGUSR2"], &mut traps), 0);
+        assert_eq!(traps.conditions(), []);
+    }
+
+    #[test]
+    fn test_invalid_conditions() {
+        let mut traps = Traps::new();
+        assert_eq!(trap(&["echo", "NOPE", "EXIT"], &mut traps), 1);
+        assert_eq!(traps.conditions(), [Condition::Exit]);
+        assert_eq!(trap(&["echo", "KILL"], &mut traps), 1);
+        assert_eq!(trap(&["echo", "SIGSTOP", "9"], &mut traps), 1);
+        assert_eq!(traps.conditions(), [Condition::Exit]);
+        assert_eq!(trap(&["-x"], &mut traps), 2);
+    }
+
+    #[test]
+    fn test_print() {
+        let mut traps = Traps::new();
+        trap(&["echo 'done'", "EXIT"], &mut traps);
+        assert_eq!(trap(&["-p"], &mut traps), 0);
+        assert_eq!(trap(&["-p", "EXIT", "INT"], &mut traps), 0);
+        assert_eq!(trap(&["-p", "NOPE"], &mut traps), 1);
+        assert_eq!(
+            definition(Condition::Exit, "echo 'done'"),
+            "trap -- 'echo '\\''done'\\''' EXIT"
+        );
+        assert_eq!(
+            definition(Condition::Signal(libc::SIGTERM), ""),
+            "trap -- '' SIGTERM"
+        );
+    }
+}
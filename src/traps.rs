@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use libc::c_int;
+
+use crate::signals;
+
+/// https://www.gnu.org/software/bash/manual/bash.html#index-trap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Condition {
+    /// runs when the shell exits
+    Exit,
+    Signal(c_int),
+    /// runs before every simple command
+    Debug,
+    /// runs when a command fails
+    Err,
+    /// runs when a shell function or a sourced script returns
+    Return,
+}
+
+impl Condition {
+    pub fn parse(spec: &str) -> Option<Condition> {
+        match spec.to_ascii_uppercase().as_str() {
+            "EXIT" | "0" => Some(Condition::Exit),
+            "DEBUG" => Some(Condition::Debug),
+            "ERR" => Some(Condition::Err),
+            "RETURN" => Some(Condition::Return),
+            _ => signals::from_name(spec).map(Condition::Signal),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Condition::Exit => "EXIT".to_string(),
+            Condition::Signal(signal) => signals::name(*signal),
+            Condition::Debug => "DEBUG".to_string(),
+            Condition::Err => "ERR".to_string(),
+            Condition::Return => "RETURN".to_string(),
+        }
+    }
+}
+
+pub struct Traps {
+    actions: BTreeMap<Condition, String>,
+}
+
+impl Traps {
+    pub fn new() -> Self {
+        Traps {
+            actions: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, condition: Condition) -> Option<&String> {
+        self.actions.get(&condition)
+    }
+
+    /// An empty `action` ignores the condition
+    pub fn set(&mut self, condition: Condition, action: String) {
+        if let Condition::Signal(signal) = condition {
+            if action.is_empty() {
+                signals::ignore(signal);
+            } else {
+                signals::catch(signal);
+            }
+        }
+        self.actions.insert(condition, action);
+    }
+
+    pub fn reset(&mut self, condition: Condition) {
+        if let Condition::Signal(signal) = condition {
+            signals::restore(signal);
+        }
+        self.actions.remove(&condition);
+    }
+
+    pub fn conditions(&self) -> Vec<Condition> {
+        self.actions.keys().copied().collect()
+    }
+
+    /// Trapped signals are reset in a subshell, but ignored ones stay ignored
+    pub fn reset_for_subshell(&mut self) {
+        self.actions.retain(|condition, action| {
+            if let Condition::Signal(signal) = condition {
+                if action.is_empty() {
+                    return true;
+                }
+                signals::default(*signal);
+            }
+            false
+        });
+        self.ignore_signals();
+    }
+
+    /// Ignore the signals trapped with an empty action again, after their
+    /// dispositions were reset for a child
+    pub fn ignore_signals(&self) {
+        for (condition, action) in &self.actions {
+            if let Condition::Signal(signal) = condition {
+                if action.is_empty() {
+                    signals::ignore(*signal);
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,32 @@
-use std::{collections::HashSet, env, fs};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::{env, fs};
+
+use crate::aliases::Aliases;
+use crate::ast::FunctionDefinition;
+use crate::parser::is_reserved_word;
 
 pub struct TypeCommand;
 
 impl TypeCommand {
-    pub fn execute(args: &[String], built_in_commands: &HashSet<String>) -> i32 {
+    pub fn execute(
+        args: &[String],
+        aliases: &Aliases,
+        built_in_commands: &HashSet<String>,
+        functions: &HashMap<String, Rc<FunctionDefinition>>,
+    ) -> i32 {
         let mut return_code: i32 = 0;
         for arg in args.iter().skip(1) {
-            if built_in_commands.contains(arg) {
+            if let Some(value) = aliases.get(arg) {
+                println!("{} is aliased to `{}'", arg, value);
+            } else if is_reserved_word(arg) {
+                println!("{} is a shell keyword", arg);
+            } else if let Some(function) = functions.get(arg) {
+                println!("{} is a function", arg);
+                println!("{}", function.text);
+            } else if built_in_commands.contains(arg) {
                 println!("{} is a shell builtin", arg);
-            } else if let Some(file_path) = TypeCommand::check_in_path(arg) {
+            } else if let Some(file_path) = TypeCommand::check_in_path(arg, None) {
                 println!("{} is {}", arg, file_path);
             } else {
                 eprintln!("{}: not found", arg);
@@ -18,18 +36,23 @@ impl TypeCommand {
         return_code
     }
 
-    fn check_in_path(command: &String) -> Option<String> {
+    /// Searches `path`, or `PATH` if it is `None`
+    pub fn check_in_path(command: &str, path: Option<&str>) -> Option<String> {
         let split_by = match env::consts::FAMILY {
             "windows" => ";",
             "unix" => ":",
             _ => unimplemented!(),
         };
-        let env_value = match env::var("PATH") {
-            Ok(path) => path,
-            Err(_) => return None,
+        let env_value = match path {
+            Some(path) => path.to_string(),
+            None => env::var("PATH").ok()?,
         };
         for path in env_value.split(split_by) {
-            for item in fs::read_dir(path).unwrap().flatten() {
+            // directories in PATH that do not exist are skipped
+            let Ok(items) = fs::read_dir(path) else {
+                continue;
+            };
+            for item in items.flatten() {
                 let item_path = item.path();
                 let file_name = item_path.file_stem().unwrap().to_str().unwrap();
                 if file_name == command {
@@ -0,0 +1,39 @@
+use crate::aliases::Aliases;
+
+pub struct UnaliasCommand;
+
+impl UnaliasCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-unalias
+    pub fn execute(args: &[String], aliases: &mut Aliases) -> i32 {
+        match args.get(1).map(String::as_str) {
+            Some("-a") => {
+                aliases.clear();
+                return 0;
+            }
+            Some(arg) if arg.starts_with('-') && arg != "-" && arg != "--" => {
+                eprintln!("unalias: {}: invalid option", arg);
+                return UnaliasCommand::usage();
+            }
+            None => return UnaliasCommand::usage(),
+            _ => {}
+        }
+
+        let names = match args[1] == "--" {
+            true => &args[2..],
+            false => &args[1..],
+        };
+        let mut status = 0;
+        for name in names {
+            if !aliases.remove(name) {
+                eprintln!("unalias: {}: not found", name);
+                status = 1;
+            }
+        }
+        status
+    }
+
+    fn usage() -> i32 {
+        eprintln!("unalias: usage: unalias [-a] name [name ...]");
+        2
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::FunctionDefinition;
+use crate::parser::is_name;
+use crate::variables::Variables;
+
+pub struct UnsetCommand;
+
+impl UnsetCommand {
+    /// https://www.gnu.org/software/bash/manual/bash.html#index-unset
+    pub fn execute(
+        args: &[String],
+        variables: &mut Variables,
+        functions: &mut HashMap<String, Rc<FunctionDefinition>>,
+    ) -> i32 {
+        let mut unset_functions = false;
+        let mut unset_variables = false;
+        let mut names = &args[1..];
+        while let Some(arg) = names.first() {
+            match arg.as_str() {
+                "-f" => unset_functions = true,
+                "-v" => unset_variables = true,
+                "--" => {
+                    names = &names[1..];
+                    break;
+                }
+                arg if arg.starts_with('-') && arg.len() > 1 => {
+                    eprintln!("unset: {}: invalid option", arg);
+                    eprintln!("unset: usage: unset [-f] [-v] [name ...]");
+                    return 2;
+                }
+                _ => break,
+            }
+            names = &names[1..];
+        }
+
+        let mut status = 0;
+        for name in names {
+            if unset_functions {
+                functions.remove(name);
+                continue;
+            }
+            // `unset 'name[subscript]'` unsets a single element
+            let element = name
+                .strip_suffix(']')
+                .and_then(|name| name.split_once('['))
+                .filter(|(name, _)| is_name(name));
+            let result = match element {
+                Some((name, subscript)) => variables.unset_element(name, subscript),
+                None if is_name(name) && variables.contains(name) => variables.unset(name),
+                // without options, a name that is not a variable may be a
+                // function
+                None if !unset_variables => {
+                    functions.remove(name);
+                    Ok(())
+                }
+                None if is_name(name) => Ok(()),
+                None => Err(format!("`{}': not a valid identifier", name)),
+            };
+            if let Err(message) = result {
+                eprintln!("unset: {}", message);
+                status = 1;
+            }
+        }
+        status
+    }
+}
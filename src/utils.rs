@@ -7,3 +7,45 @@ pub fn home_dir() -> Option<PathBuf> {
         _ => None,
     }
 }
+
+/// Error message without the " (os error N)" suffix added by `std::io::Error`
+pub fn error_message(error: &std::io::Error) -> String {
+    let message = error.to_string();
+    match message.find(" (os error") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+pub fn last_os_error() -> String {
+    error_message(&std::io::Error::last_os_error())
+}
+
+/// Quote `value` with single quotes so that the shell reads it back unchanged
+pub fn single_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Quote `value` only if the shell would read it differently unquoted, like
+/// `set -x` does
+pub fn quote(value: &str) -> String {
+    let plain = |c: char| c.is_alphanumeric() || "%+,-./:=@_^".contains(c);
+    match !value.is_empty() && value.chars().all(plain) {
+        true => value.to_string(),
+        false => single_quote(value),
+    }
+}
+
+/// Quote `value` with double quotes, escaping the characters still special
+/// inside them
+pub fn double_quote(value: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '$' | '\\' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,711 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+
+use crate::arithmetic;
+use crate::parser::is_name;
+
+/// The value of a shell variable
+#[derive(Debug, Clone)]
+pub enum Value {
+    Scalar(String),
+    /// https://www.gnu.org/software/bash/manual/bash.html#Arrays
+    Indexed(BTreeMap<usize, String>),
+    Associative(BTreeMap<String, String>),
+}
+
+/// Attributes given with `declare`, apart from the type of arrays which is
+/// part of the value
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Attributes {
+    /// assigned values are evaluated as integers
+    pub integer: bool,
+    pub readonly: bool,
+    pub exported: bool,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    /// the value is the name of the variable used in place of this one
+    pub nameref: bool,
+}
+
+impl Attributes {
+    /// Whether every attribute set in `other` is set here too
+    pub fn contains(&self, other: Attributes) -> bool {
+        (self.integer || !other.integer)
+            && (self.readonly || !other.readonly)
+            && (self.exported || !other.exported)
+            && (self.lowercase || !other.lowercase)
+            && (self.uppercase || !other.uppercase)
+            && (self.nameref || !other.nameref)
+    }
+}
+
+/// A shell variable, which has no value after `declare -i name` or `local name`
+#[derive(Debug, Clone, Default)]
+pub struct Variable {
+    pub value: Option<Value>,
+    pub attributes: Attributes,
+}
+
+/// Shell variables. Exported variables are also set in the environment of the
+/// shell process, so that they are passed on to commands.
+pub struct Variables {
+    values: HashMap<String, Variable>,
+    /// The variables shadowed by `local` in each function being run, to put
+    /// back when it returns
+    scopes: Vec<HashMap<String, Option<Variable>>>,
+}
+
+/// How many name references are followed before giving up on a loop
+const MAX_REFERENCES: usize = 8;
+
+impl Variables {
+    /// Imports the environment as exported variables
+    pub fn new() -> Self {
+        let exported = Attributes {
+            exported: true,
+            ..Attributes::default()
+        };
+        let values = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(name, _)| is_name(name))
+            .map(|(name, value)| {
+                let variable = Variable {
+                    value: Some(Value::Scalar(value)),
+                    attributes: exported,
+                };
+                (name, variable)
+            })
+            .collect();
+        Variables {
+            values,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// The value of a scalar, or the element 0 of an array
+    pub fn get(&self, name: &str) -> Option<String> {
+        match self.values.get(&self.resolve(name))?.value.as_ref()? {
+            Value::Scalar(value) => Some(value.clone()),
+            Value::Indexed(elements) => elements.get(&0).cloned(),
+            Value::Associative(elements) => elements.get("0").cloned(),
+        }
+    }
+
+    /// The variable itself, without following a name reference
+    pub fn variable(&self, name: &str) -> Option<&Variable> {
+        self.values.get(name)
+    }
+
+    /// All variables, sorted by name
+    pub fn all(&self) -> Vec<(&String, &Variable)> {
+        let mut variables: Vec<(&String, &Variable)> = self.values.iter().collect();
+        variables.sort_by_key(|(name, _)| *name);
+        variables
+    }
+
+    /// Whether the variable exists, even without a value
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(&self.resolve(name))
+    }
+
+    /// Assigning to an array without a subscript sets its element 0
+    pub fn set(&mut self, name: &str, value: String) -> Result<(), String> {
+        self.assign_scalar(name, value, false)
+    }
+
+    /// `name+=value`
+    pub fn append(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.assign_scalar(name, value.to_string(), true)
+    }
+
+    fn assign_scalar(&mut self, name: &str, value: String, append: bool) -> Result<(), String> {
+        let name = self.resolve(name);
+        match self.values.get(&name).and_then(|v| v.value.as_ref()) {
+            Some(Value::Indexed(_) | Value::Associative(_)) => {
+                self.set_element(&name, "0", value, append)
+            }
+            _ => {
+                self.writable(&name)?;
+                let current = self.get(&name);
+                let value = self.convert(&name, current.as_deref(), &value, append)?;
+                self.store(&name, Value::Scalar(value));
+                Ok(())
+            }
+        }
+    }
+
+    /// `declare -n name=target`, which sets the name reference itself
+    pub fn set_reference(&mut self, name: &str, target: String) -> Result<(), String> {
+        if !is_name(&target) {
+            return Err(format!(
+                "`{}': invalid variable name for name reference",
+                target
+            ));
+        }
+        self.writable(name)?;
+        self.store(name, Value::Scalar(target));
+        Ok(())
+    }
+
+    /// Replaces the variable with an indexed array of `values`
+    pub fn set_array(&mut self, name: &str, values: Vec<String>) -> Result<(), String> {
+        let name = self.resolve(name);
+        self.writable(&name)?;
+        let mut elements = BTreeMap::new();
+        for (index, value) in values.into_iter().enumerate() {
+            elements.insert(index, self.convert(&name, None, &value, false)?);
+        }
+        self.store(&name, Value::Indexed(elements));
+        Ok(())
+    }
+
+    /// `declare -a name`, which keeps the value of a scalar as the element 0
+    pub fn make_indexed(&mut self, name: &str) -> Result<(), String> {
+        let name = self.resolve(name);
+        let elements = match self.values.get(&name).and_then(|v| v.value.as_ref()) {
+            Some(Value::Indexed(_)) => return Ok(()),
+            Some(Value::Associative(_)) => {
+                return Err(format!(
+                    "{}: cannot convert associative to indexed array",
+                    name
+                ))
+            }
+            _ => self
+                .get(&name)
+                .map(|value| (0, value))
+                .into_iter()
+                .collect(),
+        };
+        self.writable(&name)?;
+        self.store(&name, Value::Indexed(elements));
+        Ok(())
+    }
+
+    /// `declare -A name`
+    pub fn make_associative(&mut self, name: &str) -> Result<(), String> {
+        let name = self.resolve(name);
+        let elements = match self.values.get(&name).and_then(|v| v.value.as_ref()) {
+            Some(Value::Associative(_)) => return Ok(()),
+            Some(Value::Indexed(_)) => {
+                return Err(format!(
+                    "{}: cannot convert indexed to associative array",
+                    name
+                ))
+            }
+            _ => self
+                .get(&name)
+                .map(|value| ("0".to_string(), value))
+                .into_iter()
+                .collect(),
+        };
+        self.writable(&name)?;
+        self.store(&name, Value::Associative(elements));
+        Ok(())
+    }
+
+    /// `name=(...)` and `name+=(...)`, where elements with a subscript come
+    /// from `[subscript]=value`. Elements of an indexed array without one
+    /// follow the previous element.
+    pub fn assign_array(
+        &mut self,
+        name: &str,
+        elements: Vec<(Option<String>, String)>,
+        append: bool,
+    ) -> Result<(), String> {
+        let name = self.resolve(name);
+        self.writable(&name)?;
+        if let Some(Value::Associative(current)) =
+            self.values.get(&name).and_then(|v| v.value.as_ref())
+        {
+            let mut map = if append {
+                current.clone()
+            } else {
+                BTreeMap::new()
+            };
+            for (key, value) in elements {
+                let Some(key) = key else {
+                    return Err(format!(
+                        "{}: {}: must use subscript when assigning associative array",
+                        name, value
+                    ));
+                };
+                map.insert(key, self.convert(&name, None, &value, false)?);
+            }
+            self.store(&name, Value::Associative(map));
+            return Ok(());
+        }
+
+        let mut map: BTreeMap<usize, String> = match append {
+            true => self
+                .elements(&name)
+                .into_iter()
+                .filter_map(|(key, value)| Some((key.parse().ok()?, value)))
+                .collect(),
+            false => BTreeMap::new(),
+        };
+        let mut next = map.keys().next_back().map_or(0, |last| last + 1);
+        for (subscript, value) in elements {
+            let index = match subscript {
+                Some(subscript) => self.index(&name, &subscript, &map)?,
+                None => next,
+            };
+            map.insert(index, self.convert(&name, None, &value, false)?);
+            next = index + 1;
+        }
+        self.store(&name, Value::Indexed(map));
+        Ok(())
+    }
+
+    /// `name[subscript]=value` and `name[subscript]+=value`. A scalar becomes an
+    /// indexed array.
+    pub fn set_element(
+        &mut self,
+        name: &str,
+        subscript: &str,
+        value: String,
+        append: bool,
+    ) -> Result<(), String> {
+        let name = self.resolve(name);
+        self.writable(&name)?;
+        if let Some(Value::Associative(map)) = self.values.get(&name).and_then(|v| v.value.as_ref())
+        {
+            let current = map.get(subscript).map(String::as_str);
+            let value = self.convert(&name, current, &value, append)?;
+            if let Some(Value::Associative(map)) = self.value_mut(&name) {
+                map.insert(subscript.to_string(), value);
+            }
+            return Ok(());
+        }
+        self.make_indexed(&name)?;
+        let Some(Value::Indexed(map)) = self.values.get(&name).and_then(|v| v.value.as_ref())
+        else {
+            unreachable!();
+        };
+        let index = self.index(&name, subscript, map)?;
+        let current = map.get(&index).map(String::as_str);
+        let value = self.convert(&name, current, &value, append)?;
+        if let Some(Value::Indexed(map)) = self.value_mut(&name) {
+            map.insert(index, value);
+        }
+        Ok(())
+    }
+
+    /// `${name[subscript]}`
+    pub fn element(&self, name: &str, subscript: &str) -> Result<Option<String>, String> {
+        let name = self.resolve(name);
+        match self.values.get(&name).and_then(|v| v.value.as_ref()) {
+            Some(Value::Associative(map)) => Ok(map.get(subscript).cloned()),
+            Some(Value::Indexed(map)) => {
+                let index = self.index(&name, subscript, map)?;
+                Ok(map.get(&index).cloned())
+            }
+            _ => {
+                let value: BTreeMap<usize, String> = self
+                    .get(&name)
+                    .map(|value| (0, value))
+                    .into_iter()
+                    .collect();
+                let index = self.index(&name, subscript, &value)?;
+                Ok(value.get(&index).cloned())
+            }
+        }
+    }
+
+    /// The keys and values of an array, or the value of a scalar as the
+    /// element 0
+    pub fn elements(&self, name: &str) -> Vec<(String, String)> {
+        let name = self.resolve(name);
+        match self.values.get(&name).and_then(|v| v.value.as_ref()) {
+            Some(Value::Indexed(map)) => map
+                .iter()
+                .map(|(index, value)| (index.to_string(), value.clone()))
+                .collect(),
+            Some(Value::Associative(map)) => map
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            _ => self
+                .get(&name)
+                .map(|value| ("0".to_string(), value))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    pub fn unset(&mut self, name: &str) -> Result<(), String> {
+        let name = self.resolve(name);
+        if self.attributes(&name).readonly {
+            return Err(format!("{}: cannot unset: readonly variable", name));
+        }
+        self.values.remove(&name);
+        env::remove_var(&name);
+        Ok(())
+    }
+
+    /// `unset 'name[subscript]'`
+    pub fn unset_element(&mut self, name: &str, subscript: &str) -> Result<(), String> {
+        let name = self.resolve(name);
+        if self.attributes(&name).readonly {
+            return Err(format!("{}: cannot unset: readonly variable", name));
+        }
+        match self.values.get(&name).and_then(|v| v.value.as_ref()) {
+            Some(Value::Associative(_)) => {
+                if let Some(Value::Associative(map)) = self.value_mut(&name) {
+                    map.remove(subscript);
+                }
+            }
+            Some(Value::Indexed(map)) => {
+                let index = self.index(&name, subscript, map)?;
+                if let Some(Value::Indexed(map)) = self.value_mut(&name) {
+                    map.remove(&index);
+                }
+            }
+            _ => {
+                if self.index(&name, subscript, &BTreeMap::new())? == 0 {
+                    self.unset(&name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn attributes(&self, name: &str) -> Attributes {
+        self.values
+            .get(name)
+            .map_or(Attributes::default(), |variable| variable.attributes)
+    }
+
+    /// `declare -x name` and `declare +x name`. Attributes are only changed,
+    /// values are converted by the next assignment. `-n` and `+n` apply to the
+    /// name reference itself rather than to the variable it refers to.
+    pub fn set_attributes(
+        &mut self,
+        name: &str,
+        set: Attributes,
+        clear: Attributes,
+    ) -> Result<(), String> {
+        let name = match set.nameref || clear.nameref {
+            true => name.to_string(),
+            false => self.resolve(name),
+        };
+        // a readonly variable may only be exported
+        let only_added = Attributes {
+            readonly: set.readonly,
+            exported: set.exported,
+            ..Attributes::default()
+        };
+        if self.attributes(&name).readonly && (set != only_added || clear != Attributes::default())
+        {
+            return Err(format!("{}: readonly variable", name));
+        }
+        let attributes = &mut self.values.entry(name.clone()).or_default().attributes;
+        attributes.integer = (attributes.integer || set.integer) && !clear.integer;
+        attributes.readonly = attributes.readonly || set.readonly;
+        attributes.exported = (attributes.exported || set.exported) && !clear.exported;
+        attributes.nameref = (attributes.nameref || set.nameref) && !clear.nameref;
+        // the last of `-l` and `-u` wins, both together cancel out
+        if set.lowercase || set.uppercase {
+            attributes.lowercase = set.lowercase && !set.uppercase;
+            attributes.uppercase = set.uppercase && !set.lowercase;
+        }
+        attributes.lowercase &= !clear.lowercase;
+        attributes.uppercase &= !clear.uppercase;
+        if set.exported || clear.exported {
+            self.export(&name);
+        }
+        Ok(())
+    }
+
+    /// Starts the scope of a function call, for `local`
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Puts back the variables shadowed in the scope of the returning function
+    pub fn pop_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for (name, variable) in scope {
+            match variable {
+                Some(variable) => self.values.insert(name.clone(), variable),
+                None => self.values.remove(&name),
+            };
+            self.export(&name);
+        }
+    }
+
+    /// `local name`, which shadows the variable until the function returns.
+    /// The local variable starts without a value, and is exported if the
+    /// shadowed one was.
+    pub fn declare_local(&mut self, name: &str) -> Result<(), String> {
+        if self.scopes.is_empty() {
+            return Err("can only be used in a function".to_string());
+        }
+        if self
+            .scopes
+            .last()
+            .is_some_and(|scope| scope.contains_key(name))
+        {
+            return Ok(());
+        }
+        self.writable(name)?;
+        let local = Variable {
+            value: None,
+            attributes: Attributes {
+                exported: self.attributes(name).exported,
+                ..Attributes::default()
+            },
+        };
+        let shadowed = self.values.insert(name.to_string(), local);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), shadowed);
+        }
+        Ok(())
+    }
+
+    /// The name of the variable that `name` refers to, which is `name` itself
+    /// unless it is a name reference
+    fn resolve(&self, name: &str) -> String {
+        let mut name = name.to_string();
+        for _ in 0..MAX_REFERENCES {
+            match self.values.get(&name) {
+                Some(Variable {
+                    value: Some(Value::Scalar(target)),
+                    attributes,
+                }) if attributes.nameref && !target.is_empty() => name = target.clone(),
+                _ => break,
+            }
+        }
+        name
+    }
+
+    fn writable(&self, name: &str) -> Result<(), String> {
+        match self.attributes(name).readonly {
+            true => Err(format!("{}: readonly variable", name)),
+            false => Ok(()),
+        }
+    }
+
+    /// The value to store for an assignment to a variable with attributes.
+    /// Appending to an integer adds to it.
+    fn convert(
+        &self,
+        name: &str,
+        current: Option<&str>,
+        value: &str,
+        append: bool,
+    ) -> Result<String, String> {
+        let attributes = self.attributes(name);
+        if attributes.integer {
+            let mut number = self.integer(value)?;
+            if append {
+                number = self
+                    .integer(current.unwrap_or_default())?
+                    .checked_add(number)
+                    .ok_or_else(|| format!("{}: integer overflow", name))?;
+            }
+            return Ok(number.to_string());
+        }
+        let value = match append {
+            true => format!("{}{}", current.unwrap_or_default(), value),
+            false => value.to_string(),
+        };
+        Ok(match attributes {
+            Attributes {
+                lowercase: true, ..
+            } => value.to_lowercase(),
+            Attributes {
+                uppercase: true, ..
+            } => value.to_uppercase(),
+            _ => value,
+        })
+    }
+
+    /// The value of an arithmetic expression, for integer variables and the
+    /// subscripts of indexed arrays
+    fn integer(&self, text: &str) -> Result<i64, String> {
+        arithmetic::evaluate(text.trim(), &|name| self.get(name))
+    }
+
+    /// Sets the value, keeping the attributes
+    fn store(&mut self, name: &str, value: Value) {
+        self.values.entry(name.to_string()).or_default().value = Some(value);
+        self.export(name);
+    }
+
+    fn value_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.values.get_mut(name)?.value.as_mut()
+    }
+
+    /// Copies an exported scalar to the environment. Arrays cannot be
+    /// exported.
+    fn export(&self, name: &str) {
+        match self.values.get(name) {
+            Some(Variable {
+                value: Some(Value::Scalar(value)),
+                attributes,
+            }) if attributes.exported => env::set_var(name, value),
+            _ => env::remove_var(name),
+        }
+    }
+
+    /// The index of an element of an indexed array. A negative subscript counts
+    /// back from the end.
+    fn index(
+        &self,
+        name: &str,
+        subscript: &str,
+        map: &BTreeMap<usize, String>,
+    ) -> Result<usize, String> {
+        let index = self
+            .integer(subscript)
+            .map_err(|_| format!("{}: bad array subscript", subscript.trim()))?;
+        if index >= 0 {
+            return Ok(index as usize);
+        }
+        let end = map.keys().next_back().map_or(0, |last| *last as i64 + 1);
+        usize::try_from(end.saturating_add(index))
+            .map_err(|_| format!("{}[{}]: bad array subscript", name, subscript.trim()))
+    }
+}
+
+// MARK: Tests
+
+#[cfg(test)]
+mod tests {
+    use super::{Attributes, Variables};
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_indexed_arrays() {
+        let mut variables = Variables::new();
+        variables
+            .set_array("rushell_a", strings(&["a", "b", "c"]))
+            .unwrap();
+        variables
+            .set_element("rushell_a", "5", "f".to_string(), false)
+            .unwrap();
+        variables
+            .assign_array("rushell_a", vec![(None, "g".to_string())], true)
+            .unwrap();
+        variables.unset_element("rushell_a", "-2").unwrap();
+        let keys: Vec<String> = variables
+            .elements("rushell_a")
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, strings(&["0", "1", "2", "6"]));
+        assert_eq!(
+            variables.element("rushell_a", "-1"),
+            Ok(Some("g".to_string()))
+        );
+        assert_eq!(variables.get("rushell_a"), Some("a".to_string()));
+        assert!(variables.element("rushell_a", "-8").is_err());
+        assert!(variables.element("rushell_a", "1+").is_err());
+    }
+
+    #[test]
+    fn test_associative_arrays() {
+        let mut variables = Variables::new();
+        variables.make_associative("rushell_m").unwrap();
+        variables
+            .set_element("rushell_m", "k", "v".to_string(), false)
+            .unwrap();
+        variables
+            .set_element("rushell_m", "k", "w".to_string(), true)
+            .unwrap();
+        assert_eq!(
+            variables.element("rushell_m", "k"),
+            Ok(Some("vw".to_string()))
+        );
+        assert!(variables.make_indexed("rushell_m").is_err());
+        assert!(variables
+            .assign_array("rushell_m", vec![(None, "x".to_string())], false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_attributes() {
+        let mut variables = Variables::new();
+        let integer = Attributes {
+            integer: true,
+            ..Attributes::default()
+        };
+        variables
+            .set_attributes("rushell_i", integer, Attributes::default())
+            .unwrap();
+        variables.set("rushell_i", " 4 ".to_string()).unwrap();
+        variables.append("rushell_i", "rushell_i").unwrap();
+        assert_eq!(variables.get("rushell_i"), Some("8".to_string()));
+        assert!(variables.set("rushell_i", "1+".to_string()).is_err());
+        variables
+            .set("rushell_i", "3+4*rushell_i".to_string())
+            .unwrap();
+        assert_eq!(variables.get("rushell_i"), Some("35".to_string()));
+        variables.set("rushell_i", i64::MAX.to_string()).unwrap();
+        assert!(variables.append("rushell_i", "1").is_err());
+        assert_eq!(variables.get("rushell_i"), Some(i64::MAX.to_string()));
+
+        let uppercase = Attributes {
+            uppercase: true,
+            ..Attributes::default()
+        };
+        variables
+            .set_attributes("rushell_u", uppercase, Attributes::default())
+            .unwrap();
+        variables.set("rushell_u", "ab".to_string()).unwrap();
+        assert_eq!(variables.get("rushell_u"), Some("AB".to_string()));
+
+        let readonly = Attributes {
+            readonly: true,
+            ..Attributes::default()
+        };
+        variables
+            .set_attributes("rushell_u", readonly, Attributes::default())
+            .unwrap();
+        assert!(variables.set("rushell_u", "x".to_string()).is_err());
+        assert!(variables.unset("rushell_u").is_err());
+        assert!(variables
+            .set_attributes("rushell_u", Attributes::default(), uppercase)
+            .is_err());
+    }
+
+    #[test]
+    fn test_name_references() {
+        let mut variables = Variables::new();
+        let nameref = Attributes {
+            nameref: true,
+            ..Attributes::default()
+        };
+        variables
+            .set_attributes("rushell_ref", nameref, Attributes::default())
+            .unwrap();
+        variables
+            .set_reference("rushell_ref", "rushell_target".to_string())
+            .unwrap();
+        variables.set("rushell_ref", "v".to_string()).unwrap();
+        assert_eq!(variables.get("rushell_target"), Some("v".to_string()));
+        assert!(variables
+            .set_reference("rushell_ref", "1x".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn test_local_variables() {
+        let mut variables = Variables::new();
+        assert!(variables.declare_local("rushell_l").is_err());
+        variables.set("rushell_l", "global".to_string()).unwrap();
+        variables.push_scope();
+        variables.declare_local("rushell_l").unwrap();
+        assert_eq!(variables.get("rushell_l"), None);
+        variables.set("rushell_l", "local".to_string()).unwrap();
+        variables.declare_local("rushell_l").unwrap();
+        assert_eq!(variables.get("rushell_l"), Some("local".to_string()));
+        variables.pop_scope();
+        assert_eq!(variables.get("rushell_l"), Some("global".to_string()));
+    }
+}